@@ -0,0 +1,99 @@
+use crate::compiler::lexer::Span;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Program {
+    pub statements: Vec<Stmt>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Stmt {
+    // qubit q0, q1, r[4];
+    QubitDecl(Vec<QubitDecl>),
+    // h q0;  rz(0.25) q1;  cnot q0, q1;
+    Gate(GateCall),
+    // bit c[2], flag;  (same shape as a qubit declaration)
+    BitDecl(Vec<QubitDecl>),
+    // measure q0, q1;  results go to fresh anonymous bits
+    Measure(Vec<QubitRef>, Span),
+    // measure q -> c;  measure a, b -> c[1], flag;
+    MeasureInto(Vec<QubitRef>, Vec<QubitRef>, Span),
+    // reset q0, r;
+    Reset(Vec<QubitRef>, Span),
+    // if (c[0]) x q;  if (c == 2) { ... } else { ... }
+    If(Condition, Vec<Stmt>, Vec<Stmt>, Span),
+    // while (!flag) { ... }
+    While(Condition, Vec<Stmt>, Span),
+    // for i in 0..n { ... }  unrolled at compile time, `n` excluded
+    For(String, Expr, Expr, Vec<Stmt>, Span),
+    // gate name(theta) a, b { ... }
+    GateDef(GateDef),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct QubitDecl {
+    pub name: String,
+    pub size: Option<usize>, // Some(n) for a register `q[n]`
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct GateCall {
+    pub name: String,
+    pub params: Vec<Expr>,
+    pub args: Vec<QubitRef>,
+    pub span: Span,
+}
+
+// `c[0]`, `!c[0]`, `c == 2`, `c[1] != 0`. Holds when the bits read as
+// `value` (1 when omitted), flipped by `negated`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Condition {
+    pub bits: QubitRef,
+    pub value: Option<u64>,
+    pub negated: bool,
+    pub span: Span,
+}
+
+// A user-defined gate. The body may only call gates on the formal qubit
+// arguments; calls are inlined during codegen.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GateDef {
+    pub name: String,
+    pub params: Vec<(String, Span)>,
+    pub qubits: Vec<(String, Span)>,
+    pub body: Vec<GateCall>,
+    pub span: Span, // `gate name(...) a, b`
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct QubitRef {
+    pub name: String,
+    pub index: Option<Expr>, // a constant expression, e.g. `q[i+1]`
+    pub span: Span,
+}
+
+// Constant expressions, used for gate parameters and indices
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expr {
+    Number(f64, Span),
+    Ident(String, Span),
+    Neg(Box<Expr>, Span),
+    Binary(BinOp, Box<Expr>, Box<Expr>, Span),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+impl Expr {
+    pub fn span(&self) -> Span {
+        match self {
+            Expr::Number(_, span) | Expr::Ident(_, span) => *span,
+            Expr::Neg(_, span) | Expr::Binary(_, _, _, span) => *span,
+        }
+    }
+}
@@ -0,0 +1,178 @@
+// Lowering from the checked AST to the flat IR
+use crate::compiler::ast::{GateCall, Program, Stmt};
+use crate::compiler::diagnostics::{Diagnostic, DiagnosticKind};
+use crate::compiler::ir::{conditional_block, loop_can_exit, IROp, IRProgram, Register};
+use crate::compiler::lexer::Span;
+use crate::compiler::parser::parse;
+use crate::compiler::sema::{self, eval_expr, SymbolTable};
+use std::collections::HashMap;
+
+pub fn compile(src: &str) -> Result<IRProgram, Vec<Diagnostic>> {
+    let ast = parse(src).map_err(|e| vec![e])?;
+    lower(&ast)
+}
+
+pub(crate) fn endless_loop(span: Span) -> Diagnostic {
+    Diagnostic::new(
+        DiagnosticKind::Unsupported,
+        "`while` body never writes its condition bits, so the loop cannot end once entered",
+        span,
+    )
+}
+
+pub fn lower(prog: &Program) -> Result<IRProgram, Vec<Diagnostic>> {
+    let symbols = sema::check(prog)?;
+    // Anonymous `measure q;` results go after the declared bits
+    let declared_bits = symbols.num_clbits;
+    let mut codegen = Codegen { symbols, ir: IRProgram::default() };
+    codegen.ir.num_clbits = declared_bits;
+    for stmt in &prog.statements {
+        codegen.stmt(stmt).map_err(|e| vec![e])?;
+    }
+    codegen.ir.num_qubits = codegen.symbols.num_qubits;
+    let mut qregs: Vec<Register> = codegen.symbols.qubits.iter()
+        .map(|(name, sym)| Register { name: name.clone(), offset: sym.offset, size: sym.size.unwrap_or(1) })
+        .collect();
+    qregs.sort_by_key(|r| r.offset);
+    codegen.ir.qregs = qregs;
+    let mut cregs: Vec<Register> = codegen.symbols.bits.iter()
+        .map(|(name, sym)| Register { name: name.clone(), offset: sym.offset, size: sym.size.unwrap_or(1) })
+        .collect();
+    cregs.sort_by_key(|r| r.offset);
+    if codegen.ir.num_clbits > declared_bits {
+        let name = if codegen.symbols.bits.contains_key("c") { "meas" } else { "c" };
+        cregs.push(Register { name: name.to_string(), offset: declared_bits, size: codegen.ir.num_clbits - declared_bits });
+    }
+    codegen.ir.cregs = cregs;
+    Ok(codegen.ir)
+}
+
+// Clifford+T network for CCX (Nielsen & Chuang fig. 4.9)
+pub fn toffoli_ops(a: usize, b: usize, t: usize) -> Vec<IROp> {
+    vec![
+        IROp::H(t),
+        IROp::CNOT(b, t), IROp::Tdg(t),
+        IROp::CNOT(a, t), IROp::T(t),
+        IROp::CNOT(b, t), IROp::Tdg(t),
+        IROp::CNOT(a, t), IROp::T(b), IROp::T(t),
+        IROp::H(t),
+        IROp::CNOT(a, b), IROp::T(a), IROp::Tdg(b),
+        IROp::CNOT(a, b),
+    ]
+}
+
+struct Codegen {
+    symbols: SymbolTable,
+    ir: IRProgram,
+}
+
+impl Codegen {
+    fn stmt(&mut self, stmt: &Stmt) -> Result<(), Diagnostic> {
+        match stmt {
+            Stmt::QubitDecl(_) | Stmt::BitDecl(_) | Stmt::GateDef(_) => {}
+            Stmt::Gate(call) => self.gate(call)?,
+            Stmt::Measure(args, _) => {
+                for arg in args {
+                    for q in self.symbols.resolve(arg, true)? {
+                        let clbit = self.ir.num_clbits;
+                        self.ir.num_clbits += 1;
+                        self.ir.push(IROp::Measure(q, clbit));
+                    }
+                }
+            }
+            Stmt::MeasureInto(qubits, bits, span) => {
+                for (q, c) in sema::check_measure_into(qubits, bits, *span, &self.symbols)? {
+                    self.ir.push(IROp::Measure(q, c));
+                }
+            }
+            Stmt::Reset(args, _) => {
+                for arg in args {
+                    for q in self.symbols.resolve(arg, true)? {
+                        self.ir.push(IROp::Reset(q));
+                    }
+                }
+            }
+            Stmt::If(cond, then_body, else_body, _) => {
+                let (clbits, value) = sema::check_condition(cond, &self.symbols)?;
+                let then_ops = self.block(then_body)?;
+                let else_ops = self.block(else_body)?;
+                self.ir.ops.extend(conditional_block(clbits, value, then_ops, else_ops));
+            }
+            Stmt::While(cond, body, span) => {
+                let (clbits, value) = sema::check_condition(cond, &self.symbols)?;
+                let body = self.block(body)?;
+                if !loop_can_exit(&clbits, &body) {
+                    return Err(endless_loop(*span));
+                }
+                self.ir.push(IROp::While { clbits, value, body });
+            }
+            Stmt::For(var, first, last, body, _) => {
+                let shadowed = self.symbols.consts.get(var).copied();
+                for i in self.symbols.loop_range(first, last)? {
+                    self.symbols.consts.insert(var.clone(), i as f64);
+                    for stmt in body {
+                        self.stmt(stmt)?;
+                    }
+                }
+                match shadowed {
+                    Some(v) => self.symbols.consts.insert(var.clone(), v),
+                    None => self.symbols.consts.remove(var),
+                };
+            }
+        }
+        Ok(())
+    }
+
+    // Lower nested statements into their own op list
+    fn block(&mut self, stmts: &[Stmt]) -> Result<Vec<IROp>, Diagnostic> {
+        let outer = std::mem::take(&mut self.ir.ops);
+        let result = stmts.iter().try_for_each(|stmt| self.stmt(stmt));
+        let ops = std::mem::replace(&mut self.ir.ops, outer);
+        result.map(|()| ops)
+    }
+
+    fn gate(&mut self, call: &GateCall) -> Result<(), Diagnostic> {
+        let qs = sema::check_gate(call, &self.symbols)?;
+        let p: Vec<f64> = call.params.iter().map(|e| eval_expr(e, &self.symbols.consts)).collect();
+        self.apply(&call.name, &p, &qs);
+        Ok(())
+    }
+
+    // Emit a checked gate application, inlining user-defined gates
+    fn apply(&mut self, name: &str, p: &[f64], qs: &[usize]) {
+        if let Some(def) = self.symbols.gates.get(name).cloned() {
+            let env: HashMap<String, f64> = def.params.iter().map(|(n, _)| n.clone()).zip(p.iter().copied()).collect();
+            for call in &def.body {
+                let p: Vec<f64> = call.params.iter().map(|e| eval_expr(e, &env)).collect();
+                let args: Vec<usize> = call.args.iter()
+                    .map(|a| qs[def.qubits.iter().position(|(n, _)| *n == a.name).expect("checked by sema")])
+                    .collect();
+                self.apply(&call.name, &p, &args);
+            }
+            return;
+        }
+        let op = match name {
+            "id" => IROp::I(qs[0]),
+            "h" => IROp::H(qs[0]),
+            "x" => IROp::X(qs[0]),
+            "y" => IROp::Y(qs[0]),
+            "z" => IROp::Z(qs[0]),
+            "s" => IROp::S(qs[0]),
+            "sdg" => IROp::Sdg(qs[0]),
+            "t" => IROp::T(qs[0]),
+            "tdg" => IROp::Tdg(qs[0]),
+            "rx" => IROp::RX(qs[0], p[0]),
+            "ry" => IROp::RY(qs[0], p[0]),
+            "rz" => IROp::RZ(qs[0], p[0]),
+            "cnot" | "cx" => IROp::CNOT(qs[0], qs[1]),
+            "cz" => IROp::CZ(qs[0], qs[1]),
+            "swap" => IROp::SWAP(qs[0], qs[1]),
+            "toffoli" | "ccx" => {
+                self.ir.ops.extend(toffoli_ops(qs[0], qs[1], qs[2]));
+                return;
+            }
+            other => unreachable!("gate `{}` passed sema but has no lowering", other),
+        };
+        self.ir.push(op);
+    }
+}
@@ -0,0 +1,93 @@
+use crate::compiler::lexer::{LexError, Span};
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DiagnosticKind {
+    Syntax,
+    UnknownGate,
+    UndeclaredQubit,
+    UndeclaredBit,
+    WrongArity,
+    DuplicateDeclaration,
+    IndexOutOfRange,
+    DuplicateOperand,
+    UnknownIdentifier,
+    // A constant expression with a value that makes no sense where it is used
+    InvalidValue,
+    Unsupported,
+}
+
+// A compiler error pointing at a region of the source text
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub kind: DiagnosticKind,
+    pub message: String,
+    pub span: Span,
+    // Secondary location, e.g. the earlier declaration for a duplicate
+    pub note: Option<(String, Span)>,
+}
+
+impl Diagnostic {
+    pub fn new(kind: DiagnosticKind, message: impl Into<String>, span: Span) -> Self {
+        Self { kind, message: message.into(), span, note: None }
+    }
+
+    pub fn with_note(mut self, message: impl Into<String>, span: Span) -> Self {
+        self.note = Some((message.into(), span));
+        self
+    }
+
+    // Render in the familiar rustc layout:
+    //
+    //   error: unknown gate `foo`
+    //    --> circuit.qc:3:5
+    //     |
+    //   3 |     foo q0;
+    //     |     ^^^
+    pub fn render(&self, file_name: &str, src: &str) -> String {
+        let mut out = format!("error: {}\n", self.message);
+        let gutter = self.span.line.max(self.note.as_ref().map_or(0, |(_, s)| s.line)).to_string().len();
+        out += &format!("{:w$}--> {}:{}:{}\n", "", file_name, self.span.line, self.span.col, w = gutter);
+        out += &snippet(src, self.span, "", gutter);
+        if let Some((message, span)) = &self.note {
+            out += &snippet(src, *span, message, gutter);
+        }
+        out
+    }
+}
+
+fn snippet(src: &str, span: Span, label: &str, gutter: usize) -> String {
+    let line_text = src.lines().nth(span.line.saturating_sub(1)).unwrap_or("");
+    // Underline at least one column, and never past the end of the line
+    let line_len = line_text.chars().count();
+    let span_len = src.get(span.start..span.end).map_or(0, |s| s.chars().count());
+    let width = span_len.min(line_len.saturating_sub(span.col.saturating_sub(1))).max(1);
+
+    let mut out = format!("{:w$} |\n", "", w = gutter);
+    out += &format!("{:>w$} | {}\n", span.line, line_text, w = gutter);
+    out += &format!("{:w$} | {}{}", "", " ".repeat(span.col.saturating_sub(1)), "^".repeat(width), w = gutter);
+    if !label.is_empty() {
+        out += &format!(" {}", label);
+    }
+    out.push('\n');
+    out
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.span.line, self.span.col, self.message)
+    }
+}
+
+impl std::error::Error for Diagnostic {}
+
+impl From<LexError> for Diagnostic {
+    fn from(err: LexError) -> Self {
+        Diagnostic::new(DiagnosticKind::Syntax, err.message, err.span)
+    }
+}
+
+// Render a batch of diagnostics, separated by blank lines
+pub fn render_all(diagnostics: &[Diagnostic], file_name: &str, src: &str) -> String {
+    diagnostics.iter().map(|d| d.render(file_name, src)).collect::<Vec<_>>().join("\n")
+}
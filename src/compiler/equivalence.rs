@@ -0,0 +1,368 @@
+// Checks that two programs do the same thing, up to a global phase. Their
+// unitary parts are compared with the cheapest method that applies:
+//
+//   - Clifford circuits: the stabilizer tableaux of both unitaries, which
+//     determine a Clifford up to phase, are compared exactly;
+//   - small circuits: both are applied to a few random states, whose
+//     overlaps must all have modulus 1;
+//   - everything else: the miter A B† is built and inverse pairs are
+//     cancelled from the middle outwards, together with merged Z phases,
+//     Clifford stretches that multiply to the identity, and whatever the
+//     peephole and phase-folding rewrites remove. A difference in
+//     the middle leaves P M P†, which is the identity iff M is, so the
+//     outer layers are peeled off by conjugation. Whatever is left has to
+//     be the identity, which is checked on the tableau if it is Clifford,
+//     or on random states over just the qubits it touches if there are few.
+//
+// Limitation: there is no exact check for large non-Clifford circuits.
+// The rank-decomposition backend is still a stub, so there is nothing to
+// run a miter on. A non-Clifford residue touching more than
+// `max_dense_qubits` qubits gives `Unknown`, and callers of
+// `check_equivalence` should expect that answer for such programs.
+//
+// Measurements, resets and control flow have to line up one to one, with
+// the unitary runs between them (and nested blocks) checked separately.
+// That is sufficient but not necessary, so a mismatch there is reported as
+// unknown rather than as a difference. Markers and barriers are ignored.
+use crate::compiler::ir::{IROp, IRProgram};
+use crate::compiler::optimizer::peephole::peephole_block;
+use crate::compiler::optimizer::phase_folding::fold_phases;
+use crate::math::complex::Complex;
+use crate::runtime::statevector_backend::StatevectorSimulator;
+use crate::runtime::tableau_backend::TableauSimulator;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::BTreeMap;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Method {
+    Tableau,
+    Statevector,
+    Miter,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Equivalence {
+    Equivalent(Method),
+    NotEquivalent(Method),
+    // Neither could be shown, e.g. the miter did not simplify enough
+    Unknown(String),
+}
+
+impl Equivalence {
+    pub fn is_equivalent(&self) -> bool {
+        matches!(self, Equivalence::Equivalent(_))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct EquivalenceChecker {
+    // Random input states per statevector comparison
+    pub trials: usize,
+    // Largest allowed 1 - |<a|b>|; approximate synthesis needs more slack
+    pub tolerance: f64,
+    // Most qubits compared on statevectors
+    pub max_dense_qubits: usize,
+    pub seed: u64,
+}
+
+impl Default for EquivalenceChecker {
+    fn default() -> Self {
+        Self { trials: 3, tolerance: 1e-9, max_dense_qubits: 12, seed: 0 }
+    }
+}
+
+// `EquivalenceChecker::default().check(a, b)`
+pub fn check_equivalence(a: &IRProgram, b: &IRProgram) -> Equivalence {
+    EquivalenceChecker::default().check(a, b)
+}
+
+impl EquivalenceChecker {
+    pub fn check(&self, a: &IRProgram, b: &IRProgram) -> Equivalence {
+        if a.num_qubits != b.num_qubits {
+            return Equivalence::Unknown(format!("{} qubits vs {}", a.num_qubits, b.num_qubits));
+        }
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        self.check_blocks(a.num_qubits, &a.ops, &b.ops, &mut rng)
+    }
+
+    fn check_blocks(&self, n: usize, a: &[IROp], b: &[IROp], rng: &mut StdRng) -> Equivalence {
+        let (a, b) = (segments(a), segments(b));
+        if a.len() != b.len() {
+            return Equivalence::Unknown("measurements or control flow do not line up".to_string());
+        }
+        let single = a.len() == 1;
+        let mut methods = Vec::new();
+        for (i, ((ua, sa), (ub, sb))) in a.iter().zip(&b).enumerate() {
+            let mut results = vec![self.check_unitaries(n, ua, ub, rng)];
+            match (sa, sb) {
+                (None, None) => {}
+                (Some(x), Some(y)) => match self.check_structural(n, x, y, rng) {
+                    Some(nested) => results.extend(nested),
+                    None => return Equivalence::Unknown(format!("`{}` and `{}` do not line up", x, y)),
+                },
+                _ => return Equivalence::Unknown("measurements or control flow do not line up".to_string()),
+            }
+            for result in results {
+                match result {
+                    Equivalence::Equivalent(m) => methods.push(m),
+                    Equivalence::NotEquivalent(m) if single => return Equivalence::NotEquivalent(m),
+                    Equivalence::NotEquivalent(_) => return Equivalence::Unknown(format!("unitaries before non-unitary op {} differ", i)),
+                    unknown => return unknown,
+                }
+            }
+        }
+        // Report the most general method that was needed
+        let method = [Method::Miter, Method::Statevector, Method::Tableau]
+            .into_iter()
+            .find(|m| methods.contains(m))
+            .unwrap_or(Method::Tableau);
+        Equivalence::Equivalent(method)
+    }
+
+    // Non-unitary ops match if they are the same apart from nested blocks,
+    // which are compared as programs of their own
+    fn check_structural(&self, n: usize, x: &IROp, y: &IROp, rng: &mut StdRng) -> Option<Vec<Equivalence>> {
+        match (x, y) {
+            (IROp::Conditional { clbits: c1, value: v1, op: o1 }, IROp::Conditional { clbits: c2, value: v2, op: o2 })
+                if c1 == c2 && v1 == v2 =>
+            {
+                if !o1.is_unitary() || !o2.is_unitary() {
+                    return (o1 == o2).then(Vec::new);
+                }
+                Some(vec![self.check_unitaries(n, std::slice::from_ref(&**o1), std::slice::from_ref(&**o2), rng)])
+            }
+            (IROp::IfElse { clbits: c1, value: v1, then_ops: t1, else_ops: e1 },
+             IROp::IfElse { clbits: c2, value: v2, then_ops: t2, else_ops: e2 }) if c1 == c2 && v1 == v2 => {
+                Some(vec![self.check_blocks(n, t1, t2, rng), self.check_blocks(n, e1, e2, rng)])
+            }
+            (IROp::While { clbits: c1, value: v1, body: b1 }, IROp::While { clbits: c2, value: v2, body: b2 })
+                if c1 == c2 && v1 == v2 =>
+            {
+                Some(vec![self.check_blocks(n, b1, b2, rng)])
+            }
+            _ => (x == y).then(Vec::new),
+        }
+    }
+
+    fn check_unitaries(&self, n: usize, a: &[IROp], b: &[IROp], rng: &mut StdRng) -> Equivalence {
+        if a.iter().chain(b).all(|op| op.is_clifford()) {
+            let same = same_tableau(&clifford_tableau(n, a), &clifford_tableau(n, b));
+            return if same { Equivalence::Equivalent(Method::Tableau) } else { Equivalence::NotEquivalent(Method::Tableau) };
+        }
+        if n <= self.max_dense_qubits {
+            let same = self.same_on_random_states(n, a, b, rng);
+            return if same { Equivalence::Equivalent(Method::Statevector) } else { Equivalence::NotEquivalent(Method::Statevector) };
+        }
+        self.check_miter(n, a, b, rng)
+    }
+
+    fn check_miter(&self, n: usize, a: &[IROp], b: &[IROp], rng: &mut StdRng) -> Equivalence {
+        let mut miter = a.to_vec();
+        for op in b.iter().rev() {
+            miter.push(op.inverse().expect("unitary ops have inverses"));
+        }
+        let mut residue = cancel_inverse_pairs(miter);
+        loop {
+            let before = residue.len();
+            let folded = fold_phases(&IRProgram { num_qubits: n, ops: peephole_block(residue), ..Default::default() }).0.ops;
+            let (dropped, dropped_any) = drop_identity_cliffords(n, cancel_inverse_pairs(folded));
+            let (peeled, peeled_any) = peel_conjugation(cancel_inverse_pairs(dropped));
+            residue = peeled;
+            if !dropped_any && !peeled_any && residue.len() >= before {
+                break;
+            }
+        }
+        if residue.iter().all(|op| op.is_clifford()) {
+            let same = same_tableau(&clifford_tableau(n, &residue), &TableauSimulator::identity(n));
+            return if same { Equivalence::Equivalent(Method::Miter) } else { Equivalence::NotEquivalent(Method::Miter) };
+        }
+        // The residue acts as the identity on every qubit it does not touch
+        let mut touched: Vec<usize> = residue.iter().flat_map(|op| op.qubits()).collect();
+        touched.sort_unstable();
+        touched.dedup();
+        if touched.len() > self.max_dense_qubits {
+            return Equivalence::Unknown(format!("miter left {} gates on {} qubits", residue.len(), touched.len()));
+        }
+        let local: Vec<IROp> = residue.iter().map(|op| op.map_qubits(|q| touched.binary_search(&q).unwrap())).collect();
+        let same = self.same_on_random_states(touched.len(), &local, &[], rng);
+        if same { Equivalence::Equivalent(Method::Miter) } else { Equivalence::NotEquivalent(Method::Miter) }
+    }
+
+    // |<psi| A† B |psi>| = 1 for random psi
+    fn same_on_random_states(&self, n: usize, a: &[IROp], b: &[IROp], rng: &mut StdRng) -> bool {
+        (0..self.trials).all(|_| {
+            let psi = random_state(n, rng);
+            let run = |ops: &[IROp]| {
+                let mut sim = StatevectorSimulator::from(psi.clone());
+                for op in ops {
+                    sim.apply_ir(op);
+                }
+                sim.state
+            };
+            let (x, y) = (run(a), run(b));
+            let overlap = x.iter().zip(&y).fold(Complex::zero(), |acc, (u, v)| acc.add(&u.conj().mul(v)));
+            1.0 - overlap.magnitude2().sqrt() <= self.tolerance
+        })
+    }
+}
+
+// Unitary runs, each followed by the non-unitary op that ends it (None for
+// the last run)
+fn segments(ops: &[IROp]) -> Vec<(Vec<IROp>, Option<IROp>)> {
+    let mut out = Vec::new();
+    let mut run = Vec::new();
+    for op in ops {
+        if op.is_marker() || matches!(op, IROp::Barrier(_) | IROp::I(_)) {
+            continue;
+        }
+        if op.is_unitary() {
+            run.push(op.clone());
+        } else {
+            out.push((std::mem::take(&mut run), Some(op.clone())));
+        }
+    }
+    out.push((run, None));
+    out
+}
+
+fn clifford_tableau(n: usize, ops: &[IROp]) -> TableauSimulator {
+    let mut t = TableauSimulator::identity(n);
+    for op in ops {
+        t.apply_ir(op);
+    }
+    t
+}
+
+fn same_tableau(a: &TableauSimulator, b: &TableauSimulator) -> bool {
+    let n = a.num_qubits();
+    (0..2 * n).all(|row| a.phase(row) == b.phase(row) && (0..n).all(|q| a.pauli(row, q) == b.pauli(row, q)))
+}
+
+// Normalized complex Gaussian amplitudes, i.e. a Haar-random state
+fn random_state(n: usize, rng: &mut StdRng) -> Vec<Complex> {
+    let mut gaussian = || {
+        // Box-Muller
+        let (u, v): (f64, f64) = (rng.gen_range(f64::EPSILON..1.0), rng.gen_range(0.0..1.0));
+        let r = (-2.0 * u.ln()).sqrt();
+        Complex::new(r * (2.0 * std::f64::consts::PI * v).cos(), r * (2.0 * std::f64::consts::PI * v).sin())
+    };
+    let state: Vec<Complex> = (0..1usize << n).map(|_| gaussian()).collect();
+    let norm = state.iter().map(|a| a.magnitude2()).sum::<f64>().sqrt();
+    state.iter().map(|a| Complex::new(a.re / norm, a.im / norm)).collect()
+}
+
+fn z_phase(op: &IROp) -> Option<(usize, f64)> {
+    use std::f64::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+    match *op {
+        IROp::T(q) => Some((q, FRAC_PI_4)),
+        IROp::Tdg(q) => Some((q, -FRAC_PI_4)),
+        IROp::S(q) => Some((q, FRAC_PI_2)),
+        IROp::Sdg(q) => Some((q, -FRAC_PI_2)),
+        IROp::Z(q) => Some((q, PI)),
+        IROp::RZ(q, a) => Some((q, a)),
+        _ => None,
+    }
+}
+
+// Remove every op that directly follows its own inverse, looking past ops
+// on other qubits, so A A† collapses from the middle outwards. Z phases
+// that meet are merged first, so T T against S cancels as well.
+fn cancel_inverse_pairs(ops: Vec<IROp>) -> Vec<IROp> {
+    use std::f64::consts::PI;
+    let mut out: Vec<Option<IROp>> = Vec::new();
+    // Indices into `out` of the live ops on each qubit, in order
+    let mut on_qubit: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for op in ops {
+        let qubits = op.qubits();
+        let last = qubits.iter().filter_map(|q| on_qubit.get(q).and_then(|v| v.last().copied())).max();
+        let Some(i) = last else {
+            for &q in &qubits {
+                on_qubit.entry(q).or_default().push(out.len());
+            }
+            out.push(Some(op));
+            continue;
+        };
+        let prev = out[i].clone().unwrap();
+        let remove = match (z_phase(&prev), z_phase(&op)) {
+            (Some((p, a)), Some((q, b))) if p == q => {
+                let angle = (a + b).rem_euclid(2.0 * PI);
+                if angle.min(2.0 * PI - angle) > 1e-12 {
+                    out[i] = Some(IROp::RZ(q, a + b));
+                    continue;
+                }
+                true
+            }
+            _ => {
+                let mut pq = prev.qubits();
+                let mut oq = qubits.clone();
+                pq.sort_unstable();
+                oq.sort_unstable();
+                pq == oq && prev.inverse().as_ref() == Some(&op)
+            }
+        };
+        if remove {
+            for q in &qubits {
+                on_qubit.get_mut(q).unwrap().pop();
+            }
+            out[i] = None;
+        } else {
+            for &q in &qubits {
+                on_qubit.entry(q).or_default().push(out.len());
+            }
+            out.push(Some(op));
+        }
+    }
+    out.into_iter().flatten().collect()
+}
+
+// Circuit l1 ... lm is the identity iff l2 ... lm l1 (its conjugate by l1)
+// is, so ops are rotated from the front to the back whenever that lets them
+// cancel against the end, until a whole cycle makes no progress
+fn peel_conjugation(mut ops: Vec<IROp>) -> (Vec<IROp>, bool) {
+    let mut changed = false;
+    let mut tries = 0;
+    while tries < ops.len() {
+        let first = ops.remove(0);
+        let qubits = first.qubits();
+        let meets = ops.iter().rev().find(|op| op.qubits().iter().any(|q| qubits.contains(q)));
+        let cancels = meets.is_some_and(|last| match (z_phase(last), z_phase(&first)) {
+            (Some((p, _)), Some((q, _))) => p == q,
+            _ => last.inverse().as_ref() == Some(&first),
+        });
+        ops.push(first);
+        if cancels {
+            ops = cancel_inverse_pairs(ops);
+            changed = true;
+            tries = 0;
+        } else {
+            tries += 1;
+        }
+    }
+    (ops, changed)
+}
+
+// Drop runs of consecutive Clifford gates whose product is the identity,
+// such as CZ against H CNOT H in the middle of a miter
+fn drop_identity_cliffords(n: usize, ops: Vec<IROp>) -> (Vec<IROp>, bool) {
+    let mut out = Vec::new();
+    let mut changed = false;
+    let mut run: Vec<IROp> = Vec::new();
+    let mut flush = |run: &mut Vec<IROp>, out: &mut Vec<IROp>| {
+        if run.len() >= 2 && same_tableau(&clifford_tableau(n, run), &TableauSimulator::identity(n)) {
+            changed = true;
+            run.clear();
+        }
+        out.append(run);
+    };
+    for op in ops {
+        if op.is_clifford() {
+            run.push(op);
+        } else {
+            flush(&mut run, &mut out);
+            out.push(op);
+        }
+    }
+    flush(&mut run, &mut out);
+    (out, changed)
+}
@@ -0,0 +1,464 @@
+// Flat intermediate representation shared by the frontends, the optimizer
+// passes and the runtime backends. Qubits and classical bits are plain
+// indices into the program's registers.
+use std::collections::BTreeMap;
+use std::f64::consts::PI;
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum IROp {
+    // Single-qubit Cliffords
+    I(usize),
+    X(usize),
+    Y(usize),
+    Z(usize),
+    H(usize),
+    S(usize),
+    Sdg(usize),
+    SX(usize),
+    SXdg(usize),
+    // Single-qubit non-Cliffords
+    T(usize),
+    Tdg(usize),
+    RX(usize, f64),
+    RY(usize, f64),
+    RZ(usize, f64),
+    // U3(q, theta, phi, lambda) = RZ(phi) RY(theta) RZ(lambda) up to global phase
+    U3(usize, f64, f64, f64),
+    // Two-qubit gates, control first
+    CNOT(usize, usize),
+    CZ(usize, usize),
+    SWAP(usize, usize),
+    CPhase(usize, usize, f64),
+    CU3(usize, usize, f64, f64, f64),
+    // Three-qubit and multi-controlled gates
+    Toffoli(usize, usize, usize),
+    CCZ(usize, usize, usize),
+    MCX(Vec<usize>, usize),
+    // Non-unitary
+    Measure(usize, usize), // (qubit, clbit)
+    Reset(usize),
+    Barrier(Vec<usize>),
+    // Reset the qubit into the magic state |T> = T H|0>, to be consumed by
+    // a T gadget
+    MagicState(usize),
+    // Backend hints from the scheduler: switch to the statevector before a
+    // window of non-Clifford gates, and try to go back to the tableau after
+    // it. Neither changes the quantum state.
+    Promote,
+    Demote,
+    // Apply `op` only if the little-endian value of `clbits` equals `value`
+    // (OpenQASM 2 `if(c==n)`)
+    Conditional { clbits: Vec<usize>, value: u64, op: Box<IROp> },
+    // Structured control flow on runtime measurement results (OpenQASM 3),
+    // with the same bits == value test as `Conditional`
+    IfElse { clbits: Vec<usize>, value: u64, then_ops: Vec<IROp>, else_ops: Vec<IROp> },
+    While { clbits: Vec<usize>, value: u64, body: Vec<IROp> },
+}
+
+impl IROp {
+    pub fn name(&self) -> &'static str {
+        match self {
+            IROp::I(_) => "id",
+            IROp::X(_) => "x",
+            IROp::Y(_) => "y",
+            IROp::Z(_) => "z",
+            IROp::H(_) => "h",
+            IROp::S(_) => "s",
+            IROp::Sdg(_) => "sdg",
+            IROp::SX(_) => "sx",
+            IROp::SXdg(_) => "sxdg",
+            IROp::T(_) => "t",
+            IROp::Tdg(_) => "tdg",
+            IROp::RX(..) => "rx",
+            IROp::RY(..) => "ry",
+            IROp::RZ(..) => "rz",
+            IROp::U3(..) => "u3",
+            IROp::CNOT(..) => "cx",
+            IROp::CZ(..) => "cz",
+            IROp::SWAP(..) => "swap",
+            IROp::CPhase(..) => "cp",
+            IROp::CU3(..) => "cu3",
+            IROp::Toffoli(..) => "ccx",
+            IROp::CCZ(..) => "ccz",
+            IROp::MCX(..) => "mcx",
+            IROp::Measure(..) => "measure",
+            IROp::Reset(_) => "reset",
+            IROp::Barrier(_) => "barrier",
+            IROp::MagicState(_) => "magic_t",
+            IROp::Promote => "promote",
+            IROp::Demote => "demote",
+            IROp::Conditional { .. } => "if",
+            IROp::IfElse { .. } => "if_else",
+            IROp::While { .. } => "while",
+        }
+    }
+
+    // Qubits touched by this op, controls before targets
+    pub fn qubits(&self) -> Vec<usize> {
+        match self {
+            IROp::I(q) | IROp::X(q) | IROp::Y(q) | IROp::Z(q) | IROp::H(q) | IROp::S(q)
+            | IROp::Sdg(q) | IROp::SX(q) | IROp::SXdg(q) | IROp::T(q) | IROp::Tdg(q)
+            | IROp::RX(q, _) | IROp::RY(q, _) | IROp::RZ(q, _) | IROp::U3(q, ..)
+            | IROp::Measure(q, _) | IROp::Reset(q) | IROp::MagicState(q) => vec![*q],
+            IROp::CNOT(a, b) | IROp::CZ(a, b) | IROp::SWAP(a, b)
+            | IROp::CPhase(a, b, _) | IROp::CU3(a, b, ..) => vec![*a, *b],
+            IROp::Toffoli(a, b, c) | IROp::CCZ(a, b, c) => vec![*a, *b, *c],
+            IROp::MCX(controls, target) => {
+                let mut qs = controls.clone();
+                qs.push(*target);
+                qs
+            }
+            IROp::Barrier(qs) => qs.clone(),
+            IROp::Promote | IROp::Demote => Vec::new(),
+            IROp::Conditional { op, .. } => op.qubits(),
+            IROp::IfElse { then_ops, else_ops, .. } => block_qubits(then_ops.iter().chain(else_ops)),
+            IROp::While { body, .. } => block_qubits(body.iter()),
+        }
+    }
+
+    // Classical bits read or written, condition bits first
+    pub fn clbits(&self) -> Vec<usize> {
+        match self {
+            IROp::Measure(_, c) => vec![*c],
+            IROp::Conditional { clbits, op, .. } => {
+                let mut cs = clbits.clone();
+                cs.extend(op.clbits());
+                cs
+            }
+            IROp::IfElse { clbits, then_ops, else_ops, .. } => {
+                let mut cs = clbits.clone();
+                cs.extend(then_ops.iter().chain(else_ops).flat_map(|op| op.clbits()));
+                cs
+            }
+            IROp::While { clbits, body, .. } => {
+                let mut cs = clbits.clone();
+                cs.extend(body.iter().flat_map(|op| op.clbits()));
+                cs
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn params(&self) -> Vec<f64> {
+        match self {
+            IROp::RX(_, a) | IROp::RY(_, a) | IROp::RZ(_, a) | IROp::CPhase(_, _, a) => vec![*a],
+            IROp::U3(_, a, b, c) | IROp::CU3(_, _, a, b, c) => vec![*a, *b, *c],
+            IROp::Conditional { op, .. } => op.params(),
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn is_unitary(&self) -> bool {
+        !matches!(self, IROp::Measure(..) | IROp::Reset(_) | IROp::MagicState(_) | IROp::Barrier(_) | IROp::Conditional { .. })
+            && !self.is_marker()
+            && !self.is_control_flow()
+    }
+
+    pub fn is_marker(&self) -> bool {
+        matches!(self, IROp::Promote | IROp::Demote)
+    }
+
+    pub fn is_control_flow(&self) -> bool {
+        matches!(self, IROp::IfElse { .. } | IROp::While { .. })
+    }
+
+    // Ops nested inside this one (conditional bodies and control-flow blocks)
+    pub fn nested_ops(&self) -> Vec<&IROp> {
+        match self {
+            IROp::Conditional { op, .. } => vec![op],
+            IROp::IfElse { then_ops, else_ops, .. } => then_ops.iter().chain(else_ops).collect(),
+            IROp::While { body, .. } => body.iter().collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    // Whether the op is in the Clifford group (exactly, for the parameter given)
+    pub fn is_clifford(&self) -> bool {
+        match self {
+            IROp::I(_) | IROp::X(_) | IROp::Y(_) | IROp::Z(_) | IROp::H(_) | IROp::S(_)
+            | IROp::Sdg(_) | IROp::SX(_) | IROp::SXdg(_)
+            | IROp::CNOT(..) | IROp::CZ(..) | IROp::SWAP(..) => true,
+            IROp::RX(_, a) | IROp::RY(_, a) | IROp::RZ(_, a) | IROp::CPhase(_, _, a) => {
+                is_multiple_of(*a, if matches!(self, IROp::CPhase(..)) { PI } else { PI / 2.0 })
+            }
+            IROp::U3(_, a, b, c) => [a, b, c].iter().all(|x| is_multiple_of(**x, PI / 2.0)),
+            _ => false,
+        }
+    }
+
+    // Adjoint of a unitary op; None for measurement/reset/barrier
+    pub fn inverse(&self) -> Option<IROp> {
+        Some(match self.clone() {
+            IROp::S(q) => IROp::Sdg(q),
+            IROp::Sdg(q) => IROp::S(q),
+            IROp::SX(q) => IROp::SXdg(q),
+            IROp::SXdg(q) => IROp::SX(q),
+            IROp::T(q) => IROp::Tdg(q),
+            IROp::Tdg(q) => IROp::T(q),
+            IROp::RX(q, a) => IROp::RX(q, -a),
+            IROp::RY(q, a) => IROp::RY(q, -a),
+            IROp::RZ(q, a) => IROp::RZ(q, -a),
+            IROp::U3(q, t, p, l) => IROp::U3(q, -t, -l, -p),
+            IROp::CPhase(c, t, a) => IROp::CPhase(c, t, -a),
+            IROp::CU3(c, q, t, p, l) => IROp::CU3(c, q, -t, -l, -p),
+            IROp::Measure(..) | IROp::Reset(_) | IROp::MagicState(_) | IROp::Barrier(_) | IROp::Promote | IROp::Demote | IROp::Conditional { .. }
+            | IROp::IfElse { .. } | IROp::While { .. } => return None,
+            selfinverse => selfinverse,
+        })
+    }
+
+    // Rewrite the qubit indices through `f`
+    pub fn map_qubits(&self, f: impl Fn(usize) -> usize) -> IROp {
+        self.map_qubits_dyn(&f)
+    }
+
+    fn map_qubits_dyn(&self, f: &dyn Fn(usize) -> usize) -> IROp {
+        match self.clone() {
+            IROp::I(q) => IROp::I(f(q)),
+            IROp::X(q) => IROp::X(f(q)),
+            IROp::Y(q) => IROp::Y(f(q)),
+            IROp::Z(q) => IROp::Z(f(q)),
+            IROp::H(q) => IROp::H(f(q)),
+            IROp::S(q) => IROp::S(f(q)),
+            IROp::Sdg(q) => IROp::Sdg(f(q)),
+            IROp::SX(q) => IROp::SX(f(q)),
+            IROp::SXdg(q) => IROp::SXdg(f(q)),
+            IROp::T(q) => IROp::T(f(q)),
+            IROp::Tdg(q) => IROp::Tdg(f(q)),
+            IROp::RX(q, a) => IROp::RX(f(q), a),
+            IROp::RY(q, a) => IROp::RY(f(q), a),
+            IROp::RZ(q, a) => IROp::RZ(f(q), a),
+            IROp::U3(q, a, b, c) => IROp::U3(f(q), a, b, c),
+            IROp::CNOT(a, b) => IROp::CNOT(f(a), f(b)),
+            IROp::CZ(a, b) => IROp::CZ(f(a), f(b)),
+            IROp::SWAP(a, b) => IROp::SWAP(f(a), f(b)),
+            IROp::CPhase(a, b, t) => IROp::CPhase(f(a), f(b), t),
+            IROp::CU3(a, b, x, y, z) => IROp::CU3(f(a), f(b), x, y, z),
+            IROp::Toffoli(a, b, c) => IROp::Toffoli(f(a), f(b), f(c)),
+            IROp::CCZ(a, b, c) => IROp::CCZ(f(a), f(b), f(c)),
+            IROp::MCX(cs, t) => IROp::MCX(cs.into_iter().map(f).collect(), f(t)),
+            IROp::Measure(q, c) => IROp::Measure(f(q), c),
+            IROp::Reset(q) => IROp::Reset(f(q)),
+            IROp::MagicState(q) => IROp::MagicState(f(q)),
+            IROp::Barrier(qs) => IROp::Barrier(qs.into_iter().map(f).collect()),
+            IROp::Promote => IROp::Promote,
+            IROp::Demote => IROp::Demote,
+            IROp::Conditional { clbits, value, op } => IROp::Conditional { clbits, value, op: Box::new(op.map_qubits_dyn(f)) },
+            IROp::IfElse { clbits, value, then_ops, else_ops } => IROp::IfElse {
+                clbits,
+                value,
+                then_ops: then_ops.iter().map(|op| op.map_qubits_dyn(f)).collect(),
+                else_ops: else_ops.iter().map(|op| op.map_qubits_dyn(f)).collect(),
+            },
+            IROp::While { clbits, value, body } => IROp::While {
+                clbits,
+                value,
+                body: body.iter().map(|op| op.map_qubits_dyn(f)).collect(),
+            },
+        }
+    }
+}
+
+// `if (clbits == value) { then_ops } else { else_ops }`. Without an else
+// branch, and when the body leaves the condition bits alone, this is the
+// same as conditioning each op on its own, which every backend and
+// OpenQASM 2 understands.
+pub fn conditional_block(clbits: Vec<usize>, value: u64, then_ops: Vec<IROp>, else_ops: Vec<IROp>) -> Vec<IROp> {
+    let writes_condition = then_ops.iter().flat_map(|op| op.clbits()).any(|c| clbits.contains(&c));
+    if else_ops.is_empty() && !writes_condition {
+        then_ops.into_iter()
+            .map(|op| IROp::Conditional { clbits: clbits.clone(), value, op: Box::new(op) })
+            .collect()
+    } else {
+        vec![IROp::IfElse { clbits, value, then_ops, else_ops }]
+    }
+}
+
+// Classical bits that measurements in `ops` (or nested in them) write to
+pub fn written_clbits(ops: &[IROp]) -> Vec<usize> {
+    ops.iter().flat_map(|op| match op {
+        IROp::Measure(_, c) => vec![*c],
+        op => op.nested_ops().into_iter().flat_map(|inner| written_clbits(std::slice::from_ref(inner))).collect(),
+    }).collect()
+}
+
+// A `while` whose body never writes its condition bits spins forever once
+// it is entered
+pub fn loop_can_exit(clbits: &[usize], body: &[IROp]) -> bool {
+    written_clbits(body).iter().any(|c| clbits.contains(c))
+}
+
+fn block_qubits<'a>(ops: impl Iterator<Item = &'a IROp>) -> Vec<usize> {
+    let mut qs: Vec<usize> = ops.flat_map(|op| op.qubits()).collect();
+    qs.sort_unstable();
+    qs.dedup();
+    qs
+}
+
+fn is_multiple_of(angle: f64, unit: f64) -> bool {
+    let k = angle / unit;
+    (k - k.round()).abs() < 1e-9
+}
+
+impl fmt::Display for IROp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let condition = |clbits: &[usize], value: &u64| {
+            let cs: Vec<String> = clbits.iter().map(|c| format!("c[{}]", c)).collect();
+            format!("[{}]=={}", cs.join(", "), value)
+        };
+        let block = |ops: &[IROp]| ops.iter().map(|op| format!("{}; ", op)).collect::<String>();
+        match self {
+            IROp::Conditional { clbits, value, op } => return write!(f, "if({}) {}", condition(clbits, value), op),
+            IROp::IfElse { clbits, value, then_ops, else_ops } => {
+                return write!(f, "if({}) {{ {}}} else {{ {}}}", condition(clbits, value), block(then_ops), block(else_ops));
+            }
+            IROp::While { clbits, value, body } => {
+                return write!(f, "while({}) {{ {}}}", condition(clbits, value), block(body));
+            }
+            IROp::Promote | IROp::Demote => return write!(f, "{}", self.name()),
+            _ => {}
+        }
+        write!(f, "{}", self.name())?;
+        let params = self.params();
+        if !params.is_empty() {
+            let ps: Vec<String> = params.iter().map(|p| format!("{}", p)).collect();
+            write!(f, "({})", ps.join(", "))?;
+        }
+        let qs: Vec<String> = self.qubits().iter().map(|q| format!("q[{}]", q)).collect();
+        write!(f, " {}", qs.join(", "))?;
+        if let IROp::Measure(_, c) = self {
+            write!(f, " -> c[{}]", c)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum IRError {
+    QubitOutOfRange { op: usize, qubit: usize, num_qubits: usize },
+    ClbitOutOfRange { op: usize, clbit: usize, num_clbits: usize },
+    DuplicateQubit { op: usize, qubit: usize },
+    NoControls { op: usize },
+}
+
+impl fmt::Display for IRError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IRError::QubitOutOfRange { op, qubit, num_qubits } =>
+                write!(f, "op {}: qubit {} out of range ({} qubits)", op, qubit, num_qubits),
+            IRError::ClbitOutOfRange { op, clbit, num_clbits } =>
+                write!(f, "op {}: classical bit {} out of range ({} bits)", op, clbit, num_clbits),
+            IRError::DuplicateQubit { op, qubit } =>
+                write!(f, "op {}: qubit {} used more than once", op, qubit),
+            IRError::NoControls { op } =>
+                write!(f, "op {}: multi-controlled gate without controls", op),
+        }
+    }
+}
+
+impl std::error::Error for IRError {}
+
+// A named, contiguous slice of the qubit or clbit index space
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Register {
+    pub name: String,
+    pub offset: usize,
+    pub size: usize,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct IRProgram {
+    pub num_qubits: usize,
+    pub num_clbits: usize,
+    pub ops: Vec<IROp>,
+    // Source-level register names; frontends fill these in so that
+    // exporters can reproduce them. Empty means "one anonymous register".
+    pub qregs: Vec<Register>,
+    pub cregs: Vec<Register>,
+    // Free-form annotations, e.g. the source file name or the passes applied
+    pub metadata: BTreeMap<String, String>,
+}
+
+impl IRProgram {
+    pub fn new(num_qubits: usize, num_clbits: usize) -> Self {
+        Self { num_qubits, num_clbits, ..Default::default() }
+    }
+
+    pub fn push(&mut self, op: IROp) {
+        self.ops.push(op);
+    }
+
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    // Same registers and metadata, different op list
+    pub fn with_ops(&self, ops: Vec<IROp>) -> IRProgram {
+        IRProgram { ops, ..self.clone_empty() }
+    }
+
+    // Same registers and metadata, no ops
+    pub fn clone_empty(&self) -> IRProgram {
+        IRProgram {
+            num_qubits: self.num_qubits,
+            num_clbits: self.num_clbits,
+            ops: Vec::new(),
+            qregs: self.qregs.clone(),
+            cregs: self.cregs.clone(),
+            metadata: self.metadata.clone(),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), IRError> {
+        for (i, op) in self.ops.iter().enumerate() {
+            self.validate_op(i, op)?;
+        }
+        Ok(())
+    }
+
+    // Errors in nested ops are reported against the top-level op index
+    fn validate_op(&self, i: usize, op: &IROp) -> Result<(), IRError> {
+        let nested = op.nested_ops();
+        if !nested.is_empty() || op.is_control_flow() {
+            for inner in nested {
+                self.validate_op(i, inner)?;
+            }
+        } else {
+            if let IROp::MCX(controls, _) = op {
+                if controls.is_empty() {
+                    return Err(IRError::NoControls { op: i });
+                }
+            }
+            let qubits = op.qubits();
+            for (j, &q) in qubits.iter().enumerate() {
+                if q >= self.num_qubits {
+                    return Err(IRError::QubitOutOfRange { op: i, qubit: q, num_qubits: self.num_qubits });
+                }
+                if qubits[..j].contains(&q) {
+                    return Err(IRError::DuplicateQubit { op: i, qubit: q });
+                }
+            }
+        }
+        for c in op.clbits() {
+            if c >= self.num_clbits {
+                return Err(IRError::ClbitOutOfRange { op: i, clbit: c, num_clbits: self.num_clbits });
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for IRProgram {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "// {} qubits, {} clbits", self.num_qubits, self.num_clbits)?;
+        for op in &self.ops {
+            writeln!(f, "{};", op)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,277 @@
+// Tokenizer shared by the native circuit language and the OpenQASM
+// frontends. Native source looks like:
+//
+//     qubit q0, q1;
+//     h q0;
+//     cnot q0, q1;
+//     measure q0, q1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start: usize, // byte offsets into the source
+    pub end: usize,
+    pub line: usize, // 1-based
+    pub col: usize,  // 1-based, in chars
+}
+
+impl Span {
+    // Smallest span covering both `self` and `other`
+    pub fn to(&self, other: Span) -> Span {
+        if other.start < self.start {
+            return other.to(*self);
+        }
+        Span { start: self.start, end: other.end.max(self.end), line: self.line, col: self.col }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TokenKind {
+    Ident(String),
+    Int(u64),
+    Float(f64),
+    Comma,
+    Semicolon,
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    LBrace,
+    RBrace,
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Caret,
+    Arrow,
+    EqEq,
+    NotEq,
+    Assign,
+    Bang,
+    Colon,
+    DotDot,
+    At,
+    Str(String),
+    Eof,
+}
+
+impl TokenKind {
+    pub fn describe(&self) -> String {
+        match self {
+            TokenKind::Ident(name) => format!("identifier `{}`", name),
+            TokenKind::Int(v) => format!("integer `{}`", v),
+            TokenKind::Float(v) => format!("number `{}`", v),
+            TokenKind::Comma => "`,`".to_string(),
+            TokenKind::Semicolon => "`;`".to_string(),
+            TokenKind::LParen => "`(`".to_string(),
+            TokenKind::RParen => "`)`".to_string(),
+            TokenKind::LBracket => "`[`".to_string(),
+            TokenKind::RBracket => "`]`".to_string(),
+            TokenKind::LBrace => "`{`".to_string(),
+            TokenKind::RBrace => "`}`".to_string(),
+            TokenKind::Plus => "`+`".to_string(),
+            TokenKind::Minus => "`-`".to_string(),
+            TokenKind::Star => "`*`".to_string(),
+            TokenKind::Slash => "`/`".to_string(),
+            TokenKind::Caret => "`^`".to_string(),
+            TokenKind::Arrow => "`->`".to_string(),
+            TokenKind::EqEq => "`==`".to_string(),
+            TokenKind::NotEq => "`!=`".to_string(),
+            TokenKind::Assign => "`=`".to_string(),
+            TokenKind::Bang => "`!`".to_string(),
+            TokenKind::Colon => "`:`".to_string(),
+            TokenKind::DotDot => "`..`".to_string(),
+            TokenKind::At => "`@`".to_string(),
+            TokenKind::Str(text) => format!("string \"{}\"", text),
+            TokenKind::Eof => "end of input".to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LexError {
+    pub message: String,
+    pub span: Span,
+}
+
+pub struct Lexer<'a> {
+    src: &'a str,
+    pos: usize,
+    line: usize,
+    col: usize,
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(src: &'a str) -> Self {
+        Self { src, pos: 0, line: 1, col: 1 }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.src[self.pos..].chars().next()
+    }
+
+    fn peek_second(&self) -> Option<char> {
+        let mut chars = self.src[self.pos..].chars();
+        chars.next();
+        chars.next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        if c == '\n' {
+            self.line += 1;
+            self.col = 1;
+        } else {
+            self.col += 1;
+        }
+        Some(c)
+    }
+
+    fn skip_trivia(&mut self) {
+        loop {
+            match self.peek() {
+                Some(c) if c.is_whitespace() => { self.bump(); }
+                Some('/') if self.peek_second() == Some('/') => {
+                    while let Some(c) = self.peek() {
+                        if c == '\n' { break; }
+                        self.bump();
+                    }
+                }
+                _ => break,
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<TokenKind, String> {
+        let start = self.pos;
+        let mut is_float = false;
+        while let Some(c) = self.peek() {
+            if c.is_ascii_digit() {
+                self.bump();
+            } else if c == '.' && !is_float && self.peek_second() != Some('.') {
+                is_float = true;
+                self.bump();
+            } else if (c == 'e' || c == 'E') && self.pos > start {
+                // exponent, optionally signed
+                is_float = true;
+                self.bump();
+                if matches!(self.peek(), Some('+') | Some('-')) {
+                    self.bump();
+                }
+            } else {
+                break;
+            }
+        }
+        let text = &self.src[start..self.pos];
+        if is_float {
+            text.parse::<f64>().map(TokenKind::Float).map_err(|_| format!("malformed number `{}`", text))
+        } else {
+            text.parse::<u64>().map(TokenKind::Int).map_err(|_| format!("integer `{}` is out of range", text))
+        }
+    }
+
+    pub fn next_token(&mut self) -> Result<Token, LexError> {
+        self.skip_trivia();
+        let (start, line, col) = (self.pos, self.line, self.col);
+        let kind = match self.peek() {
+            None => TokenKind::Eof,
+            Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+                while let Some(c) = self.peek() {
+                    if c.is_ascii_alphanumeric() || c == '_' { self.bump(); } else { break; }
+                }
+                TokenKind::Ident(self.src[start..self.pos].to_string())
+            }
+            Some(c) if c.is_ascii_digit() || (c == '.' && self.peek_second().is_some_and(|d| d.is_ascii_digit())) => {
+                match self.number() {
+                    Ok(kind) => kind,
+                    Err(message) => {
+                        let span = Span { start, end: self.pos, line, col };
+                        return Err(LexError { message, span });
+                    }
+                }
+            }
+            Some('-') if self.peek_second() == Some('>') => {
+                self.bump();
+                self.bump();
+                TokenKind::Arrow
+            }
+            Some('=') if self.peek_second() == Some('=') => {
+                self.bump();
+                self.bump();
+                TokenKind::EqEq
+            }
+            Some('.') if self.peek_second() == Some('.') => {
+                self.bump();
+                self.bump();
+                TokenKind::DotDot
+            }
+            Some('!') if self.peek_second() == Some('=') => {
+                self.bump();
+                self.bump();
+                TokenKind::NotEq
+            }
+            Some('"') => {
+                self.bump();
+                let text_start = self.pos;
+                while let Some(c) = self.peek() {
+                    if c == '"' || c == '\n' { break; }
+                    self.bump();
+                }
+                let text = self.src[text_start..self.pos].to_string();
+                if self.peek() != Some('"') {
+                    let span = Span { start, end: self.pos, line, col };
+                    return Err(LexError { message: "unterminated string literal".to_string(), span });
+                }
+                self.bump();
+                TokenKind::Str(text)
+            }
+            Some(c) => {
+                self.bump();
+                match c {
+                    ',' => TokenKind::Comma,
+                    ';' => TokenKind::Semicolon,
+                    '(' => TokenKind::LParen,
+                    ')' => TokenKind::RParen,
+                    '[' => TokenKind::LBracket,
+                    ']' => TokenKind::RBracket,
+                    '{' => TokenKind::LBrace,
+                    '}' => TokenKind::RBrace,
+                    '+' => TokenKind::Plus,
+                    '-' => TokenKind::Minus,
+                    '*' => TokenKind::Star,
+                    '/' => TokenKind::Slash,
+                    '^' => TokenKind::Caret,
+                    '=' => TokenKind::Assign,
+                    '!' => TokenKind::Bang,
+                    ':' => TokenKind::Colon,
+                    '@' => TokenKind::At,
+                    other => {
+                        let span = Span { start, end: self.pos, line, col };
+                        return Err(LexError { message: format!("unexpected character `{}`", other), span });
+                    }
+                }
+            }
+        };
+        Ok(Token { kind, span: Span { start, end: self.pos, line, col } })
+    }
+}
+
+// Tokenize the whole source; the returned vector always ends with `Eof`.
+pub fn tokenize(src: &str) -> Result<Vec<Token>, LexError> {
+    let mut lexer = Lexer::new(src);
+    let mut tokens = Vec::new();
+    loop {
+        let tok = lexer.next_token()?;
+        let done = tok.kind == TokenKind::Eof;
+        tokens.push(tok);
+        if done {
+            return Ok(tokens);
+        }
+    }
+}
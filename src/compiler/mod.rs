@@ -0,0 +1,16 @@
+pub mod ast;
+pub mod codegen;
+pub mod diagnostics;
+pub mod equivalence;
+pub mod ir;
+pub mod lexer;
+pub mod optimizer;
+pub mod parser;
+pub mod qasm;
+pub mod resources;
+pub mod routing;
+pub mod scheduling;
+pub mod runtime;
+pub mod sema;
+pub mod synthesis;
+pub mod zx;
@@ -0,0 +1,187 @@
+// Read-only facts about a program that passes can ask the PassManager for.
+// Results are cached until a pass that does not preserve them runs.
+use crate::compiler::ir::{IROp, IRProgram};
+use std::collections::BTreeMap;
+use std::f64::consts::PI;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum AnalysisKind {
+    GateCounts,
+    Depth,
+    NonClifford,
+}
+
+// Summary numbers recorded before and after every pass
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Metrics {
+    pub gates: usize,
+    pub two_qubit: usize,
+    pub depth: usize,
+    pub t_count: usize,
+}
+
+impl Metrics {
+    pub fn of(prog: &IRProgram) -> Metrics {
+        let mut metrics = Metrics { depth: depth(prog), ..Default::default() };
+        for op in unitaries(&prog.ops) {
+            metrics.gates += 1;
+            if op.qubits().len() >= 2 {
+                metrics.two_qubit += 1;
+            }
+        }
+        metrics.t_count = t_count(&prog.ops);
+        metrics
+    }
+}
+
+// Every unitary op, including those nested in conditionals and blocks
+pub fn unitaries(ops: &[IROp]) -> Vec<&IROp> {
+    let mut out = Vec::new();
+    for op in ops {
+        if op.is_unitary() {
+            out.push(op);
+        } else {
+            out.extend(unitaries_nested(op));
+        }
+    }
+    out
+}
+
+fn unitaries_nested(op: &IROp) -> Vec<&IROp> {
+    op.nested_ops().into_iter().flat_map(|inner| {
+        if inner.is_unitary() { vec![inner] } else { unitaries_nested(inner) }
+    }).collect()
+}
+
+pub fn gate_counts(ops: &[IROp]) -> BTreeMap<&'static str, usize> {
+    let mut counts = BTreeMap::new();
+    for op in unitaries(ops) {
+        *counts.entry(op.name()).or_insert(0) += 1;
+    }
+    counts
+}
+
+// T/T† gates plus Z rotations by an odd multiple of pi/4
+pub fn t_count(ops: &[IROp]) -> usize {
+    unitaries(ops).into_iter().filter(|op| is_t_like(op)).count()
+}
+
+fn is_t_like(op: &IROp) -> bool {
+    match op {
+        IROp::T(_) | IROp::Tdg(_) => true,
+        IROp::RZ(_, a) => {
+            let k = a / (PI / 4.0);
+            (k - k.round()).abs() < 1e-9 && (k.round() as i64) % 2 != 0
+        }
+        IROp::Conditional { op, .. } => is_t_like(op),
+        _ => false,
+    }
+}
+
+// Number of layers when every op starts as soon as its qubits and clbits
+// are free. Barriers line their qubits up without taking a layer; a block
+// takes as long as its longest branch (one iteration for `while`).
+pub fn depth(prog: &IRProgram) -> usize {
+    weighted_depth(prog, &|_| 1)
+}
+
+// Depth counting only the T-like gates counted by `t_count`
+pub fn t_depth(prog: &IRProgram) -> usize {
+    weighted_depth(prog, &|op| is_t_like(op) as usize)
+}
+
+// Longest path through the program where each op other than barriers,
+// markers and blocks takes `weight(op)` layers
+fn weighted_depth(prog: &IRProgram, weight: &dyn Fn(&IROp) -> usize) -> usize {
+    let mut qubit_free = vec![0; prog.num_qubits];
+    let mut clbit_free = vec![0; prog.num_clbits];
+    schedule(&prog.ops, &mut qubit_free, &mut clbit_free, weight)
+}
+
+fn schedule(ops: &[IROp], qubit_free: &mut [usize], clbit_free: &mut [usize], weight: &dyn Fn(&IROp) -> usize) -> usize {
+    let mut end = 0;
+    for op in ops {
+        let qs = op.qubits();
+        let cs = op.clbits();
+        let start = qs.iter().map(|&q| qubit_free[q]).chain(cs.iter().map(|&c| clbit_free[c])).max().unwrap_or(0);
+        let duration = match op {
+            IROp::Barrier(_) | IROp::Promote | IROp::Demote => 0,
+            IROp::IfElse { then_ops, else_ops, .. } => {
+                block_depth(then_ops, qubit_free.len(), clbit_free.len(), weight)
+                    .max(block_depth(else_ops, qubit_free.len(), clbit_free.len(), weight))
+            }
+            IROp::While { body, .. } => block_depth(body, qubit_free.len(), clbit_free.len(), weight),
+            op => weight(op),
+        };
+        for q in qs {
+            qubit_free[q] = start + duration;
+        }
+        for c in cs {
+            clbit_free[c] = start + duration;
+        }
+        end = end.max(start + duration);
+    }
+    end
+}
+
+fn block_depth(ops: &[IROp], num_qubits: usize, num_clbits: usize, weight: &dyn Fn(&IROp) -> usize) -> usize {
+    schedule(ops, &mut vec![0; num_qubits], &mut vec![0; num_clbits], weight)
+}
+
+// Indices of top-level ops that are, or contain, a non-Clifford unitary.
+// These are the points where the runtime has to leave the tableau.
+pub fn non_clifford_ops(ops: &[IROp]) -> Vec<usize> {
+    ops.iter().enumerate()
+        .filter(|(_, op)| unitaries(std::slice::from_ref(*op)).iter().any(|u| !u.is_clifford()))
+        .map(|(i, _)| i)
+        .collect()
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Analyses {
+    gate_counts: Option<BTreeMap<&'static str, usize>>,
+    depth: Option<usize>,
+    non_clifford: Option<Vec<usize>>,
+}
+
+impl Analyses {
+    pub fn ensure(&mut self, kind: AnalysisKind, prog: &IRProgram) {
+        match kind {
+            AnalysisKind::GateCounts => {
+                self.gate_counts.get_or_insert_with(|| gate_counts(&prog.ops));
+            }
+            AnalysisKind::Depth => {
+                self.depth.get_or_insert_with(|| depth(prog));
+            }
+            AnalysisKind::NonClifford => {
+                self.non_clifford.get_or_insert_with(|| non_clifford_ops(&prog.ops));
+            }
+        }
+    }
+
+    // Drop every cached result not listed in `keep`
+    pub fn invalidate(&mut self, keep: &[AnalysisKind]) {
+        if !keep.contains(&AnalysisKind::GateCounts) {
+            self.gate_counts = None;
+        }
+        if !keep.contains(&AnalysisKind::Depth) {
+            self.depth = None;
+        }
+        if !keep.contains(&AnalysisKind::NonClifford) {
+            self.non_clifford = None;
+        }
+    }
+
+    // The getters panic when the running pass did not declare the analysis
+    pub fn gate_counts(&self) -> &BTreeMap<&'static str, usize> {
+        self.gate_counts.as_ref().expect("GateCounts analysis was not required by this pass")
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth.expect("Depth analysis was not required by this pass")
+    }
+
+    pub fn non_clifford(&self) -> &[usize] {
+        self.non_clifford.as_deref().expect("NonClifford analysis was not required by this pass")
+    }
+}
@@ -0,0 +1,70 @@
+// Clifford block resynthesis. Each maximal run of Clifford gates is
+// replaced by a circuit synthesized from its tableau, which is only kept
+// if it has fewer gates than the run, so the pass never makes things worse.
+use crate::compiler::ir::{IROp, IRProgram};
+use crate::compiler::optimizer::analysis::Analyses;
+use crate::compiler::optimizer::pass_manager::Pass;
+use crate::compiler::optimizer::peephole::peephole_block;
+use crate::runtime::tableau_backend::TableauSimulator;
+use crate::tableau::gates::CliffordGate;
+use crate::tableau::synthesis::synthesize;
+
+pub struct CliffordResynthesis;
+
+impl Pass for CliffordResynthesis {
+    fn name(&self) -> &'static str {
+        "clifford_resynthesis"
+    }
+
+    fn run(&mut self, mut prog: IRProgram, _: &Analyses) -> IRProgram {
+        prog.ops = clifford_block(std::mem::take(&mut prog.ops));
+        prog
+    }
+}
+
+fn clifford_block(ops: Vec<IROp>) -> Vec<IROp> {
+    let mut out = Vec::with_capacity(ops.len());
+    let mut run = Vec::new();
+    for op in ops {
+        if op.is_clifford() {
+            run.push(op);
+            continue;
+        }
+        out.extend(resynthesize_clifford(std::mem::take(&mut run)));
+        out.push(match op {
+            IROp::IfElse { clbits, value, then_ops, else_ops } => IROp::IfElse {
+                clbits,
+                value,
+                then_ops: clifford_block(then_ops),
+                else_ops: clifford_block(else_ops),
+            },
+            IROp::While { clbits, value, body } => IROp::While { clbits, value, body: clifford_block(body) },
+            op => op,
+        });
+    }
+    out.extend(resynthesize_clifford(run));
+    out
+}
+
+// The run rebuilt from its tableau, or unchanged if that is not shorter
+pub fn resynthesize_clifford(ops: Vec<IROp>) -> Vec<IROp> {
+    if ops.len() < 2 {
+        return ops;
+    }
+    let mut used: Vec<usize> = ops.iter().flat_map(|op| op.qubits()).collect();
+    used.sort_unstable();
+    used.dedup();
+    let mut t = TableauSimulator::identity(used.len());
+    for op in &ops {
+        t.apply_ir(&op.map_qubits(|q| used.binary_search(&q).unwrap()));
+    }
+    let new = peephole_block(synthesize(t).into_iter().map(|g| match g {
+        CliffordGate::H(q) => IROp::H(used[q]),
+        CliffordGate::S(q) => IROp::S(used[q]),
+        CliffordGate::Sdg(q) => IROp::Sdg(used[q]),
+        CliffordGate::X(q) => IROp::X(used[q]),
+        CliffordGate::Z(q) => IROp::Z(used[q]),
+        CliffordGate::CNOT(c, t) => IROp::CNOT(used[c], used[t]),
+    }).collect());
+    if new.len() < ops.len() { new } else { ops }
+}
@@ -0,0 +1,206 @@
+// Lowering into a target gate set. Multi-qubit gates are first broken into
+// CNOTs and single-qubit gates without ancillas, then every single-qubit
+// gate is rewritten in the basis:
+//
+//   CliffordT: H, S, T, CNOT, plus S†, T† and the Paulis. Z rotations that
+//              are not multiples of pi/4 stay as RZ, as they have no exact
+//              Clifford+T form and need an approximation pass.
+//   RzSx:      RZ, SX, CNOT (the IBM native set without X).
+//
+// Everything holds up to global phase.
+use crate::compiler::codegen::toffoli_ops;
+use crate::compiler::ir::{IROp, IRProgram};
+use crate::compiler::optimizer::analysis::Analyses;
+use crate::compiler::optimizer::pass_manager::Pass;
+use crate::compiler::optimizer::peephole::z_rotation;
+use std::f64::consts::{FRAC_PI_2, PI};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Basis {
+    CliffordT,
+    RzSx,
+}
+
+impl Basis {
+    pub fn contains(&self, op: &IROp) -> bool {
+        match self {
+            Basis::CliffordT => matches!(
+                op,
+                IROp::H(_) | IROp::S(_) | IROp::Sdg(_) | IROp::T(_) | IROp::Tdg(_)
+                    | IROp::X(_) | IROp::Y(_) | IROp::Z(_) | IROp::RZ(..) | IROp::CNOT(..)
+            ),
+            Basis::RzSx => matches!(op, IROp::RZ(..) | IROp::SX(_) | IROp::CNOT(..)),
+        }
+    }
+}
+
+pub struct Decompose {
+    basis: Basis,
+}
+
+impl Decompose {
+    pub fn new(basis: Basis) -> Self {
+        Self { basis }
+    }
+}
+
+impl Pass for Decompose {
+    fn name(&self) -> &'static str {
+        "decompose"
+    }
+
+    fn run(&mut self, mut prog: IRProgram, _: &Analyses) -> IRProgram {
+        prog.ops = decompose_block(std::mem::take(&mut prog.ops), self.basis);
+        prog
+    }
+}
+
+pub fn decompose_block(ops: Vec<IROp>, basis: Basis) -> Vec<IROp> {
+    ops.into_iter().flat_map(|op| decompose(op, basis)).collect()
+}
+
+// `op` in `basis`; non-unitary ops are returned as they are
+pub fn decompose(op: IROp, basis: Basis) -> Vec<IROp> {
+    match op {
+        IROp::Conditional { clbits, value, op } => decompose(*op, basis)
+            .into_iter()
+            .map(|op| IROp::Conditional { clbits: clbits.clone(), value, op: Box::new(op) })
+            .collect(),
+        IROp::IfElse { clbits, value, then_ops, else_ops } => vec![IROp::IfElse {
+            clbits,
+            value,
+            then_ops: decompose_block(then_ops, basis),
+            else_ops: decompose_block(else_ops, basis),
+        }],
+        IROp::While { clbits, value, body } => vec![IROp::While { clbits, value, body: decompose_block(body, basis) }],
+        // RZ is in the Clifford+T basis, but multiples of pi/4 become S/T
+        IROp::RZ(q, a) if basis == Basis::CliffordT => z_rotation(q, a),
+        op if !op.is_unitary() || basis.contains(&op) => vec![op],
+        op => lower(op).into_iter().flat_map(|op| single_qubit(op, basis)).collect(),
+    }
+}
+
+// CNOTs and single-qubit gates
+fn lower(op: IROp) -> Vec<IROp> {
+    match op {
+        IROp::CZ(a, b) => vec![IROp::H(b), IROp::CNOT(a, b), IROp::H(b)],
+        IROp::SWAP(a, b) => vec![IROp::CNOT(a, b), IROp::CNOT(b, a), IROp::CNOT(a, b)],
+        IROp::CPhase(a, b, t) => vec![
+            IROp::RZ(a, t / 2.0), IROp::RZ(b, t / 2.0),
+            IROp::CNOT(a, b), IROp::RZ(b, -t / 2.0), IROp::CNOT(a, b),
+        ],
+        // Nielsen & Chuang fig. 4.6 with the phase of U3 on the control
+        IROp::CU3(c, q, theta, phi, lambda) => vec![
+            IROp::RZ(c, (lambda + phi) / 2.0), IROp::RZ(q, (lambda - phi) / 2.0),
+            IROp::CNOT(c, q), IROp::U3(q, -theta / 2.0, 0.0, -(phi + lambda) / 2.0),
+            IROp::CNOT(c, q), IROp::U3(q, theta / 2.0, phi, 0.0),
+        ],
+        IROp::Toffoli(a, b, t) => toffoli_ops(a, b, t),
+        // H on the target around a Toffoli, where both Hs cancel
+        IROp::CCZ(a, b, t) => toffoli_ops(a, b, t).into_iter().filter(|op| *op != IROp::H(t)).collect(),
+        IROp::MCX(controls, t) => match controls[..] {
+            [] => vec![IROp::X(t)],
+            [c] => vec![IROp::CNOT(c, t)],
+            [a, b] => toffoli_ops(a, b, t),
+            _ => {
+                let mut qubits = controls;
+                qubits.push(t);
+                let mut ops = vec![IROp::H(t)];
+                ops.extend(multi_controlled_z(&qubits));
+                ops.push(IROp::H(t));
+                ops
+            }
+        },
+        op => vec![op],
+    }
+}
+
+// Ancilla-free C^(m-1)Z on `qubits` from the phase polynomial
+//   x_0 x_1 ... x_(m-1) = 2^(1-m) sum over nonempty S of (-1)^(|S|-1) parity(S),
+// with each parity built up in Gray-code order on the highest qubit of S.
+// This costs about 2^m CNOTs and rotations by pi / 2^(m-1), so it is only
+// meant for a handful of controls.
+fn multi_controlled_z(qubits: &[usize]) -> Vec<IROp> {
+    let unit = PI / (1u64 << (qubits.len() - 1)) as f64;
+    let mut ops = Vec::new();
+    for (j, &top) in qubits.iter().enumerate() {
+        let lower = 1usize << j;
+        for i in 0..lower {
+            if i > 0 {
+                ops.push(IROp::CNOT(qubits[i.trailing_zeros() as usize], top));
+            }
+            let size = (i ^ (i >> 1)).count_ones() + 1;
+            ops.push(IROp::RZ(top, if size % 2 == 1 { unit } else { -unit }));
+        }
+        // The last Gray code only has bit j-1 set
+        if j > 0 {
+            ops.push(IROp::CNOT(qubits[j - 1], top));
+        }
+    }
+    ops
+}
+
+// A single-qubit gate (or a CNOT, which is in every basis) in `basis`
+fn single_qubit(op: IROp, basis: Basis) -> Vec<IROp> {
+    if basis.contains(&op) {
+        return match (basis, op) {
+            (Basis::CliffordT, IROp::RZ(q, a)) => z_rotation(q, a),
+            (_, op) => vec![op],
+        };
+    }
+    let Some((q, theta, phi, lambda)) = as_u3(&op) else { return vec![op] };
+    match basis {
+        Basis::CliffordT => {
+            if theta == 0.0 {
+                return z_rotation(q, phi + lambda);
+            }
+            // RY(theta) = S H RZ(theta) H S†
+            let mut ops = z_rotation(q, lambda - FRAC_PI_2);
+            ops.push(IROp::H(q));
+            ops.extend(z_rotation(q, theta));
+            ops.push(IROp::H(q));
+            ops.extend(z_rotation(q, phi + FRAC_PI_2));
+            ops
+        }
+        Basis::RzSx => {
+            if theta == 0.0 {
+                return rz(q, phi + lambda);
+            }
+            // U3 = RZ(phi + pi) SX RZ(theta + pi) SX RZ(lambda)
+            let mut ops = rz(q, lambda);
+            ops.push(IROp::SX(q));
+            ops.extend(rz(q, theta + PI));
+            ops.push(IROp::SX(q));
+            ops.extend(rz(q, phi + PI));
+            ops
+        }
+    }
+}
+
+// RZ, left out when it is the identity
+fn rz(q: usize, angle: f64) -> Vec<IROp> {
+    let turns = angle / (2.0 * PI);
+    if (turns - turns.round()).abs() < 1e-12 { Vec::new() } else { vec![IROp::RZ(q, angle)] }
+}
+
+// U3 angles of a single-qubit gate, up to global phase
+fn as_u3(op: &IROp) -> Option<(usize, f64, f64, f64)> {
+    Some(match *op {
+        IROp::I(q) => (q, 0.0, 0.0, 0.0),
+        IROp::X(q) => (q, PI, 0.0, PI),
+        IROp::Y(q) => (q, PI, FRAC_PI_2, FRAC_PI_2),
+        IROp::Z(q) => (q, 0.0, 0.0, PI),
+        IROp::H(q) => (q, FRAC_PI_2, 0.0, PI),
+        IROp::S(q) => (q, 0.0, 0.0, FRAC_PI_2),
+        IROp::Sdg(q) => (q, 0.0, 0.0, -FRAC_PI_2),
+        IROp::T(q) => (q, 0.0, 0.0, PI / 4.0),
+        IROp::Tdg(q) => (q, 0.0, 0.0, -PI / 4.0),
+        IROp::SX(q) => (q, FRAC_PI_2, -FRAC_PI_2, FRAC_PI_2),
+        IROp::SXdg(q) => (q, -FRAC_PI_2, -FRAC_PI_2, FRAC_PI_2),
+        IROp::RX(q, a) => (q, a, -FRAC_PI_2, FRAC_PI_2),
+        IROp::RY(q, a) => (q, a, 0.0, 0.0),
+        IROp::RZ(q, a) => (q, 0.0, 0.0, a),
+        IROp::U3(q, theta, phi, lambda) => (q, theta, phi, lambda),
+        _ => return None,
+    })
+}
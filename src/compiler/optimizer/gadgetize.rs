@@ -0,0 +1,100 @@
+// Replace T gates by magic-state injection ("gadgets"), so that the only
+// non-Clifford step left is preparing |T> = T H|0> on an ancilla:
+//
+//   q ──────●────────[S]──   q: T|psi>
+//           │         ║
+//   a |T> ──X──[M]════╝      correction S when the outcome is 1
+//
+// The outcome is 0 or 1 with probability 1/2 whatever the state of q, and
+// leaves T or T† on q; S turns the latter into T. T† is S† followed by a
+// T gadget. One ancilla and one classical bit are appended to the program
+// and reused by every gadget, as each one measures the ancilla before the
+// next prepares it again. Other non-Clifford rotations are left alone, so
+// lower them to Clifford+T with `Decompose` first.
+//
+// The RuntimeController keeps gadgetized programs off the statevector: a
+// `MagicState` moves it from the tableau to a stabilizer sum, which at most
+// doubles its terms per magic state and merges them again when the gadget
+// measures. A Clifford+T program therefore never promotes once gadgetized.
+use crate::compiler::ir::{IROp, IRProgram, Register};
+use crate::compiler::optimizer::analysis::Analyses;
+use crate::compiler::optimizer::pass_manager::Pass;
+
+pub struct TGadgetization;
+
+impl Pass for TGadgetization {
+    fn name(&self) -> &'static str {
+        "gadgetize_t"
+    }
+
+    fn run(&mut self, mut prog: IRProgram, _: &Analyses) -> IRProgram {
+        if !prog.ops.iter().any(has_t) {
+            return prog;
+        }
+        let (ancilla, clbit) = (prog.num_qubits, prog.num_clbits);
+        prog.num_qubits += 1;
+        prog.num_clbits += 1;
+        if !prog.qregs.is_empty() {
+            let name = fresh_name(&prog.qregs, "magic");
+            prog.qregs.push(Register { name, offset: ancilla, size: 1 });
+        }
+        if !prog.cregs.is_empty() {
+            let name = fresh_name(&prog.cregs, "magic");
+            prog.cregs.push(Register { name, offset: clbit, size: 1 });
+        }
+        prog.ops = gadgetize_block(std::mem::take(&mut prog.ops), ancilla, clbit);
+        prog
+    }
+}
+
+// T on `qubit` through the magic state on `ancilla`, measured into `clbit`
+pub fn t_gadget(qubit: usize, ancilla: usize, clbit: usize) -> Vec<IROp> {
+    vec![
+        IROp::MagicState(ancilla),
+        IROp::CNOT(qubit, ancilla),
+        IROp::Measure(ancilla, clbit),
+        IROp::Conditional { clbits: vec![clbit], value: 1, op: Box::new(IROp::S(qubit)) },
+    ]
+}
+
+pub fn gadgetize_block(ops: Vec<IROp>, ancilla: usize, clbit: usize) -> Vec<IROp> {
+    ops.into_iter().flat_map(|op| gadgetize(op, ancilla, clbit)).collect()
+}
+
+fn gadgetize(op: IROp, ancilla: usize, clbit: usize) -> Vec<IROp> {
+    match op {
+        IROp::T(q) => t_gadget(q, ancilla, clbit),
+        IROp::Tdg(q) => {
+            let mut ops = vec![IROp::Sdg(q)];
+            ops.extend(t_gadget(q, ancilla, clbit));
+            ops
+        }
+        // The gadget measures, so a conditional T becomes a block
+        IROp::Conditional { clbits, value, op } if has_t(&op) => vec![IROp::IfElse {
+            clbits,
+            value,
+            then_ops: gadgetize(*op, ancilla, clbit),
+            else_ops: Vec::new(),
+        }],
+        IROp::IfElse { clbits, value, then_ops, else_ops } => vec![IROp::IfElse {
+            clbits,
+            value,
+            then_ops: gadgetize_block(then_ops, ancilla, clbit),
+            else_ops: gadgetize_block(else_ops, ancilla, clbit),
+        }],
+        IROp::While { clbits, value, body } => vec![IROp::While { clbits, value, body: gadgetize_block(body, ancilla, clbit) }],
+        op => vec![op],
+    }
+}
+
+fn has_t(op: &IROp) -> bool {
+    matches!(op, IROp::T(_) | IROp::Tdg(_)) || op.nested_ops().into_iter().any(has_t)
+}
+
+fn fresh_name(regs: &[Register], base: &str) -> String {
+    let mut name = base.to_string();
+    while regs.iter().any(|r| r.name == name) {
+        name.push('_');
+    }
+    name
+}
@@ -0,0 +1,22 @@
+// IRProgram -> IRProgram optimizations, driven by a PassManager. Each pass
+// lives in its own module; `pipeline` lists which ones run at each level.
+pub mod analysis;
+pub mod clifford_resynth;
+pub mod decompose;
+pub mod gadgetize;
+pub mod pass_manager;
+pub mod peephole;
+pub mod phase_folding;
+pub mod push_back;
+pub mod rz_synthesis;
+pub mod simplify;
+pub mod sk_synthesis;
+pub mod zx_simplify;
+
+use crate::compiler::ir::IRProgram;
+use pass_manager::{OptLevel, PassManager};
+
+// Run the standard pipeline for `level`
+pub fn optimize(prog: IRProgram, level: OptLevel) -> IRProgram {
+    PassManager::for_level(level).run(prog)
+}
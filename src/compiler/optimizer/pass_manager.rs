@@ -0,0 +1,181 @@
+use crate::compiler::equivalence::{Equivalence, EquivalenceChecker};
+use crate::compiler::ir::IRProgram;
+use crate::compiler::optimizer::analysis::{AnalysisKind, Analyses, Metrics};
+use crate::compiler::optimizer::clifford_resynth::CliffordResynthesis;
+use crate::compiler::optimizer::peephole::Peephole;
+use crate::compiler::optimizer::phase_folding::PhaseFolding;
+use crate::compiler::optimizer::push_back::NonCliffordPushBack;
+use crate::compiler::optimizer::simplify::RemoveIdentities;
+use crate::compiler::optimizer::zx_simplify::ZXSimplify;
+use std::fmt;
+
+// A program transformation. Passes must keep the program's behaviour the
+// same (up to global phase) and leave it valid.
+pub trait Pass {
+    fn name(&self) -> &'static str;
+
+    // Analyses computed before `run` and readable through `analyses`
+    fn requires(&self) -> &[AnalysisKind] {
+        &[]
+    }
+
+    // Analyses that are still correct after `run`; the rest are recomputed
+    fn preserves(&self) -> &[AnalysisKind] {
+        &[]
+    }
+
+    fn run(&mut self, prog: IRProgram, analyses: &Analyses) -> IRProgram;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum OptLevel {
+    O0, // no passes
+    O1, // cheap local cleanups
+    O2, // plus the standard gate-count reductions
+    O3, // plus expensive global rewrites
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PassStats {
+    pub pass: &'static str,
+    pub before: Metrics,
+    pub after: Metrics,
+    // The verifier's verdict, when the PassManager has one
+    pub verification: Option<Equivalence>,
+}
+
+impl PassStats {
+    pub fn changed(&self) -> bool {
+        self.before != self.after
+    }
+
+    // The verifier showed that the pass changed the program, so its output
+    // was thrown away
+    pub fn failed_verification(&self) -> bool {
+        matches!(self.verification, Some(Equivalence::NotEquivalent(_)))
+    }
+}
+
+impl fmt::Display for PassStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}: gates {} -> {}, depth {} -> {}, T {} -> {}",
+            self.pass,
+            self.before.gates, self.after.gates,
+            self.before.depth, self.after.depth,
+            self.before.t_count, self.after.t_count,
+        )?;
+        if self.failed_verification() {
+            write!(f, " (failed verification, reverted)")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct PassManager {
+    passes: Vec<Box<dyn Pass>>,
+    stats: Vec<PassStats>,
+    // Checks every pass's output against its input when set
+    verifier: Option<EquivalenceChecker>,
+}
+
+impl PassManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // The standard pipeline for each optimization level
+    pub fn for_level(level: OptLevel) -> Self {
+        let mut pm = PassManager::new();
+        if level >= OptLevel::O1 {
+            pm.add(RemoveIdentities);
+            pm.add(Peephole);
+        }
+        if level >= OptLevel::O2 {
+            pm.add(PhaseFolding);
+            pm.add(Peephole);
+        }
+        if level >= OptLevel::O3 {
+            pm.add(ZXSimplify);
+            pm.add(CliffordResynthesis);
+            pm.add(Peephole);
+        }
+        // Scheduling goes last so the markers see the final gates
+        if level >= OptLevel::O2 {
+            pm.add(NonCliffordPushBack);
+        }
+        pm
+    }
+
+    pub fn add(&mut self, pass: impl Pass + 'static) -> &mut Self {
+        self.passes.push(Box::new(pass));
+        self
+    }
+
+    pub fn with(mut self, pass: impl Pass + 'static) -> Self {
+        self.add(pass);
+        self
+    }
+
+    // Check every pass's output against its input. A pass that provably
+    // changes what the program does is undone and reported through
+    // `verification_failures`. Approximate passes need a checker with a
+    // matching tolerance.
+    pub fn verified(mut self, checker: EquivalenceChecker) -> Self {
+        self.verifier = Some(checker);
+        self
+    }
+
+    pub fn pass_names(&self) -> Vec<&'static str> {
+        self.passes.iter().map(|p| p.name()).collect()
+    }
+
+    // Run every pass in order, recording metrics around each one. The
+    // names of the passes applied are appended to `metadata["passes"]`.
+    pub fn run(&mut self, mut prog: IRProgram) -> IRProgram {
+        self.stats.clear();
+        let mut analyses = Analyses::default();
+        let mut metrics = Metrics::of(&prog);
+        for pass in &mut self.passes {
+            for &kind in pass.requires() {
+                analyses.ensure(kind, &prog);
+            }
+            let before = self.verifier.as_ref().map(|_| prog.clone());
+            prog = pass.run(prog, &analyses);
+            debug_assert_eq!(prog.validate(), Ok(()), "pass `{}` produced invalid IR", pass.name());
+            let mut verification = None;
+            if let (Some(checker), Some(before)) = (&self.verifier, before) {
+                let result = checker.check(&before, &prog);
+                if matches!(result, Equivalence::NotEquivalent(_)) {
+                    prog = before;
+                }
+                verification = Some(result);
+            }
+            analyses.invalidate(pass.preserves());
+            let after = Metrics::of(&prog);
+            self.stats.push(PassStats { pass: pass.name(), before: metrics, after, verification });
+            metrics = after;
+        }
+        if !self.passes.is_empty() {
+            let names = self.pass_names().join(", ");
+            let entry = prog.metadata.entry("passes".to_string()).or_default();
+            if !entry.is_empty() {
+                entry.push_str(", ");
+            }
+            entry.push_str(&names);
+        }
+        prog
+    }
+
+    // Statistics from the last `run`, one entry per pass
+    pub fn stats(&self) -> &[PassStats] {
+        &self.stats
+    }
+
+    // Passes from the last `run` whose output the verifier rejected
+    pub fn verification_failures(&self) -> Vec<&PassStats> {
+        self.stats.iter().filter(|s| s.failed_verification()).collect()
+    }
+}
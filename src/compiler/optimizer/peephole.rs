@@ -0,0 +1,195 @@
+// Local cancellation and merging. Each new op is moved back past the ops it
+// commutes with until it meets one it can combine with:
+//
+//   H·H, CX·CX, CZ·CZ, SWAP·SWAP, S·S†, T·T†, SX·SX† -> nothing
+//   T·T -> S, S·S -> Z, T·S† -> T† ...        (Z phases in units of pi/4)
+//   RZ(a)·RZ(b) -> RZ(a+b), likewise RX, RY and CP
+//
+// Everything here holds up to global phase. The pass repeats until nothing
+// changes, so chains like T·T·S collapse all the way to Z.
+use crate::compiler::ir::{IROp, IRProgram};
+use crate::compiler::optimizer::analysis::Analyses;
+use crate::compiler::optimizer::pass_manager::Pass;
+use crate::compiler::optimizer::simplify::is_identity;
+use std::f64::consts::PI;
+
+pub struct Peephole;
+
+impl Pass for Peephole {
+    fn name(&self) -> &'static str {
+        "peephole"
+    }
+
+    fn run(&mut self, mut prog: IRProgram, _: &Analyses) -> IRProgram {
+        prog.ops = peephole_block(std::mem::take(&mut prog.ops));
+        prog
+    }
+}
+
+pub fn peephole_block(ops: Vec<IROp>) -> Vec<IROp> {
+    let mut ops: Vec<IROp> = ops.into_iter().map(peephole_nested).collect();
+    loop {
+        let before = ops.len();
+        ops = sweep(ops);
+        if ops.len() == before {
+            return ops;
+        }
+    }
+}
+
+fn peephole_nested(op: IROp) -> IROp {
+    match op {
+        IROp::IfElse { clbits, value, then_ops, else_ops } => IROp::IfElse {
+            clbits,
+            value,
+            then_ops: peephole_block(then_ops),
+            else_ops: peephole_block(else_ops),
+        },
+        IROp::While { clbits, value, body } => IROp::While { clbits, value, body: peephole_block(body) },
+        op => op,
+    }
+}
+
+fn sweep(ops: Vec<IROp>) -> Vec<IROp> {
+    let mut out: Vec<IROp> = Vec::with_capacity(ops.len());
+    'next: for op in ops {
+        if op.is_unitary() {
+            for j in (0..out.len()).rev() {
+                if let Some(merged) = combine(&out[j], &op) {
+                    out.splice(j..=j, merged.into_iter().filter(|m| !is_identity(m)));
+                    continue 'next;
+                }
+                if !commutes(&out[j], &op) {
+                    break;
+                }
+            }
+        }
+        out.push(op);
+    }
+    out
+}
+
+fn same_qubits(a: &IROp, b: &IROp) -> bool {
+    let (mut qa, mut qb) = (a.qubits(), b.qubits());
+    qa.sort_unstable();
+    qb.sort_unstable();
+    qa == qb
+}
+
+// Z rotation in units of pi/4 for the named phase gates
+fn phase_eighths(op: &IROp) -> Option<u32> {
+    match op {
+        IROp::T(_) => Some(1),
+        IROp::S(_) => Some(2),
+        IROp::Z(_) => Some(4),
+        IROp::Sdg(_) => Some(6),
+        IROp::Tdg(_) => Some(7),
+        _ => None,
+    }
+}
+
+// Shortest named-gate form of a Z rotation by k*pi/4
+fn phase_gates(q: usize, k: u32) -> Option<Vec<IROp>> {
+    Some(match k % 8 {
+        0 => Vec::new(),
+        1 => vec![IROp::T(q)],
+        2 => vec![IROp::S(q)],
+        4 => vec![IROp::Z(q)],
+        6 => vec![IROp::Sdg(q)],
+        7 => vec![IROp::Tdg(q)],
+        _ => return None,
+    })
+}
+
+// Z rotation by `angle` as named phase gates where that is exact, else RZ
+pub fn z_rotation(q: usize, angle: f64) -> Vec<IROp> {
+    let k = angle / (PI / 4.0);
+    if (k - k.round()).abs() < 1e-12 {
+        let k = k.round().rem_euclid(8.0) as u32;
+        return phase_gates(q, k).unwrap_or_else(|| {
+            let mut ops = phase_gates(q, k - 1).expect("even multiple");
+            ops.push(IROp::T(q));
+            ops
+        });
+    }
+    vec![IROp::RZ(q, angle)]
+}
+
+pub fn z_angle(op: &IROp) -> Option<f64> {
+    match op {
+        IROp::RZ(_, a) => Some(*a),
+        _ => phase_eighths(op).map(|k| k as f64 * PI / 4.0),
+    }
+}
+
+// Replacement for `a` followed by `b`, if they combine
+fn combine(a: &IROp, b: &IROp) -> Option<Vec<IROp>> {
+    if !a.is_unitary() || !same_qubits(a, b) {
+        return None;
+    }
+    let cancels = match (a, b) {
+        (IROp::CZ(..), IROp::CZ(..)) | (IROp::SWAP(..), IROp::SWAP(..)) | (IROp::CCZ(..), IROp::CCZ(..)) => true,
+        (IROp::Toffoli(a0, a1, at), IROp::Toffoli(b0, b1, bt)) => at == bt && (a0 == b0 || a0 == b1) && (a1 == b0 || a1 == b1),
+        (IROp::MCX(ac, at), IROp::MCX(bc, bt)) => at == bt && ac.len() == bc.len() && ac.iter().all(|c| bc.contains(c)),
+        _ => a.inverse().as_ref() == Some(b),
+    };
+    if cancels {
+        return Some(Vec::new());
+    }
+    match (a, b) {
+        (IROp::RX(q, x), IROp::RX(_, y)) => Some(vec![IROp::RX(*q, x + y)]),
+        (IROp::RY(q, x), IROp::RY(_, y)) => Some(vec![IROp::RY(*q, x + y)]),
+        (IROp::CPhase(c, t, x), IROp::CPhase(_, _, y)) => Some(vec![IROp::CPhase(*c, *t, x + y)]),
+        _ => {
+            let q = a.qubits()[0];
+            if let (Some(ka), Some(kb)) = (phase_eighths(a), phase_eighths(b)) {
+                return phase_gates(q, ka + kb);
+            }
+            let angle = z_angle(a)? + z_angle(b)?;
+            let k = angle / (PI / 4.0);
+            if (k - k.round()).abs() < 1e-12 {
+                if let Some(gates) = phase_gates(q, k.round().rem_euclid(8.0) as u32) {
+                    return Some(gates);
+                }
+            }
+            Some(vec![IROp::RZ(q, angle)])
+        }
+    }
+}
+
+fn is_diagonal(op: &IROp) -> bool {
+    matches!(
+        op,
+        IROp::I(_) | IROp::Z(_) | IROp::S(_) | IROp::Sdg(_) | IROp::T(_) | IROp::Tdg(_) | IROp::RZ(..)
+            | IROp::CZ(..) | IROp::CPhase(..) | IROp::CCZ(..)
+    )
+}
+
+// X-type single-qubit gates, which commute with a CNOT on its target
+fn is_x_rotation(op: &IROp) -> bool {
+    matches!(op, IROp::X(_) | IROp::SX(_) | IROp::SXdg(_) | IROp::RX(..))
+}
+
+// Whether `a` followed by `b` equals `b` followed by `a`
+pub fn commutes(a: &IROp, b: &IROp) -> bool {
+    let (qa, qb) = (a.qubits(), b.qubits());
+    if qa.iter().all(|q| !qb.contains(q)) {
+        // Classical wires still order measurements and conditionals
+        let (ca, cb) = (a.clbits(), b.clbits());
+        return ca.iter().all(|c| !cb.contains(c));
+    }
+    if !a.is_unitary() || !b.is_unitary() {
+        return false;
+    }
+    if is_diagonal(a) && is_diagonal(b) {
+        return true;
+    }
+    let past_cnot = |single: &IROp, cnot: &IROp| match *cnot {
+        IROp::CNOT(c, t) if single.qubits().len() == 1 => {
+            let q = single.qubits()[0];
+            (q == c && is_diagonal(single)) || (q == t && is_x_rotation(single))
+        }
+        _ => false,
+    };
+    past_cnot(a, b) || past_cnot(b, a)
+}
@@ -0,0 +1,122 @@
+// Phase folding (Amy, Maslov & Mosca, "Polynomial-time T-depth optimization
+// of Clifford+T circuits via matroid partitioning"). Over {CNOT, X, SWAP}
+// every qubit holds an affine parity of path variables, and each Z-phase
+// gate adds a term e^{i theta * parity} to the phase polynomial. Terms with
+// the same parity are merged and emitted once, where the parity first
+// appeared. Any other gate on a qubit starts a fresh variable there, so the
+// pass works across H and friends without breaking the circuit into blocks.
+use crate::compiler::ir::{IROp, IRProgram};
+use crate::compiler::optimizer::analysis::{t_count, Analyses};
+use crate::compiler::optimizer::pass_manager::Pass;
+use crate::compiler::optimizer::peephole::{z_angle, z_rotation};
+use std::collections::{BTreeSet, HashMap};
+
+pub struct PhaseFolding;
+
+impl Pass for PhaseFolding {
+    fn name(&self) -> &'static str {
+        "phase_folding"
+    }
+
+    fn run(&mut self, prog: IRProgram, _: &Analyses) -> IRProgram {
+        let (mut out, report) = fold_phases(&prog);
+        out.metadata.insert("t_count".to_string(), format!("{} -> {}", report.before, report.after));
+        out
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TCountReport {
+    pub before: usize,
+    pub after: usize,
+}
+
+pub fn fold_phases(prog: &IRProgram) -> (IRProgram, TCountReport) {
+    let ops = fold_block(&prog.ops, prog.num_qubits);
+    let report = TCountReport { before: t_count(&prog.ops), after: t_count(&ops) };
+    (prog.with_ops(ops), report)
+}
+
+// x_{vars} xor constant
+#[derive(Clone)]
+struct Parity {
+    vars: BTreeSet<usize>,
+    flipped: bool,
+}
+
+struct Term {
+    angle: f64,
+    slot: usize,
+    qubit: usize,
+    flipped: bool, // the qubit held the negated parity at `slot`
+}
+
+fn fold_block(ops: &[IROp], num_qubits: usize) -> Vec<IROp> {
+    let mut parity: Vec<Parity> = (0..num_qubits).map(|q| Parity { vars: BTreeSet::from([q]), flipped: false }).collect();
+    let mut next_var = num_qubits;
+    let mut terms: HashMap<BTreeSet<usize>, Term> = HashMap::new();
+    // Phase gates leave a hole that the merged term may fill later
+    let mut out: Vec<Option<IROp>> = Vec::with_capacity(ops.len());
+
+    for op in ops {
+        if let Some(theta) = z_angle(op).filter(|_| op.is_unitary()) {
+            let q = op.qubits()[0];
+            let p = &parity[q];
+            // e^{i theta (x xor 1)} = e^{i theta} e^{-i theta x}
+            let signed = if p.flipped { -theta } else { theta };
+            match terms.get_mut(&p.vars) {
+                Some(term) => term.angle += signed,
+                None => {
+                    terms.insert(p.vars.clone(), Term { angle: signed, slot: out.len(), qubit: q, flipped: p.flipped });
+                }
+            }
+            out.push(None);
+            continue;
+        }
+        match op {
+            IROp::CNOT(c, t) => {
+                let control = parity[*c].clone();
+                let target = &mut parity[*t];
+                target.vars = target.vars.symmetric_difference(&control.vars).copied().collect();
+                target.flipped ^= control.flipped;
+            }
+            IROp::X(q) => parity[*q].flipped ^= true,
+            IROp::SWAP(a, b) => parity.swap(*a, *b),
+            // Diagonal gates leave every qubit's value alone
+            IROp::I(_) | IROp::CZ(..) | IROp::CPhase(..) | IROp::CCZ(..) | IROp::Barrier(_)
+            | IROp::Promote | IROp::Demote => {}
+            _ => {
+                for q in op.qubits() {
+                    parity[q] = Parity { vars: BTreeSet::from([next_var]), flipped: false };
+                    next_var += 1;
+                }
+            }
+        }
+        out.push(Some(nested(op, num_qubits)));
+    }
+
+    let mut placed: Vec<Vec<IROp>> = out.into_iter().map(|op| op.into_iter().collect()).collect();
+    for term in terms.into_values() {
+        let angle = if term.flipped { -term.angle } else { term.angle };
+        placed[term.slot] = z_rotation(term.qubit, angle);
+    }
+    placed.into_iter().flatten().collect()
+}
+
+// Blocks are folded on their own
+fn nested(op: &IROp, num_qubits: usize) -> IROp {
+    match op {
+        IROp::IfElse { clbits, value, then_ops, else_ops } => IROp::IfElse {
+            clbits: clbits.clone(),
+            value: *value,
+            then_ops: fold_block(then_ops, num_qubits),
+            else_ops: fold_block(else_ops, num_qubits),
+        },
+        IROp::While { clbits, value, body } => IROp::While {
+            clbits: clbits.clone(),
+            value: *value,
+            body: fold_block(body, num_qubits),
+        },
+        op => op.clone(),
+    }
+}
@@ -0,0 +1,137 @@
+// Delay non-Clifford gates so the runtime can stay on the tableau for as
+// long as possible. T/RZ-style gates are commuted towards the end of the
+// program and pile up into windows, and each window is bracketed by
+// `Promote`/`Demote` markers for the RuntimeController.
+use crate::compiler::ir::{IROp, IRProgram};
+use crate::compiler::optimizer::analysis::{unitaries, AnalysisKind, Analyses};
+use crate::compiler::optimizer::pass_manager::Pass;
+use crate::compiler::optimizer::peephole::commutes;
+use std::collections::BTreeSet;
+
+pub struct NonCliffordPushBack;
+
+impl Pass for NonCliffordPushBack {
+    fn name(&self) -> &'static str {
+        "push_back_non_clifford"
+    }
+
+    fn requires(&self) -> &[AnalysisKind] {
+        &[AnalysisKind::NonClifford]
+    }
+
+    fn preserves(&self) -> &[AnalysisKind] {
+        &[AnalysisKind::GateCounts]
+    }
+
+    fn run(&mut self, mut prog: IRProgram, analyses: &Analyses) -> IRProgram {
+        if analyses.non_clifford().is_empty() {
+            return prog;
+        }
+        // Markers from an earlier run would pin the old windows in place
+        let ops: Vec<IROp> = std::mem::take(&mut prog.ops).into_iter().filter(|op| !op.is_marker()).collect();
+        prog.ops = mark_windows(push_back(ops));
+        prog
+    }
+}
+
+// Magic states don't count: the runtime injects them next to the tableau
+// rather than promoting
+fn is_non_clifford(op: &IROp) -> bool {
+    unitaries(std::slice::from_ref(op)).iter().any(|u| !u.is_clifford())
+}
+
+// Bubble every non-Clifford unitary right past the Clifford ops it commutes
+// with. Going from the back means later gates have already moved, so a gate
+// stops right behind the next non-Clifford one and the two form a window.
+pub fn push_back(mut ops: Vec<IROp>) -> Vec<IROp> {
+    for i in (0..ops.len()).rev() {
+        if !ops[i].is_unitary() || ops[i].is_clifford() {
+            continue;
+        }
+        let mut j = i;
+        while j + 1 < ops.len() {
+            let next = &ops[j + 1];
+            if is_non_clifford(next) || matches!(next, IROp::Barrier(_)) || !commutes(&ops[j], next) {
+                break;
+            }
+            ops.swap(j, j + 1);
+            j += 1;
+        }
+    }
+    ops
+}
+
+// A stretch of ops the runtime runs promoted: from the first non-Clifford
+// op up to and including the measurement or reset that leaves every qubit
+// the window touched clean again. `end` is exclusive and `None` when the
+// program ends while still promoted.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Window {
+    pub start: usize,
+    pub end: Option<usize>,
+    pub non_clifford: usize,
+    // Every qubit tainted inside the window
+    pub qubits: Vec<usize>,
+}
+
+// Qubits are tainted by non-Clifford gates and by multi-qubit gates that
+// involve a tainted qubit. This only tracks the gates applied inside the
+// window, so the runtime still checks that the state really is a
+// stabilizer state before demoting.
+pub fn windows(ops: &[IROp]) -> Vec<Window> {
+    let mut found = Vec::new();
+    let mut current: Option<Window> = None;
+    let mut tainted = BTreeSet::new();
+    for (i, op) in ops.iter().enumerate() {
+        if is_non_clifford(op) {
+            let window = current.get_or_insert_with(|| Window { start: i, end: None, non_clifford: 0, qubits: Vec::new() });
+            window.non_clifford += 1;
+            window.qubits.extend(op.qubits());
+            tainted.extend(op.qubits());
+        } else if let Some(window) = current.as_mut() {
+            match op {
+                IROp::Measure(q, _) | IROp::Reset(q) => {
+                    tainted.remove(q);
+                    if tainted.is_empty() {
+                        window.end = Some(i + 1);
+                    }
+                }
+                _ => {
+                    let qs = op.qubits();
+                    if qs.iter().any(|q| tainted.contains(q)) {
+                        window.qubits.extend(&qs);
+                        tainted.extend(qs);
+                    }
+                }
+            }
+        }
+        if current.as_ref().is_some_and(|w| w.end.is_some()) {
+            found.extend(current.take());
+        }
+    }
+    found.extend(current);
+    for window in &mut found {
+        window.qubits.sort_unstable();
+        window.qubits.dedup();
+    }
+    found
+}
+
+// Put `Promote` before the first op of each window and `Demote` right after
+// the op that closes it
+pub fn mark_windows(ops: Vec<IROp>) -> Vec<IROp> {
+    let found = windows(&ops);
+    let starts: BTreeSet<usize> = found.iter().map(|w| w.start).collect();
+    let ends: BTreeSet<usize> = found.iter().filter_map(|w| w.end).collect();
+    let mut out = Vec::with_capacity(ops.len() + 2 * found.len());
+    for (i, op) in ops.into_iter().enumerate() {
+        if starts.contains(&i) {
+            out.push(IROp::Promote);
+        }
+        out.push(op);
+        if ends.contains(&(i + 1)) {
+            out.push(IROp::Demote);
+        }
+    }
+    out
+}
@@ -0,0 +1,65 @@
+// Replace Z rotations by Clifford+T circuits. Multiples of pi/4 become
+// their exact phase gates; other angles are approximated to `epsilon` by
+// `approximate_rz`, with one synthesis per distinct angle. Rotations that
+// cannot be approximated that closely are left as RZ.
+use crate::compiler::ir::{IROp, IRProgram};
+use crate::compiler::optimizer::analysis::Analyses;
+use crate::compiler::optimizer::pass_manager::Pass;
+use crate::compiler::optimizer::peephole::z_rotation;
+use crate::compiler::synthesis::approximate_rz;
+use std::collections::BTreeMap;
+
+pub struct RotationSynthesis {
+    epsilon: f64,
+    // Circuits on qubit 0, keyed by the angle's bits
+    cache: BTreeMap<u64, Option<Vec<IROp>>>,
+}
+
+impl RotationSynthesis {
+    pub fn new(epsilon: f64) -> Self {
+        Self { epsilon, cache: BTreeMap::new() }
+    }
+
+    fn block(&mut self, ops: Vec<IROp>) -> Vec<IROp> {
+        ops.into_iter().flat_map(|op| self.op(op)).collect()
+    }
+
+    fn op(&mut self, op: IROp) -> Vec<IROp> {
+        match op {
+            IROp::RZ(q, angle) => {
+                let exact = z_rotation(q, angle);
+                if !matches!(exact[..], [IROp::RZ(..)]) {
+                    return exact;
+                }
+                let epsilon = self.epsilon;
+                let circuit = self.cache.entry(angle.to_bits()).or_insert_with(|| approximate_rz(angle, epsilon).map(|nf| nf.ops(0)));
+                match circuit {
+                    Some(ops) => ops.iter().map(|op| op.map_qubits(|_| q)).collect(),
+                    None => exact,
+                }
+            }
+            IROp::Conditional { clbits, value, op } => self
+                .op(*op)
+                .into_iter()
+                .map(|op| IROp::Conditional { clbits: clbits.clone(), value, op: Box::new(op) })
+                .collect(),
+            IROp::IfElse { clbits, value, then_ops, else_ops } => {
+                let then_ops = self.block(then_ops);
+                vec![IROp::IfElse { clbits, value, then_ops, else_ops: self.block(else_ops) }]
+            }
+            IROp::While { clbits, value, body } => vec![IROp::While { clbits, value, body: self.block(body) }],
+            op => vec![op],
+        }
+    }
+}
+
+impl Pass for RotationSynthesis {
+    fn name(&self) -> &'static str {
+        "rz_synthesis"
+    }
+
+    fn run(&mut self, mut prog: IRProgram, _: &Analyses) -> IRProgram {
+        prog.ops = self.block(std::mem::take(&mut prog.ops));
+        prog
+    }
+}
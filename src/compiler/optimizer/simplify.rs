@@ -0,0 +1,50 @@
+// Drop ops that act as the identity (up to global phase): `id`, rotations
+// by multiples of 2pi and conditionals/blocks left empty by that.
+use crate::compiler::ir::{IROp, IRProgram};
+use crate::compiler::optimizer::analysis::Analyses;
+use crate::compiler::optimizer::pass_manager::Pass;
+use std::f64::consts::PI;
+
+pub struct RemoveIdentities;
+
+impl Pass for RemoveIdentities {
+    fn name(&self) -> &'static str {
+        "remove_identities"
+    }
+
+    fn run(&mut self, mut prog: IRProgram, _: &Analyses) -> IRProgram {
+        prog.ops = simplify_block(std::mem::take(&mut prog.ops));
+        prog
+    }
+}
+
+fn is_full_turn(angle: f64) -> bool {
+    let k = angle / (2.0 * PI);
+    (k - k.round()).abs() < 1e-12
+}
+
+pub fn is_identity(op: &IROp) -> bool {
+    match op {
+        IROp::I(_) => true,
+        IROp::RX(_, a) | IROp::RY(_, a) | IROp::RZ(_, a) | IROp::CPhase(_, _, a) => is_full_turn(*a),
+        _ => false,
+    }
+}
+
+fn simplify_block(ops: Vec<IROp>) -> Vec<IROp> {
+    ops.into_iter().filter_map(|op| match op {
+        IROp::Conditional { op: inner, .. } if is_identity(&inner) => None,
+        IROp::IfElse { clbits, value, then_ops, else_ops } => {
+            let (then_ops, else_ops) = (simplify_block(then_ops), simplify_block(else_ops));
+            if then_ops.is_empty() && else_ops.is_empty() {
+                None
+            } else {
+                Some(IROp::IfElse { clbits, value, then_ops, else_ops })
+            }
+        }
+        // An empty `while` body still spins (or not) on its condition, so keep it
+        IROp::While { clbits, value, body } => Some(IROp::While { clbits, value, body: simplify_block(body) }),
+        op if is_identity(&op) => None,
+        op => Some(op),
+    }).collect()
+}
@@ -0,0 +1,74 @@
+// Replace arbitrary single-qubit rotations (RX, RY, RZ, U3) by Clifford+T
+// circuits from Solovay-Kitaev, so the circuit can run on backends that
+// only know Clifford+T. Z rotations by multiples of pi/4 become their exact
+// phase gates. Multi-qubit gates should be lowered with `Decompose` first.
+use crate::compiler::ir::{IROp, IRProgram};
+use crate::compiler::optimizer::analysis::Analyses;
+use crate::compiler::optimizer::pass_manager::Pass;
+use crate::compiler::optimizer::peephole::z_rotation;
+use crate::compiler::synthesis::SolovayKitaev;
+use crate::statevector::gates::Gates;
+use std::collections::BTreeMap;
+
+pub struct SKSynthesis {
+    sk: SolovayKitaev,
+    epsilon: f64,
+    // Circuits on qubit 0, keyed by the U3 angles' bits
+    cache: BTreeMap<[u64; 3], Vec<IROp>>,
+}
+
+impl SKSynthesis {
+    // `max_t` sizes the Solovay-Kitaev net, see `SolovayKitaev::new`
+    pub fn new(max_t: usize, epsilon: f64) -> Self {
+        Self { sk: SolovayKitaev::new(max_t), epsilon, cache: BTreeMap::new() }
+    }
+
+    fn block(&mut self, ops: Vec<IROp>) -> Vec<IROp> {
+        ops.into_iter().flat_map(|op| self.op(op)).collect()
+    }
+
+    fn synthesize(&mut self, q: usize, theta: f64, phi: f64, lambda: f64) -> Vec<IROp> {
+        let (sk, epsilon) = (&self.sk, self.epsilon);
+        let key = [theta.to_bits(), phi.to_bits(), lambda.to_bits()];
+        let circuit = self.cache.entry(key).or_insert_with(|| sk.compile(&Gates::u3(theta, phi, lambda), epsilon));
+        circuit.iter().map(|op| op.map_qubits(|_| q)).collect()
+    }
+
+    fn op(&mut self, op: IROp) -> Vec<IROp> {
+        use std::f64::consts::FRAC_PI_2;
+        match op {
+            IROp::RZ(q, angle) => {
+                let exact = z_rotation(q, angle);
+                if !matches!(exact[..], [IROp::RZ(..)]) {
+                    return exact;
+                }
+                self.synthesize(q, 0.0, 0.0, angle)
+            }
+            IROp::RX(q, angle) => self.synthesize(q, angle, -FRAC_PI_2, FRAC_PI_2),
+            IROp::RY(q, angle) => self.synthesize(q, angle, 0.0, 0.0),
+            IROp::U3(q, theta, phi, lambda) => self.synthesize(q, theta, phi, lambda),
+            IROp::Conditional { clbits, value, op } => self
+                .op(*op)
+                .into_iter()
+                .map(|op| IROp::Conditional { clbits: clbits.clone(), value, op: Box::new(op) })
+                .collect(),
+            IROp::IfElse { clbits, value, then_ops, else_ops } => {
+                let then_ops = self.block(then_ops);
+                vec![IROp::IfElse { clbits, value, then_ops, else_ops: self.block(else_ops) }]
+            }
+            IROp::While { clbits, value, body } => vec![IROp::While { clbits, value, body: self.block(body) }],
+            op => vec![op],
+        }
+    }
+}
+
+impl Pass for SKSynthesis {
+    fn name(&self) -> &'static str {
+        "sk_synthesis"
+    }
+
+    fn run(&mut self, mut prog: IRProgram, _: &Analyses) -> IRProgram {
+        prog.ops = self.block(std::mem::take(&mut prog.ops));
+        prog
+    }
+}
@@ -0,0 +1,68 @@
+// ZX-calculus resynthesis. Each maximal run of gates the ZX module can
+// translate becomes a diagram, goes through `full_reduce` and is extracted
+// again. The new gates are kept only if they need fewer T gates than the
+// run's Clifford+T decomposition (or as many, in fewer gates), since
+// extraction tends to add CNOTs.
+use crate::compiler::ir::{IROp, IRProgram};
+use crate::compiler::optimizer::analysis::{t_count, Analyses};
+use crate::compiler::optimizer::pass_manager::Pass;
+use crate::compiler::optimizer::peephole::peephole_block;
+use crate::compiler::zx::graph::is_supported;
+use crate::compiler::zx::{extract_circuit, full_reduce, ZXGraph};
+
+pub struct ZXSimplify;
+
+impl Pass for ZXSimplify {
+    fn name(&self) -> &'static str {
+        "zx_simplify"
+    }
+
+    fn run(&mut self, mut prog: IRProgram, _: &Analyses) -> IRProgram {
+        prog.ops = zx_block(std::mem::take(&mut prog.ops));
+        prog
+    }
+}
+
+fn zx_block(ops: Vec<IROp>) -> Vec<IROp> {
+    let mut out = Vec::with_capacity(ops.len());
+    let mut run = Vec::new();
+    for op in ops {
+        if is_supported(&op) {
+            run.push(op);
+            continue;
+        }
+        out.extend(resynthesize(std::mem::take(&mut run)));
+        out.push(match op {
+            IROp::IfElse { clbits, value, then_ops, else_ops } => {
+                IROp::IfElse { clbits, value, then_ops: zx_block(then_ops), else_ops: zx_block(else_ops) }
+            }
+            IROp::While { clbits, value, body } => IROp::While { clbits, value, body: zx_block(body) },
+            op => op,
+        });
+    }
+    out.extend(resynthesize(run));
+    out
+}
+
+// The run rewritten through ZX, or unchanged if that does not pay off
+pub fn resynthesize(ops: Vec<IROp>) -> Vec<IROp> {
+    // Work on the qubits the run touches only
+    let mut used: Vec<usize> = ops.iter().flat_map(|op| op.qubits()).collect();
+    used.sort_unstable();
+    used.dedup();
+    let local: Vec<IROp> = ops.iter().map(|op| op.map_qubits(|q| used.binary_search(&q).unwrap())).collect();
+    let Some(mut g) = ZXGraph::from_circuit(used.len(), &local) else { return ops };
+    let before = g.t_count();
+    if before == 0 {
+        return ops;
+    }
+    full_reduce(&mut g);
+    let Some(extracted) = extract_circuit(g) else { return ops };
+    let new = peephole_block(extracted);
+    let after = t_count(&new);
+    if after < before || (after == before && new.len() < ops.len()) {
+        new.iter().map(|op| op.map_qubits(|q| used[q])).collect()
+    } else {
+        ops
+    }
+}
@@ -0,0 +1,313 @@
+use crate::compiler::ast::{BinOp, Condition, Expr, GateCall, GateDef, Program, QubitDecl, QubitRef, Stmt};
+use crate::compiler::diagnostics::{Diagnostic, DiagnosticKind};
+use crate::compiler::lexer::{tokenize, Span, Token, TokenKind};
+
+pub fn parse(src: &str) -> Result<Program, Diagnostic> {
+    let tokens = tokenize(src)?;
+    Parser::new(tokens).program()
+}
+
+pub struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    pub fn new(tokens: Vec<Token>) -> Self {
+        Self { tokens, pos: 0 }
+    }
+
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos]
+    }
+
+    fn advance(&mut self) -> Token {
+        let tok = self.tokens[self.pos].clone();
+        if tok.kind != TokenKind::Eof {
+            self.pos += 1;
+        }
+        tok
+    }
+
+    fn check(&self, kind: &TokenKind) -> bool {
+        &self.peek().kind == kind
+    }
+
+    fn eat(&mut self, kind: &TokenKind) -> bool {
+        if self.check(kind) {
+            self.advance();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn error_here(&self, expected: &str) -> Diagnostic {
+        let tok = self.peek();
+        let message = format!("expected {}, found {}", expected, tok.kind.describe());
+        Diagnostic::new(DiagnosticKind::Syntax, message, tok.span)
+    }
+
+    fn expect(&mut self, kind: TokenKind) -> Result<Token, Diagnostic> {
+        if self.check(&kind) {
+            Ok(self.advance())
+        } else {
+            Err(self.error_here(&kind.describe()))
+        }
+    }
+
+    fn ident(&mut self) -> Result<(String, Span), Diagnostic> {
+        match &self.peek().kind {
+            TokenKind::Ident(name) => {
+                let name = name.clone();
+                let span = self.advance().span;
+                Ok((name, span))
+            }
+            _ => Err(self.error_here("an identifier")),
+        }
+    }
+
+    fn index(&mut self) -> Result<(usize, Span), Diagnostic> {
+        match self.peek().kind {
+            TokenKind::Int(v) => {
+                let span = self.advance().span;
+                Ok((v as usize, span))
+            }
+            _ => Err(self.error_here("an integer")),
+        }
+    }
+
+    pub fn program(&mut self) -> Result<Program, Diagnostic> {
+        let mut statements = Vec::new();
+        while !self.check(&TokenKind::Eof) {
+            statements.push(self.statement()?);
+        }
+        Ok(Program { statements })
+    }
+
+    fn statement(&mut self) -> Result<Stmt, Diagnostic> {
+        let (keyword, start) = self.ident()?;
+        let stmt = match keyword.as_str() {
+            // Definitions end with their closing brace, not a semicolon
+            "gate" => return Ok(Stmt::GateDef(self.gate_def(start)?)),
+            "if" => {
+                let cond = self.condition()?;
+                let then_body = self.body()?;
+                let is_else = matches!(&self.peek().kind, TokenKind::Ident(kw) if kw == "else");
+                let else_body = if is_else {
+                    self.advance();
+                    self.body()?
+                } else {
+                    Vec::new()
+                };
+                return Ok(Stmt::If(cond, then_body, else_body, start));
+            }
+            "while" => {
+                let cond = self.condition()?;
+                return Ok(Stmt::While(cond, self.body()?, start));
+            }
+            "for" => {
+                let (var, _) = self.ident()?;
+                let (kw, kw_span) = self.ident()?;
+                if kw != "in" {
+                    return Err(Diagnostic::new(DiagnosticKind::Syntax, format!("expected `in`, found `{}`", kw), kw_span));
+                }
+                let first = self.expr()?;
+                self.expect(TokenKind::DotDot)?;
+                let last = self.expr()?;
+                let span = start.to(last.span());
+                return Ok(Stmt::For(var, first, last, self.body()?, span));
+            }
+            "qubit" => Stmt::QubitDecl(self.comma_list(Self::qubit_decl)?),
+            "bit" => Stmt::BitDecl(self.comma_list(Self::qubit_decl)?),
+            "measure" => {
+                let args = self.comma_list(Self::qubit_ref)?;
+                if self.eat(&TokenKind::Arrow) {
+                    let bits = self.comma_list(Self::qubit_ref)?;
+                    let span = start.to(bits.last().map(|b| b.span).unwrap_or(start));
+                    Stmt::MeasureInto(args, bits, span)
+                } else {
+                    let span = start.to(args.last().map(|a| a.span).unwrap_or(start));
+                    Stmt::Measure(args, span)
+                }
+            }
+            "reset" => {
+                let args = self.comma_list(Self::qubit_ref)?;
+                let span = start.to(args.last().map(|a| a.span).unwrap_or(start));
+                Stmt::Reset(args, span)
+            }
+            _ => Stmt::Gate(self.gate_call(keyword, start)?),
+        };
+        self.expect(TokenKind::Semicolon)?;
+        Ok(stmt)
+    }
+
+    // `{ stmt* }` or a single statement
+    fn body(&mut self) -> Result<Vec<Stmt>, Diagnostic> {
+        if !self.eat(&TokenKind::LBrace) {
+            return Ok(vec![self.statement()?]);
+        }
+        let mut stmts = Vec::new();
+        while !self.eat(&TokenKind::RBrace) {
+            if self.check(&TokenKind::Eof) {
+                return Err(self.error_here("`}`"));
+            }
+            stmts.push(self.statement()?);
+        }
+        Ok(stmts)
+    }
+
+    // (c[0])  (!c[0])  (c == 2)  (c[1] != 0)
+    fn condition(&mut self) -> Result<Condition, Diagnostic> {
+        let open = self.expect(TokenKind::LParen)?;
+        let mut negated = self.eat(&TokenKind::Bang);
+        let bits = self.qubit_ref()?;
+        let mut value = None;
+        if !negated {
+            if self.eat(&TokenKind::EqEq) {
+                value = Some(self.index()?.0 as u64);
+            } else if self.eat(&TokenKind::NotEq) {
+                value = Some(self.index()?.0 as u64);
+                negated = true;
+            }
+        }
+        let close = self.expect(TokenKind::RParen)?;
+        Ok(Condition { bits, value, negated, span: open.span.to(close.span) })
+    }
+
+    fn comma_list<T>(&mut self, item: fn(&mut Self) -> Result<T, Diagnostic>) -> Result<Vec<T>, Diagnostic> {
+        let mut items = vec![item(self)?];
+        while self.eat(&TokenKind::Comma) {
+            items.push(item(self)?);
+        }
+        Ok(items)
+    }
+
+    fn qubit_decl(&mut self) -> Result<QubitDecl, Diagnostic> {
+        let (name, span) = self.ident()?;
+        if self.eat(&TokenKind::LBracket) {
+            let (size, _) = self.index()?;
+            let close = self.expect(TokenKind::RBracket)?;
+            return Ok(QubitDecl { name, size: Some(size), span: span.to(close.span) });
+        }
+        Ok(QubitDecl { name, size: None, span })
+    }
+
+    fn qubit_ref(&mut self) -> Result<QubitRef, Diagnostic> {
+        let (name, span) = self.ident()?;
+        if self.eat(&TokenKind::LBracket) {
+            let index = self.expr()?;
+            let close = self.expect(TokenKind::RBracket)?;
+            return Ok(QubitRef { name, index: Some(index), span: span.to(close.span) });
+        }
+        Ok(QubitRef { name, index: None, span })
+    }
+
+    fn gate_call(&mut self, name: String, start: Span) -> Result<GateCall, Diagnostic> {
+        let mut params = Vec::new();
+        if self.eat(&TokenKind::LParen) {
+            if !self.check(&TokenKind::RParen) {
+                params = self.comma_list(Self::expr)?;
+            }
+            self.expect(TokenKind::RParen)?;
+        }
+        let args = if self.check(&TokenKind::Semicolon) { Vec::new() } else { self.comma_list(Self::qubit_ref)? };
+        let end = args.last().map(|a| a.span).unwrap_or(start);
+        Ok(GateCall { name, params, args, span: start.to(end) })
+    }
+
+    // gate name(theta, phi) a, b { call; call; }
+    fn gate_def(&mut self, start: Span) -> Result<GateDef, Diagnostic> {
+        let (name, _) = self.ident()?;
+        let mut params = Vec::new();
+        if self.eat(&TokenKind::LParen) {
+            if !self.check(&TokenKind::RParen) {
+                params = self.comma_list(Self::ident)?;
+            }
+            self.expect(TokenKind::RParen)?;
+        }
+        let qubits = self.comma_list(Self::ident)?;
+        let span = start.to(qubits.last().map(|q| q.1).unwrap_or(start));
+        self.expect(TokenKind::LBrace)?;
+        let mut body = Vec::new();
+        while !self.eat(&TokenKind::RBrace) {
+            if self.check(&TokenKind::Eof) {
+                return Err(self.error_here("`}`"));
+            }
+            let (callee, call_start) = self.ident()?;
+            if matches!(callee.as_str(), "qubit" | "bit" | "measure" | "reset" | "if" | "while" | "for" | "gate") {
+                return Err(Diagnostic::new(
+                    DiagnosticKind::Syntax,
+                    format!("`{}` is not allowed inside a gate body", callee),
+                    call_start,
+                ));
+            }
+            body.push(self.gate_call(callee, call_start)?);
+            self.expect(TokenKind::Semicolon)?;
+        }
+        Ok(GateDef { name, params, qubits, body, span })
+    }
+
+    // expr := term (('+' | '-') term)*
+    fn expr(&mut self) -> Result<Expr, Diagnostic> {
+        let mut lhs = self.term()?;
+        loop {
+            let op = match self.peek().kind {
+                TokenKind::Plus => BinOp::Add,
+                TokenKind::Minus => BinOp::Sub,
+                _ => return Ok(lhs),
+            };
+            self.advance();
+            let rhs = self.term()?;
+            let span = lhs.span().to(rhs.span());
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs), span);
+        }
+    }
+
+    // term := unary (('*' | '/') unary)*
+    fn term(&mut self) -> Result<Expr, Diagnostic> {
+        let mut lhs = self.unary()?;
+        loop {
+            let op = match self.peek().kind {
+                TokenKind::Star => BinOp::Mul,
+                TokenKind::Slash => BinOp::Div,
+                _ => return Ok(lhs),
+            };
+            self.advance();
+            let rhs = self.unary()?;
+            let span = lhs.span().to(rhs.span());
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs), span);
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, Diagnostic> {
+        let tok = self.peek().clone();
+        match tok.kind {
+            TokenKind::Minus => {
+                self.advance();
+                let inner = self.unary()?;
+                let span = tok.span.to(inner.span());
+                Ok(Expr::Neg(Box::new(inner), span))
+            }
+            TokenKind::LParen => {
+                self.advance();
+                let inner = self.expr()?;
+                self.expect(TokenKind::RParen)?;
+                Ok(inner)
+            }
+            TokenKind::Int(v) => {
+                self.advance();
+                Ok(Expr::Number(v as f64, tok.span))
+            }
+            TokenKind::Float(v) => {
+                self.advance();
+                Ok(Expr::Number(v, tok.span))
+            }
+            TokenKind::Ident(name) => {
+                self.advance();
+                Ok(Expr::Ident(name, tok.span))
+            }
+            _ => Err(self.error_here("a parameter value")),
+        }
+    }
+}
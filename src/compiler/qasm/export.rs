@@ -0,0 +1,226 @@
+// OpenQASM 2.0 / 3.0 emitters. Angles are printed so that the importer
+// reads back the identical f64, using `pi` fractions where that is exact.
+use crate::compiler::ir::{IROp, IRProgram, Register};
+use std::f64::consts::PI;
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExportError {
+    pub op: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ExportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "op {}: {}", self.op, self.message)
+    }
+}
+
+impl std::error::Error for ExportError {}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Version {
+    V2,
+    V3,
+}
+
+pub fn export_qasm2(prog: &IRProgram) -> Result<String, ExportError> {
+    Emitter::new(prog, Version::V2).emit()
+}
+
+pub fn export_qasm3(prog: &IRProgram) -> Result<String, ExportError> {
+    Emitter::new(prog, Version::V3).emit()
+}
+
+// Shortest text that evaluates back to exactly `angle`
+pub fn format_angle(angle: f64) -> String {
+    if angle == 0.0 {
+        return "0".to_string();
+    }
+    let sign = if angle < 0.0 { "-" } else { "" };
+    let mag = angle.abs();
+    for den in 1..=64u32 {
+        let num = (mag * den as f64 / PI).round() as u32;
+        if num == 0 {
+            continue;
+        }
+        // Mirror how the importer evaluates `n*pi/d`
+        let value = if num == 1 { PI } else { num as f64 * PI };
+        let value = if den == 1 { value } else { value / den as f64 };
+        if value == mag {
+            let num_text = if num == 1 { "pi".to_string() } else { format!("{}*pi", num) };
+            let den_text = if den == 1 { String::new() } else { format!("/{}", den) };
+            return format!("{}{}{}", sign, num_text, den_text);
+        }
+    }
+    format!("{}", angle)
+}
+
+// Registers covering the whole index space; falls back to a single
+// anonymous register when the program's layout is missing or partial.
+fn full_layout(regs: &[Register], total: usize, default_name: &str) -> Vec<Register> {
+    let covered: usize = regs.iter().map(|r| r.size).sum();
+    if covered == total && !regs.is_empty() {
+        regs.to_vec()
+    } else if total == 0 {
+        Vec::new()
+    } else {
+        vec![Register { name: default_name.to_string(), offset: 0, size: total }]
+    }
+}
+
+fn locate(regs: &[Register], index: usize) -> String {
+    let reg = regs.iter().find(|r| index >= r.offset && index < r.offset + r.size)
+        .expect("index covered by register layout");
+    format!("{}[{}]", reg.name, index - reg.offset)
+}
+
+struct Emitter<'a> {
+    prog: &'a IRProgram,
+    version: Version,
+    qregs: Vec<Register>,
+    cregs: Vec<Register>,
+}
+
+impl<'a> Emitter<'a> {
+    fn new(prog: &'a IRProgram, version: Version) -> Self {
+        Self {
+            prog,
+            version,
+            qregs: full_layout(&prog.qregs, prog.num_qubits, "q"),
+            cregs: full_layout(&prog.cregs, prog.num_clbits, "c"),
+        }
+    }
+
+    fn emit(&self) -> Result<String, ExportError> {
+        let mut out = String::new();
+        for (key, value) in &self.prog.metadata {
+            out += &format!("// {}: {}\n", key, value);
+        }
+        match self.version {
+            Version::V2 => {
+                out += "OPENQASM 2.0;\ninclude \"qelib1.inc\";\n";
+                for r in &self.qregs {
+                    out += &format!("qreg {}[{}];\n", r.name, r.size);
+                }
+                for r in &self.cregs {
+                    out += &format!("creg {}[{}];\n", r.name, r.size);
+                }
+            }
+            Version::V3 => {
+                out += "OPENQASM 3.0;\ninclude \"stdgates.inc\";\n";
+                for r in &self.qregs {
+                    out += &format!("qubit[{}] {};\n", r.size, r.name);
+                }
+                for r in &self.cregs {
+                    out += &format!("bit[{}] {};\n", r.size, r.name);
+                }
+            }
+        }
+        for (i, op) in self.prog.ops.iter().enumerate() {
+            out += &self.statement(op, 0).map_err(|message| ExportError { op: i, message })?;
+            out.push('\n');
+        }
+        Ok(out)
+    }
+
+    fn q(&self, index: usize) -> String {
+        locate(&self.qregs, index)
+    }
+
+    fn c(&self, index: usize) -> String {
+        locate(&self.cregs, index)
+    }
+
+    fn block(&self, ops: &[IROp], indent: usize) -> Result<String, String> {
+        let mut out = String::from("{\n");
+        for op in ops {
+            out += &format!("{}{}\n", "    ".repeat(indent + 1), self.statement(op, indent + 1)?);
+        }
+        out += &format!("{}}}", "    ".repeat(indent));
+        Ok(out)
+    }
+
+    fn statement(&self, op: &IROp, indent: usize) -> Result<String, String> {
+        match op {
+            IROp::IfElse { .. } | IROp::While { .. } if self.version == Version::V2 => {
+                Err("OpenQASM 2 has no block control flow".to_string())
+            }
+            IROp::IfElse { clbits, value, then_ops, else_ops } => {
+                let mut out = format!("if ({}) {}", self.condition(clbits, *value)?, self.block(then_ops, indent)?);
+                if !else_ops.is_empty() {
+                    out += &format!(" else {}", self.block(else_ops, indent)?);
+                }
+                Ok(out)
+            }
+            IROp::While { clbits, value, body } => {
+                Ok(format!("while ({}) {}", self.condition(clbits, *value)?, self.block(body, indent)?))
+            }
+            // Backend hints have no OpenQASM equivalent
+            IROp::Promote | IROp::Demote => Ok(format!("// {}", op.name())),
+            // No magic-state primitive either, so spell out the preparation
+            IROp::MagicState(q) => Ok(format!("reset {0}; h {0}; t {0};", self.q(*q))),
+            IROp::Measure(q, c) => Ok(match self.version {
+                Version::V2 => format!("measure {} -> {};", self.q(*q), self.c(*c)),
+                Version::V3 => format!("{} = measure {};", self.c(*c), self.q(*q)),
+            }),
+            // `if` guards a single statement in OpenQASM 2
+            IROp::Conditional { op, .. } if self.version == Version::V2 && matches!(**op, IROp::MagicState(_)) => {
+                Err("OpenQASM 2 cannot condition a magic-state preparation, which takes several statements".to_string())
+            }
+            IROp::Conditional { clbits, value, op } => {
+                let condition = self.condition(clbits, *value)?;
+                let body = self.statement(op, indent)?;
+                Ok(match self.version {
+                    Version::V2 => format!("if({}) {}", condition, body),
+                    Version::V3 => format!("if ({}) {{ {} }}", condition, body),
+                })
+            }
+            _ => {
+                let (name, params) = self.gate_name(op)?;
+                let qubits: Vec<String> = op.qubits().iter().map(|&q| self.q(q)).collect();
+                let params = if params.is_empty() {
+                    String::new()
+                } else {
+                    format!("({})", params.iter().map(|p| format_angle(*p)).collect::<Vec<_>>().join(", "))
+                };
+                Ok(format!("{}{} {};", name, params, qubits.join(", ")))
+            }
+        }
+    }
+
+    // Conditions must name a whole classical register (or, in QASM 3, one bit)
+    fn condition(&self, clbits: &[usize], value: u64) -> Result<String, String> {
+        if let Some(reg) = self.cregs.iter().find(|r| clbits.len() == r.size && clbits.iter().copied().eq(r.offset..r.offset + r.size)) {
+            return Ok(format!("{}=={}", reg.name, value));
+        }
+        if self.version == Version::V3 && clbits.len() == 1 {
+            let bit = self.c(clbits[0]);
+            return Ok(if value == 1 { bit } else { format!("!{}", bit) });
+        }
+        Err("condition does not cover exactly one classical register".to_string())
+    }
+
+    fn gate_name(&self, op: &IROp) -> Result<(String, Vec<f64>), String> {
+        let v3 = self.version == Version::V3;
+        let name = match op {
+            IROp::CPhase(..) => if v3 { "cp" } else { "cu1" },
+            IROp::SXdg(_) if v3 => "inv @ sx",
+            IROp::CU3(_, _, t, p, l) => {
+                return Ok(if v3 { ("cu".to_string(), vec![*t, *p, *l, 0.0]) } else { ("cu3".to_string(), vec![*t, *p, *l]) });
+            }
+            IROp::CCZ(..) if v3 => "ctrl(2) @ z",
+            IROp::MCX(controls, _) => match controls.len() {
+                1 => "cx",
+                n if v3 => return Ok((format!("ctrl({}) @ x", n), Vec::new())),
+                2 => "ccx",
+                n => return Err(format!("OpenQASM 2 has no {}-controlled X", n)),
+            },
+            IROp::CCZ(..) => return Err("OpenQASM 2 has no ccz gate".to_string()),
+            IROp::Barrier(_) | IROp::Reset(_) => op.name(),
+            op if op.is_unitary() => op.name(),
+            other => return Err(format!("`{}` cannot be exported", other.name())),
+        };
+        Ok((name.to_string(), op.params()))
+    }
+}
@@ -0,0 +1,662 @@
+// OpenQASM frontend. Produces the same IRProgram shapes as the native
+// language: qelib1 gates with a direct IROp counterpart map onto it, the rest
+// are expanded from their qelib1 definitions. The OpenQASM 3 additions live
+// in qasm3.rs.
+use crate::compiler::codegen::toffoli_ops;
+use crate::compiler::diagnostics::{Diagnostic, DiagnosticKind};
+use crate::compiler::ir::{IROp, IRProgram, Register};
+use crate::compiler::lexer::{tokenize, Span, Token, TokenKind};
+use std::collections::HashMap;
+use std::f64::consts::{PI, TAU};
+
+// Parts of qelib1.inc that have no single IROp equivalent
+const QELIB1_DEFINITIONS: &str = "
+gate cy a,b { sdg b; cx a,b; s b; }
+gate ch a,b { h b; sdg b; cx a,b; h b; t b; cx a,b; t b; h b; s b; x b; s a; }
+gate crz(lambda) a,b { u1(lambda/2) b; cx a,b; u1(-lambda/2) b; cx a,b; }
+gate crx(theta) a,b { u1(pi/2) b; cx a,b; u3(-theta/2,0,0) b; cx a,b; u3(theta/2,-pi/2,0) b; }
+gate cry(theta) a,b { ry(theta/2) b; cx a,b; ry(-theta/2) b; cx a,b; }
+gate cswap a,b,c { cx c,b; ccx a,b,c; cx c,b; }
+gate rzz(theta) a,b { cx a,b; u1(theta) b; cx a,b; }
+gate rxx(theta) a,b { u3(pi/2,theta,0) a; h b; cx a,b; u1(-theta) b; cx a,b; h b; u2(-pi,pi-theta) a; }
+";
+
+pub fn import_qasm2(src: &str) -> Result<IRProgram, Diagnostic> {
+    import_version(src, 2, false)
+}
+
+pub fn import_qasm3(src: &str) -> Result<IRProgram, Diagnostic> {
+    import_version(src, 3, false)
+}
+
+// Pick the dialect from the `OPENQASM` header, defaulting to 2.0
+pub fn import_qasm(src: &str) -> Result<IRProgram, Diagnostic> {
+    import_version(src, 2, true)
+}
+
+fn import_version(src: &str, version: u32, detect: bool) -> Result<IRProgram, Diagnostic> {
+    let mut importer = Importer::new(tokenize(src)?);
+    importer.version = version;
+    importer.program(detect)?;
+    Ok(importer.ir)
+}
+
+// Parameter expression, kept symbolic inside gate bodies
+#[derive(Clone, Debug)]
+pub enum ParamExpr {
+    Num(f64),
+    Var(String, Span),
+    Neg(Box<ParamExpr>),
+    Bin(char, Box<ParamExpr>, Box<ParamExpr>),
+    Call(String, Box<ParamExpr>, Span),
+}
+
+impl ParamExpr {
+    pub fn eval(&self, env: &HashMap<String, f64>) -> Result<f64, Diagnostic> {
+        Ok(match self {
+            ParamExpr::Num(v) => *v,
+            ParamExpr::Var(name, span) => match env.get(name) {
+                Some(v) => *v,
+                None if name == "pi" => PI,
+                None if name == "tau" => TAU,
+                None => return Err(Diagnostic::new(
+                    DiagnosticKind::UnknownIdentifier,
+                    format!("unknown parameter `{}`", name),
+                    *span,
+                )),
+            },
+            ParamExpr::Neg(inner) => -inner.eval(env)?,
+            ParamExpr::Bin(op, l, r) => {
+                let (l, r) = (l.eval(env)?, r.eval(env)?);
+                match op {
+                    '+' => l + r,
+                    '-' => l - r,
+                    '*' => l * r,
+                    '/' => l / r,
+                    _ => l.powf(r),
+                }
+            }
+            ParamExpr::Call(func, arg, span) => {
+                let x = arg.eval(env)?;
+                match func.as_str() {
+                    "sin" => x.sin(),
+                    "cos" => x.cos(),
+                    "tan" => x.tan(),
+                    "exp" => x.exp(),
+                    "ln" => x.ln(),
+                    "sqrt" => x.sqrt(),
+                    _ => return Err(Diagnostic::new(
+                        DiagnosticKind::UnknownIdentifier,
+                        format!("unknown function `{}`", func),
+                        *span,
+                    )),
+                }
+            }
+        })
+    }
+}
+
+// Operand as written: `q` or `q[3]`
+#[derive(Clone, Debug)]
+pub(super) struct Operand {
+    pub(super) name: String,
+    pub(super) index: Option<usize>,
+    pub(super) span: Span,
+}
+
+#[derive(Clone, Debug)]
+struct BodyCall {
+    name: String,
+    params: Vec<ParamExpr>,
+    args: Vec<String>,
+    span: Span,
+}
+
+#[derive(Clone, Debug)]
+struct GateDef {
+    params: Vec<String>,
+    args: Vec<String>,
+    body: Vec<BodyCall>, // barriers inside bodies are dropped
+}
+
+pub(super) struct Importer {
+    pub(super) tokens: Vec<Token>,
+    pub(super) pos: usize,
+    pub(super) ir: IRProgram,
+    gates: HashMap<String, GateDef>,
+    qelib: bool,
+    pub(super) version: u32,
+    // Compile-time integer variables (OpenQASM 3 loop indices)
+    pub(super) env: HashMap<String, f64>,
+}
+
+impl Importer {
+    fn new(tokens: Vec<Token>) -> Self {
+        Self {
+            tokens,
+            pos: 0,
+            ir: IRProgram::default(),
+            gates: HashMap::new(),
+            qelib: false,
+            version: 2,
+            env: HashMap::new(),
+        }
+    }
+
+    // Run `parse` and take back the ops it emitted
+    pub(super) fn capture(&mut self, parse: impl FnOnce(&mut Self) -> Result<(), Diagnostic>) -> Result<Vec<IROp>, Diagnostic> {
+        let start = self.ir.ops.len();
+        parse(self)?;
+        Ok(self.ir.ops.drain(start..).collect())
+    }
+
+    pub(super) fn peek(&self) -> &Token {
+        &self.tokens[self.pos]
+    }
+
+    pub(super) fn advance(&mut self) -> Token {
+        let tok = self.tokens[self.pos].clone();
+        if tok.kind != TokenKind::Eof {
+            self.pos += 1;
+        }
+        tok
+    }
+
+    pub(super) fn check(&self, kind: &TokenKind) -> bool {
+        &self.peek().kind == kind
+    }
+
+    pub(super) fn eat(&mut self, kind: &TokenKind) -> bool {
+        let found = self.check(kind);
+        if found {
+            self.advance();
+        }
+        found
+    }
+
+    pub(super) fn error_here(&self, expected: &str) -> Diagnostic {
+        let tok = self.peek();
+        Diagnostic::new(DiagnosticKind::Syntax, format!("expected {}, found {}", expected, tok.kind.describe()), tok.span)
+    }
+
+    pub(super) fn expect(&mut self, kind: TokenKind) -> Result<Span, Diagnostic> {
+        if self.check(&kind) { Ok(self.advance().span) } else { Err(self.error_here(&kind.describe())) }
+    }
+
+    pub(super) fn ident(&mut self) -> Result<(String, Span), Diagnostic> {
+        match &self.peek().kind {
+            TokenKind::Ident(name) => {
+                let name = name.clone();
+                Ok((name, self.advance().span))
+            }
+            _ => Err(self.error_here("an identifier")),
+        }
+    }
+
+    pub(super) fn int(&mut self) -> Result<usize, Diagnostic> {
+        match self.peek().kind {
+            TokenKind::Int(v) => {
+                self.advance();
+                Ok(v as usize)
+            }
+            _ => Err(self.error_here("an integer")),
+        }
+    }
+
+    fn program(&mut self, detect: bool) -> Result<(), Diagnostic> {
+        if let TokenKind::Ident(kw) = &self.peek().kind {
+            if kw == "OPENQASM" {
+                self.advance();
+                let span = self.peek().span;
+                let version = match self.advance().kind {
+                    TokenKind::Float(2.0) | TokenKind::Int(2) => 2,
+                    TokenKind::Float(3.0) | TokenKind::Int(3) => 3,
+                    _ => 0,
+                };
+                if version == 0 || (!detect && version != self.version) {
+                    let message = format!("expected OPENQASM {}.0 here", self.version);
+                    return Err(Diagnostic::new(DiagnosticKind::Unsupported, message, span));
+                }
+                self.version = version;
+                self.expect(TokenKind::Semicolon)?;
+            }
+        }
+        while !self.check(&TokenKind::Eof) {
+            self.statement()?;
+        }
+        Ok(())
+    }
+
+    pub(super) fn statement(&mut self) -> Result<(), Diagnostic> {
+        let (keyword, span) = self.ident()?;
+        if self.version >= 3 && self.statement_v3(&keyword, span)? {
+            return Ok(());
+        }
+        match keyword.as_str() {
+            "include" => {
+                let file_span = self.peek().span;
+                match self.advance().kind {
+                    TokenKind::Str(file) if file == "qelib1.inc" || file == "stdgates.inc" => self.include_qelib1(),
+                    TokenKind::Str(file) => {
+                        return Err(Diagnostic::new(DiagnosticKind::Unsupported, format!("cannot include \"{}\"", file), file_span));
+                    }
+                    _ => return Err(Diagnostic::new(DiagnosticKind::Syntax, "expected a file name", file_span)),
+                }
+                self.expect(TokenKind::Semicolon)?;
+            }
+            "qreg" | "creg" => self.register_decl(&keyword)?,
+            "gate" => self.gate_def()?,
+            "opaque" => {
+                return Err(Diagnostic::new(DiagnosticKind::Unsupported, "opaque gates cannot be simulated", span));
+            }
+            "if" => {
+                self.expect(TokenKind::LParen)?;
+                let (creg, creg_span) = self.ident()?;
+                self.expect(TokenKind::EqEq)?;
+                let value = self.int()? as u64;
+                self.expect(TokenKind::RParen)?;
+                let clbits = self.creg_bits(&creg, creg_span)?;
+                let (op_name, op_span) = self.ident()?;
+                let ops = self.capture(|this| this.quantum_op(&op_name, op_span))?;
+                let conditioned = ops.into_iter()
+                    .map(|op| IROp::Conditional { clbits: clbits.clone(), value, op: Box::new(op) });
+                self.ir.ops.extend(conditioned);
+            }
+            _ => self.quantum_op(&keyword, span)?,
+        }
+        Ok(())
+    }
+
+    pub(super) fn include_qelib1(&mut self) {
+        if self.qelib {
+            return;
+        }
+        self.qelib = true;
+        let defs = tokenize(QELIB1_DEFINITIONS).expect("built-in qelib1 definitions tokenize");
+        let mut sub = Importer::new(defs);
+        sub.qelib = true;
+        while !sub.check(&TokenKind::Eof) {
+            sub.statement().expect("built-in qelib1 definitions parse");
+        }
+        self.gates.extend(sub.gates);
+    }
+
+    pub(super) fn register_decl(&mut self, kind: &str) -> Result<(), Diagnostic> {
+        let (name, span) = self.ident()?;
+        self.expect(TokenKind::LBracket)?;
+        let size = self.int()?;
+        self.expect(TokenKind::RBracket)?;
+        self.expect(TokenKind::Semicolon)?;
+        self.add_register(kind == "qreg", name, size, span)
+    }
+
+    pub(super) fn add_register(&mut self, quantum: bool, name: String, size: usize, span: Span) -> Result<(), Diagnostic> {
+        if self.ir.qregs.iter().chain(self.ir.cregs.iter()).any(|r| r.name == name) {
+            return Err(Diagnostic::new(
+                DiagnosticKind::DuplicateDeclaration,
+                format!("register `{}` is declared more than once", name),
+                span,
+            ));
+        }
+        if quantum {
+            self.ir.qregs.push(Register { name, offset: self.ir.num_qubits, size });
+            self.ir.num_qubits += size;
+        } else {
+            self.ir.cregs.push(Register { name, offset: self.ir.num_clbits, size });
+            self.ir.num_clbits += size;
+        }
+        Ok(())
+    }
+
+    fn ident_list(&mut self) -> Result<Vec<(String, Span)>, Diagnostic> {
+        let mut items = vec![self.ident()?];
+        while self.eat(&TokenKind::Comma) {
+            items.push(self.ident()?);
+        }
+        Ok(items)
+    }
+
+    fn gate_def(&mut self) -> Result<(), Diagnostic> {
+        let (name, span) = self.ident()?;
+        let mut params = Vec::new();
+        if self.eat(&TokenKind::LParen) {
+            if !self.check(&TokenKind::RParen) {
+                params = self.ident_list()?.into_iter().map(|(p, _)| p).collect();
+            }
+            self.expect(TokenKind::RParen)?;
+        }
+        let args: Vec<String> = self.ident_list()?.into_iter().map(|(a, _)| a).collect();
+        self.expect(TokenKind::LBrace)?;
+        let mut body = Vec::new();
+        while !self.eat(&TokenKind::RBrace) {
+            let (callee, call_span) = self.ident()?;
+            let call_params = self.param_list()?;
+            let call_args = self.ident_list()?;
+            self.expect(TokenKind::Semicolon)?;
+            for (arg, arg_span) in &call_args {
+                if !args.contains(arg) {
+                    return Err(Diagnostic::new(
+                        DiagnosticKind::UndeclaredQubit,
+                        format!("`{}` is not an argument of gate `{}`", arg, name),
+                        *arg_span,
+                    ));
+                }
+            }
+            if callee == "barrier" {
+                continue;
+            }
+            if !self.is_known_gate(&callee) {
+                return Err(Diagnostic::new(DiagnosticKind::UnknownGate, format!("unknown gate `{}`", callee), call_span));
+            }
+            body.push(BodyCall { name: callee, params: call_params, args: call_args.into_iter().map(|(a, _)| a).collect(), span: call_span });
+        }
+        if self.is_known_gate(&name) {
+            return Err(Diagnostic::new(DiagnosticKind::DuplicateDeclaration, format!("gate `{}` is already defined", name), span));
+        }
+        self.gates.insert(name, GateDef { params, args, body });
+        Ok(())
+    }
+
+    pub(super) fn param_list(&mut self) -> Result<Vec<ParamExpr>, Diagnostic> {
+        let mut params = Vec::new();
+        if self.eat(&TokenKind::LParen) {
+            if !self.check(&TokenKind::RParen) {
+                params.push(self.expr()?);
+                while self.eat(&TokenKind::Comma) {
+                    params.push(self.expr()?);
+                }
+            }
+            self.expect(TokenKind::RParen)?;
+        }
+        Ok(params)
+    }
+
+    // expr := term (('+'|'-') term)*
+    pub(super) fn expr(&mut self) -> Result<ParamExpr, Diagnostic> {
+        let mut lhs = self.term()?;
+        loop {
+            let op = match self.peek().kind {
+                TokenKind::Plus => '+',
+                TokenKind::Minus => '-',
+                _ => return Ok(lhs),
+            };
+            self.advance();
+            lhs = ParamExpr::Bin(op, Box::new(lhs), Box::new(self.term()?));
+        }
+    }
+
+    // term := unary (('*'|'/') unary)*
+    fn term(&mut self) -> Result<ParamExpr, Diagnostic> {
+        let mut lhs = self.unary()?;
+        loop {
+            let op = match self.peek().kind {
+                TokenKind::Star => '*',
+                TokenKind::Slash => '/',
+                _ => return Ok(lhs),
+            };
+            self.advance();
+            lhs = ParamExpr::Bin(op, Box::new(lhs), Box::new(self.unary()?));
+        }
+    }
+
+    // unary := '-' unary | power
+    fn unary(&mut self) -> Result<ParamExpr, Diagnostic> {
+        if self.eat(&TokenKind::Minus) {
+            return Ok(ParamExpr::Neg(Box::new(self.unary()?)));
+        }
+        self.power()
+    }
+
+    // power := atom ('^' unary)?   (right associative)
+    fn power(&mut self) -> Result<ParamExpr, Diagnostic> {
+        let base = self.atom()?;
+        if self.eat(&TokenKind::Caret) {
+            return Ok(ParamExpr::Bin('^', Box::new(base), Box::new(self.unary()?)));
+        }
+        Ok(base)
+    }
+
+    fn atom(&mut self) -> Result<ParamExpr, Diagnostic> {
+        let tok = self.peek().clone();
+        match tok.kind {
+            TokenKind::Int(v) => {
+                self.advance();
+                Ok(ParamExpr::Num(v as f64))
+            }
+            TokenKind::Float(v) => {
+                self.advance();
+                Ok(ParamExpr::Num(v))
+            }
+            TokenKind::LParen => {
+                self.advance();
+                let inner = self.expr()?;
+                self.expect(TokenKind::RParen)?;
+                Ok(inner)
+            }
+            TokenKind::Ident(name) => {
+                self.advance();
+                if self.eat(&TokenKind::LParen) {
+                    let arg = self.expr()?;
+                    self.expect(TokenKind::RParen)?;
+                    return Ok(ParamExpr::Call(name, Box::new(arg), tok.span));
+                }
+                Ok(ParamExpr::Var(name, tok.span))
+            }
+            _ => Err(self.error_here("an expression")),
+        }
+    }
+
+    pub(super) fn operand(&mut self) -> Result<Operand, Diagnostic> {
+        let (name, span) = self.ident()?;
+        if self.eat(&TokenKind::LBracket) {
+            let index = self.index_expr()?;
+            let close = self.expect(TokenKind::RBracket)?;
+            return Ok(Operand { name, index: Some(index), span: span.to(close) });
+        }
+        Ok(Operand { name, index: None, span })
+    }
+
+    // Register index: an integer literal, or in OpenQASM 3 any expression
+    // over loop variables that evaluates to a non-negative integer
+    pub(super) fn index_expr(&mut self) -> Result<usize, Diagnostic> {
+        if self.version < 3 {
+            return self.int();
+        }
+        let span = self.peek().span;
+        let value = self.expr()?.eval(&self.env)?;
+        if value < 0.0 || value.fract() != 0.0 {
+            return Err(Diagnostic::new(DiagnosticKind::IndexOutOfRange, format!("index {} is not a non-negative integer", value), span));
+        }
+        Ok(value as usize)
+    }
+
+    pub(super) fn operand_list(&mut self) -> Result<Vec<Operand>, Diagnostic> {
+        let mut items = vec![self.operand()?];
+        while self.eat(&TokenKind::Comma) {
+            items.push(self.operand()?);
+        }
+        Ok(items)
+    }
+
+    pub(super) fn resolve(regs: &[Register], kind: &str, operand: &Operand) -> Result<Vec<usize>, Diagnostic> {
+        let reg = regs.iter().find(|r| r.name == operand.name).ok_or_else(|| Diagnostic::new(
+            DiagnosticKind::UndeclaredQubit,
+            format!("use of undeclared {} `{}`", kind, operand.name),
+            operand.span,
+        ))?;
+        match operand.index {
+            None => Ok((reg.offset..reg.offset + reg.size).collect()),
+            Some(i) if i < reg.size => Ok(vec![reg.offset + i]),
+            Some(i) => Err(Diagnostic::new(
+                DiagnosticKind::IndexOutOfRange,
+                format!("index {} is out of range for {} `{}` of size {}", i, kind, operand.name, reg.size),
+                operand.span,
+            )),
+        }
+    }
+
+    pub(super) fn creg_bits(&self, name: &str, span: Span) -> Result<Vec<usize>, Diagnostic> {
+        Self::resolve(&self.ir.cregs, "creg", &Operand { name: name.to_string(), index: None, span })
+    }
+
+    // Expand register operands: `cx q, r;` applies cx q[i], r[i] for each i
+    pub(super) fn broadcast(groups: Vec<Vec<usize>>, span: Span) -> Result<Vec<Vec<usize>>, Diagnostic> {
+        let width = groups.iter().map(|g| g.len()).max().unwrap_or(1);
+        if groups.iter().any(|g| g.len() != 1 && g.len() != width) {
+            return Err(Diagnostic::new(DiagnosticKind::WrongArity, "registers in one statement must have equal sizes", span));
+        }
+        Ok((0..width).map(|i| groups.iter().map(|g| if g.len() == 1 { g[0] } else { g[i] }).collect()).collect())
+    }
+
+    pub(super) fn quantum_op(&mut self, name: &str, span: Span) -> Result<(), Diagnostic> {
+        match name {
+            "measure" => {
+                let q = self.operand()?;
+                self.expect(TokenKind::Arrow)?;
+                let c = self.operand()?;
+                let end = self.expect(TokenKind::Semicolon)?;
+                let qs = Self::resolve(&self.ir.qregs, "qreg", &q)?;
+                let cs = Self::resolve(&self.ir.cregs, "creg", &c)?;
+                if qs.len() != cs.len() {
+                    return Err(Diagnostic::new(DiagnosticKind::WrongArity, "measure needs registers of equal size", span.to(end)));
+                }
+                for (q, c) in qs.into_iter().zip(cs) {
+                    self.ir.push(IROp::Measure(q, c));
+                }
+            }
+            "reset" => {
+                let q = self.operand()?;
+                self.expect(TokenKind::Semicolon)?;
+                for q in Self::resolve(&self.ir.qregs, "qreg", &q)? {
+                    self.ir.push(IROp::Reset(q));
+                }
+            }
+            "barrier" => {
+                let operands = self.operand_list()?;
+                self.expect(TokenKind::Semicolon)?;
+                let mut qs = Vec::new();
+                for op in &operands {
+                    qs.extend(Self::resolve(&self.ir.qregs, "qreg", op)?);
+                }
+                self.ir.push(IROp::Barrier(qs));
+            }
+            _ => {
+                let params = self.param_list()?;
+                let operands = self.operand_list()?;
+                let end = self.expect(TokenKind::Semicolon)?;
+                let values = params.iter().map(|p| p.eval(&self.env)).collect::<Result<Vec<_>, _>>()?;
+                let groups = operands.iter()
+                    .map(|o| Self::resolve(&self.ir.qregs, "qreg", o))
+                    .collect::<Result<Vec<_>, _>>()?;
+                for qubits in Self::broadcast(groups, span.to(end))? {
+                    if qubits.iter().enumerate().any(|(i, q)| qubits[..i].contains(q)) {
+                        return Err(Diagnostic::new(
+                            DiagnosticKind::DuplicateOperand,
+                            format!("gate `{}` is applied to the same qubit twice", name),
+                            span.to(end),
+                        ));
+                    }
+                    let mut out = Vec::new();
+                    self.apply_gate(name, &values, &qubits, span, &mut out)?;
+                    self.ir.ops.extend(out);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub(super) fn is_known_gate(&self, name: &str) -> bool {
+        native_signature(name, self.qelib).is_some() || self.gates.contains_key(name)
+    }
+
+    pub(super) fn apply_gate(&self, name: &str, p: &[f64], q: &[usize], span: Span, out: &mut Vec<IROp>) -> Result<(), Diagnostic> {
+        let (num_params, num_qubits) = if let Some(sig) = native_signature(name, self.qelib) {
+            sig
+        } else if let Some(def) = self.gates.get(name) {
+            (def.params.len(), def.args.len())
+        } else {
+            return Err(Diagnostic::new(DiagnosticKind::UnknownGate, format!("unknown gate `{}`", name), span));
+        };
+        if p.len() != num_params || q.len() != num_qubits {
+            return Err(Diagnostic::new(
+                DiagnosticKind::WrongArity,
+                format!("gate `{}` takes {} parameters and {} qubits, got {} and {}", name, num_params, num_qubits, p.len(), q.len()),
+                span,
+            ));
+        }
+        if native_signature(name, self.qelib).is_some() {
+            native_ops(name, p, q, out);
+            return Ok(());
+        }
+        let def = &self.gates[name];
+        let env: HashMap<String, f64> = def.params.iter().cloned().zip(p.iter().copied()).collect();
+        for call in &def.body {
+            let values = call.params.iter().map(|e| e.eval(&env)).collect::<Result<Vec<_>, _>>()?;
+            let qubits: Vec<usize> = call.args.iter()
+                .map(|a| q[def.args.iter().position(|d| d == a).unwrap()])
+                .collect();
+            self.apply_gate(&call.name, &values, &qubits, call.span, out)?;
+        }
+        Ok(())
+    }
+}
+
+// (parameters, qubits) for gates that lower straight to IROps
+fn native_signature(name: &str, qelib: bool) -> Option<(usize, usize)> {
+    let sig = match name {
+        "U" => return Some((3, 1)),
+        "CX" => return Some((0, 2)),
+        "id" | "u0" | "x" | "y" | "z" | "h" | "s" | "sdg" | "t" | "tdg" | "sx" | "sxdg" => (0, 1),
+        "rx" | "ry" | "rz" | "u1" | "p" | "phase" => (1, 1),
+        "u2" => (2, 1),
+        "u3" | "u" => (3, 1),
+        "cx" | "cz" | "swap" => (0, 2),
+        "cu1" | "cp" | "cphase" => (1, 2),
+        "cu3" => (3, 2),
+        "cu" => (4, 2),
+        "ccx" => (0, 3),
+        _ => return None,
+    };
+    if qelib { Some(sig) } else { None }
+}
+
+fn native_ops(name: &str, p: &[f64], q: &[usize], out: &mut Vec<IROp>) {
+    let op = match name {
+        "id" | "u0" => IROp::I(q[0]),
+        "x" => IROp::X(q[0]),
+        "y" => IROp::Y(q[0]),
+        "z" => IROp::Z(q[0]),
+        "h" => IROp::H(q[0]),
+        "s" => IROp::S(q[0]),
+        "sdg" => IROp::Sdg(q[0]),
+        "t" => IROp::T(q[0]),
+        "tdg" => IROp::Tdg(q[0]),
+        "sx" => IROp::SX(q[0]),
+        "sxdg" => IROp::SXdg(q[0]),
+        "rx" => IROp::RX(q[0], p[0]),
+        "ry" => IROp::RY(q[0], p[0]),
+        // u1 and p differ from rz only by a global phase
+        "rz" | "u1" | "p" | "phase" => IROp::RZ(q[0], p[0]),
+        "u2" => IROp::U3(q[0], PI / 2.0, p[0], p[1]),
+        "U" | "u3" | "u" => IROp::U3(q[0], p[0], p[1], p[2]),
+        "CX" | "cx" => IROp::CNOT(q[0], q[1]),
+        "cz" => IROp::CZ(q[0], q[1]),
+        "swap" => IROp::SWAP(q[0], q[1]),
+        "cu1" | "cp" | "cphase" => IROp::CPhase(q[0], q[1], p[0]),
+        "cu3" => IROp::CU3(q[0], q[1], p[0], p[1], p[2]),
+        // OpenQASM 3 cu(theta, phi, lambda, gamma): gamma is a phase on the control
+        "cu" => {
+            out.push(IROp::CU3(q[0], q[1], p[0], p[1], p[2]));
+            if p[3] != 0.0 {
+                out.push(IROp::RZ(q[0], p[3]));
+            }
+            return;
+        }
+        "ccx" => {
+            out.extend(toffoli_ops(q[0], q[1], q[2]));
+            return;
+        }
+        _ => unreachable!("`{}` has a native signature but no lowering", name),
+    };
+    out.push(op);
+}
@@ -0,0 +1,328 @@
+// OpenQASM 3 subset on top of the OpenQASM 2 importer: `qubit`/`bit`
+// declarations, `c[0] = measure q[0];`, `if`/`else` and `while` on
+// measurement results, `for` loops (unrolled at import time) and the
+// `inv @` / `ctrl @` gate modifiers.
+use crate::compiler::diagnostics::{Diagnostic, DiagnosticKind};
+use crate::compiler::codegen::endless_loop;
+use crate::compiler::ir::{conditional_block, loop_can_exit, IROp};
+use crate::compiler::lexer::{Span, TokenKind};
+use crate::compiler::qasm::import::{Importer, Operand};
+use crate::compiler::sema::MAX_UNROLL;
+
+impl Importer {
+    // Handle statements that only exist in OpenQASM 3. Returns Ok(false) to
+    // let the OpenQASM 2 rules handle `keyword`.
+    pub(super) fn statement_v3(&mut self, keyword: &str, span: Span) -> Result<bool, Diagnostic> {
+        match keyword {
+            "qubit" | "bit" => self.declaration(keyword == "qubit", span)?,
+            "for" => self.for_loop()?,
+            "while" => {
+                let (clbits, value) = self.condition()?;
+                let body = self.capture(Self::body)?;
+                if !loop_can_exit(&clbits, &body) {
+                    return Err(endless_loop(span));
+                }
+                self.ir.push(IROp::While { clbits, value, body });
+            }
+            "if" => self.if_else()?,
+            "inv" | "ctrl" | "negctrl" | "pow" if matches!(self.peek().kind, TokenKind::At | TokenKind::LParen) => {
+                self.modified_gate(keyword, span)?;
+            }
+            name if self.is_creg(name) && matches!(self.peek().kind, TokenKind::LBracket | TokenKind::Assign) => {
+                let target = self.operand_named(name.to_string(), span)?;
+                self.expect(TokenKind::Assign)?;
+                self.measure_into(target)?;
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    fn is_creg(&self, name: &str) -> bool {
+        self.ir.cregs.iter().any(|r| r.name == name)
+    }
+
+    fn operand_named(&mut self, name: String, span: Span) -> Result<Operand, Diagnostic> {
+        if self.eat(&TokenKind::LBracket) {
+            let index = self.index_expr()?;
+            let close = self.expect(TokenKind::RBracket)?;
+            return Ok(Operand { name, index: Some(index), span: span.to(close) });
+        }
+        Ok(Operand { name, index: None, span })
+    }
+
+    // `= measure q[0];` after the target bits have been parsed
+    fn measure_into(&mut self, target: Operand) -> Result<(), Diagnostic> {
+        let (kw, kw_span) = self.ident()?;
+        if kw != "measure" {
+            return Err(Diagnostic::new(DiagnosticKind::Unsupported, "only measurement results can be assigned to bits", kw_span));
+        }
+        let q = self.operand()?;
+        let end = self.expect(TokenKind::Semicolon)?;
+        let qs = Self::resolve(&self.ir.qregs, "qubit", &q)?;
+        let cs = Self::resolve(&self.ir.cregs, "bit", &target)?;
+        if qs.len() != cs.len() {
+            return Err(Diagnostic::new(DiagnosticKind::WrongArity, "measure needs registers of equal size", target.span.to(end)));
+        }
+        for (q, c) in qs.into_iter().zip(cs) {
+            self.ir.push(IROp::Measure(q, c));
+        }
+        Ok(())
+    }
+
+    // qubit q;  qubit[4] q;  bit[2] c;  bit c = measure q;
+    fn declaration(&mut self, quantum: bool, span: Span) -> Result<(), Diagnostic> {
+        let mut size = None;
+        if self.eat(&TokenKind::LBracket) {
+            size = Some(self.index_expr()?);
+            self.expect(TokenKind::RBracket)?;
+        }
+        let (name, name_span) = self.ident()?;
+        self.add_register(quantum, name.clone(), size.unwrap_or(1), span.to(name_span))?;
+        if !quantum && self.eat(&TokenKind::Assign) {
+            return self.measure_into(Operand { name, index: None, span: name_span });
+        }
+        self.expect(TokenKind::Semicolon)?;
+        Ok(())
+    }
+
+    // `{ stmt* }` or a single statement
+    fn body(&mut self) -> Result<(), Diagnostic> {
+        if self.eat(&TokenKind::LBrace) {
+            while !self.eat(&TokenKind::RBrace) {
+                if self.check(&TokenKind::Eof) {
+                    return Err(self.error_here("`}`"));
+                }
+                self.statement()?;
+            }
+            Ok(())
+        } else {
+            self.statement()
+        }
+    }
+
+    // Step over a body without emitting anything (empty loop ranges)
+    fn skip_body(&mut self) -> Result<(), Diagnostic> {
+        let mut depth = 0usize;
+        loop {
+            match self.advance().kind {
+                TokenKind::LBrace => depth += 1,
+                TokenKind::RBrace if depth == 1 => return Ok(()),
+                TokenKind::RBrace => depth = depth.saturating_sub(1),
+                TokenKind::Semicolon if depth == 0 => return Ok(()),
+                TokenKind::Eof => return Err(self.error_here("the end of the loop body")),
+                _ => {}
+            }
+        }
+    }
+
+    // `(c[0])`, `(!c[0])`, `(c == 3)`, `(c[1] != 0)`
+    fn condition(&mut self) -> Result<(Vec<usize>, u64), Diagnostic> {
+        self.expect(TokenKind::LParen)?;
+        let negated = self.eat(&TokenKind::Bang);
+        let bits = self.operand()?;
+        let clbits = Self::resolve(&self.ir.cregs, "bit", &bits)?;
+        let mut value = 1;
+        let mut not_equal = negated;
+        if !negated {
+            if self.eat(&TokenKind::EqEq) {
+                value = self.int()? as u64;
+            } else if self.eat(&TokenKind::NotEq) {
+                value = self.int()? as u64;
+                not_equal = true;
+            } else if clbits.len() != 1 {
+                return Err(Diagnostic::new(DiagnosticKind::Syntax, "compare a multi-bit register against a value", bits.span));
+            }
+        }
+        let close = self.expect(TokenKind::RParen)?;
+        if not_equal {
+            if clbits.len() != 1 || value > 1 {
+                return Err(Diagnostic::new(DiagnosticKind::Unsupported, "negated conditions are only supported on single bits", bits.span.to(close)));
+            }
+            value = 1 - value;
+        }
+        Ok((clbits, value))
+    }
+
+    fn if_else(&mut self) -> Result<(), Diagnostic> {
+        let (clbits, value) = self.condition()?;
+        let then_ops = self.capture(Self::body)?;
+        let is_else = matches!(&self.peek().kind, TokenKind::Ident(kw) if kw == "else");
+        let else_ops = if is_else {
+            self.advance();
+            self.capture(Self::body)?
+        } else {
+            Vec::new()
+        };
+        self.ir.ops.extend(conditional_block(clbits, value, then_ops, else_ops));
+        Ok(())
+    }
+
+    // for uint i in [0:3] { ... }   for i in [0:2:8] stmt;   for int i in {1, 4} { ... }
+    fn for_loop(&mut self) -> Result<(), Diagnostic> {
+        let (first, first_span) = self.ident()?;
+        let mut var = (first, first_span);
+        if self.eat(&TokenKind::LBracket) {
+            self.int()?;
+            self.expect(TokenKind::RBracket)?;
+            var = self.ident()?;
+        } else if matches!(self.peek().kind, TokenKind::Ident(ref kw) if kw != "in") {
+            var = self.ident()?;
+        }
+        let (kw, kw_span) = self.ident()?;
+        if kw != "in" {
+            return Err(Diagnostic::new(DiagnosticKind::Syntax, format!("expected `in`, found `{}`", kw), kw_span));
+        }
+        let values = self.loop_values()?;
+
+        let body_start = self.pos;
+        let shadowed = self.env.get(&var.0).copied();
+        for v in &values {
+            self.env.insert(var.0.clone(), *v);
+            self.pos = body_start;
+            self.body()?;
+        }
+        match shadowed {
+            Some(v) => self.env.insert(var.0.clone(), v),
+            None => self.env.remove(&var.0),
+        };
+        if values.is_empty() {
+            self.skip_body()?;
+        }
+        Ok(())
+    }
+
+    fn loop_values(&mut self) -> Result<Vec<f64>, Diagnostic> {
+        let span = self.peek().span;
+        if self.eat(&TokenKind::LBrace) {
+            let mut values = vec![self.expr()?.eval(&self.env)?];
+            while self.eat(&TokenKind::Comma) {
+                values.push(self.expr()?.eval(&self.env)?);
+            }
+            self.expect(TokenKind::RBrace)?;
+            return Ok(values);
+        }
+        self.expect(TokenKind::LBracket)?;
+        let mut bounds = vec![self.expr()?.eval(&self.env)?];
+        while self.eat(&TokenKind::Colon) {
+            bounds.push(self.expr()?.eval(&self.env)?);
+        }
+        let close = self.expect(TokenKind::RBracket)?;
+        // OpenQASM 3 ranges include their end point
+        let (start, step, end) = match bounds[..] {
+            [start, end] => (start, 1.0, end),
+            [start, step, end] => (start, step, end),
+            _ => return Err(Diagnostic::new(DiagnosticKind::Syntax, "expected a range `[start:end]` or `[start:step:end]`", span.to(close))),
+        };
+        if step == 0.0 {
+            return Err(Diagnostic::new(DiagnosticKind::Syntax, "range step must be non-zero", span.to(close)));
+        }
+        let iterations = ((end - start) / step).floor() + 1.0;
+        if iterations > MAX_UNROLL as f64 {
+            return Err(Diagnostic::new(
+                DiagnosticKind::Unsupported,
+                format!("loop has {} iterations, more than the unrolling limit of {}", iterations, MAX_UNROLL),
+                span.to(close),
+            ));
+        }
+        // Multiply rather than accumulate so that rounding doesn't drift
+        let count = iterations.max(0.0) as i64;
+        Ok((0..count).map(|i| start + i as f64 * step).collect())
+    }
+
+    // inv @ s q;   ctrl @ x a, b;   ctrl(2) @ z a, b, c;
+    fn modified_gate(&mut self, first: &str, span: Span) -> Result<(), Diagnostic> {
+        let mut inverse = false;
+        let mut controls = 0;
+        let mut modifier = (first.to_string(), span);
+        loop {
+            match modifier.0.as_str() {
+                "inv" => inverse = !inverse,
+                "ctrl" => {
+                    let mut n = 1;
+                    if self.eat(&TokenKind::LParen) {
+                        n = self.int()?;
+                        self.expect(TokenKind::RParen)?;
+                    }
+                    controls += n;
+                }
+                other => {
+                    return Err(Diagnostic::new(DiagnosticKind::Unsupported, format!("`{}` modifier is not supported", other), modifier.1));
+                }
+            }
+            self.expect(TokenKind::At)?;
+            modifier = self.ident()?;
+            let is_modifier = matches!(modifier.0.as_str(), "inv" | "ctrl" | "negctrl" | "pow")
+                && matches!(self.peek().kind, TokenKind::At | TokenKind::LParen);
+            if !is_modifier {
+                break;
+            }
+        }
+        let (name, name_span) = modifier;
+        let params = self.param_list()?;
+        let operands = self.operand_list()?;
+        let end = self.expect(TokenKind::Semicolon)?;
+        let values = params.iter().map(|p| p.eval(&self.env)).collect::<Result<Vec<_>, _>>()?;
+        let groups = operands.iter()
+            .map(|o| Self::resolve(&self.ir.qregs, "qubit", o))
+            .collect::<Result<Vec<_>, _>>()?;
+        let whole = span.to(end);
+        if let Some(o) = operands.iter().zip(&groups).find_map(|(o, g)| g.is_empty().then_some(o)) {
+            return Err(Diagnostic::new(DiagnosticKind::WrongArity, format!("register `{}` is empty", o.name), o.span));
+        }
+        if groups.len() < controls {
+            return Err(Diagnostic::new(DiagnosticKind::WrongArity, "not enough operands for the controls", whole));
+        }
+
+        // Register operands broadcast as for unmodified gates
+        let mut ops = Vec::new();
+        for qubits in Self::broadcast(groups, whole)? {
+            if qubits.iter().enumerate().any(|(i, q)| qubits[..i].contains(q)) {
+                return Err(Diagnostic::new(
+                    DiagnosticKind::DuplicateOperand,
+                    format!("gate `{}` is applied to the same qubit twice", name),
+                    whole,
+                ));
+            }
+            let (ctrl_qubits, targets) = qubits.split_at(controls);
+            let mut gate = Vec::new();
+            self.apply_gate(&name, &values, targets, name_span, &mut gate)?;
+            if inverse {
+                gate = gate.iter().rev()
+                    .map(|op| op.inverse().ok_or_else(|| Diagnostic::new(DiagnosticKind::Unsupported, "cannot invert a non-unitary gate", whole)))
+                    .collect::<Result<Vec<_>, _>>()?;
+            }
+            if controls > 0 {
+                gate = controlled(ctrl_qubits, &gate).ok_or_else(|| Diagnostic::new(
+                    DiagnosticKind::Unsupported,
+                    format!("`ctrl @` is only supported on x, z and cx, not `{}`", name),
+                    whole,
+                ))?;
+            }
+            ops.extend(gate);
+        }
+        self.ir.ops.extend(ops);
+        Ok(())
+    }
+}
+
+// Add `controls` to a single X/Z/CX/CZ; None for anything else
+fn controlled(controls: &[usize], ops: &[IROp]) -> Option<Vec<IROp>> {
+    let [op] = ops else { return None };
+    let with = |extra: &[usize]| -> Vec<usize> { controls.iter().chain(extra).copied().collect() };
+    let x_on = |cs: Vec<usize>, t: usize| if cs.len() == 1 { IROp::CNOT(cs[0], t) } else { IROp::MCX(cs, t) };
+    Some(match *op {
+        IROp::X(t) => vec![x_on(with(&[]), t)],
+        IROp::CNOT(c, t) => vec![x_on(with(&[c]), t)],
+        IROp::MCX(ref cs, t) => vec![IROp::MCX(with(cs), t)],
+        IROp::Z(t) | IROp::CZ(_, t) => {
+            let cs = match *op { IROp::CZ(c, _) => with(&[c]), _ => with(&[]) };
+            match cs[..] {
+                [c] => vec![IROp::CZ(c, t)],
+                [a, b] => vec![IROp::CCZ(a, b, t)],
+                _ => vec![IROp::H(t), IROp::MCX(cs, t), IROp::H(t)],
+            }
+        }
+        _ => return None,
+    })
+}
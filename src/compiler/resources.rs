@@ -0,0 +1,144 @@
+// Static resource estimates for a program, to pick a backend before a long
+// run. Gate figures come from the optimizer analyses; simulator costs are
+// the usual asymptotic ones:
+// - statevector: 2^n complex amplitudes
+// - tableau: 2n rows of 2n + 1 bits
+// - rank decomposition: about 2^(0.23 t) stabilizer terms for t magic
+//   states (Bravyi-Gosset stabilizer rank of T^{⊗t})
+use crate::compiler::ir::{IROp, IRProgram};
+use crate::compiler::optimizer::analysis::{depth, gate_counts, t_count, t_depth, unitaries};
+use crate::compiler::optimizer::decompose::{decompose_block, Basis};
+use crate::compiler::optimizer::push_back::{windows, Window};
+use crate::runtime::controller::BackendType;
+use std::collections::BTreeMap;
+use std::fmt;
+
+pub const RANK_EXPONENT: f64 = 0.23;
+
+// Bytes per statevector amplitude (two f64s)
+const AMPLITUDE_BYTES: f64 = 16.0;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResourceReport {
+    pub num_qubits: usize,
+    pub num_clbits: usize,
+    pub gate_counts: BTreeMap<&'static str, usize>,
+    pub gates: usize,
+    pub two_qubit: usize,
+    pub measurements: usize,
+    pub depth: usize,
+    pub t_count: usize,
+    pub t_depth: usize,
+    // Non-Clifford gates after lowering to Clifford+T, each of which needs
+    // one magic state: T-like gates plus arbitrary Z rotations, plus the
+    // magic states already injected by T gadgets
+    pub magic_states: usize,
+    pub windows: Vec<Window>,
+}
+
+impl ResourceReport {
+    pub fn of(prog: &IRProgram) -> Self {
+        let counts = gate_counts(&prog.ops);
+        let all = unitaries(&prog.ops);
+        let lowered = decompose_block(prog.ops.clone(), Basis::CliffordT);
+        Self {
+            num_qubits: prog.num_qubits,
+            num_clbits: prog.num_clbits,
+            gates: counts.values().sum(),
+            gate_counts: counts,
+            two_qubit: all.iter().filter(|op| op.qubits().len() == 2).count(),
+            measurements: count(&prog.ops, &|op| matches!(op, IROp::Measure(..))),
+            depth: depth(prog),
+            t_count: t_count(&prog.ops),
+            t_depth: t_depth(prog),
+            magic_states: unitaries(&lowered).iter().filter(|op| !op.is_clifford()).count()
+                + count(&prog.ops, &|op| matches!(op, IROp::MagicState(_))),
+            windows: windows(&prog.ops),
+        }
+    }
+
+    pub fn is_clifford(&self) -> bool {
+        self.magic_states == 0
+    }
+
+    pub fn statevector_amplitudes(&self) -> f64 {
+        2f64.powi(self.num_qubits as i32)
+    }
+
+    pub fn statevector_bytes(&self) -> f64 {
+        AMPLITUDE_BYTES * self.statevector_amplitudes()
+    }
+
+    pub fn tableau_bits(&self) -> usize {
+        let n = self.num_qubits;
+        2 * n * (2 * n + 1)
+    }
+
+    pub fn rank_terms(&self) -> f64 {
+        2f64.powf(RANK_EXPONENT * self.magic_states as f64)
+    }
+
+    // Clifford programs stay on the tableau. Otherwise each of the
+    // rank-decomposition terms is a stabilizer state of O(n^2) bits, so it
+    // only wins once that is smaller than the full statevector.
+    pub fn recommended_backend(&self) -> BackendType {
+        if self.is_clifford() {
+            BackendType::Tableau
+        } else if self.rank_terms() * self.tableau_bits() as f64 / 8.0 < self.statevector_bytes() {
+            BackendType::RankDecomposition
+        } else {
+            BackendType::Statevector
+        }
+    }
+}
+
+pub fn estimate_resources(prog: &IRProgram) -> ResourceReport {
+    ResourceReport::of(prog)
+}
+
+// Ops matching `pred`, including those nested in conditionals and blocks
+fn count(ops: &[IROp], pred: &dyn Fn(&IROp) -> bool) -> usize {
+    ops.iter().map(|op| {
+        if pred(op) {
+            1
+        } else {
+            op.nested_ops().into_iter().map(|inner| count(std::slice::from_ref(inner), pred)).sum()
+        }
+    }).sum()
+}
+
+fn bytes(b: f64) -> String {
+    let units = ["B", "KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+    let mut value = b;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < units.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", value, units[0])
+    } else {
+        format!("{:.1} {}", value, units[unit])
+    }
+}
+
+impl fmt::Display for ResourceReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "qubits: {}, clbits: {}", self.num_qubits, self.num_clbits)?;
+        writeln!(f, "gates: {} ({} two-qubit, {} measurements)", self.gates, self.two_qubit, self.measurements)?;
+        for (name, count) in &self.gate_counts {
+            writeln!(f, "  {}: {}", name, count)?;
+        }
+        writeln!(f, "depth: {}", self.depth)?;
+        writeln!(f, "T-count: {}, T-depth: {}, magic states: {}", self.t_count, self.t_depth, self.magic_states)?;
+        writeln!(f, "non-Clifford windows: {}", self.windows.len())?;
+        for w in &self.windows {
+            let end = w.end.map_or(String::new(), |e| e.to_string());
+            writeln!(f, "  ops {}..{}: {} non-Clifford on qubits {:?}", w.start, end, w.non_clifford, w.qubits)?;
+        }
+        writeln!(f, "statevector: 2^{} amplitudes, {}", self.num_qubits, bytes(self.statevector_bytes()))?;
+        writeln!(f, "tableau: {} bits", self.tableau_bits())?;
+        writeln!(f, "rank decomposition: ~{:.0} terms", self.rank_terms())?;
+        write!(f, "recommended backend: {:?}", self.recommended_backend())
+    }
+}
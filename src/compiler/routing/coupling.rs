@@ -0,0 +1,139 @@
+// Which pairs of physical qubits a device can run two-qubit gates on. Edges
+// are undirected; all-pairs distances are computed once for the router.
+use crate::compiler::routing::sabre::RoutingError;
+use std::collections::VecDeque;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CouplingMap {
+    num_qubits: usize,
+    // Sorted, each as (low, high)
+    edges: Vec<(usize, usize)>,
+    neighbours: Vec<Vec<usize>>,
+    // Shortest-path lengths, usize::MAX between components
+    distances: Vec<Vec<usize>>,
+}
+
+impl CouplingMap {
+    // A custom device; self-loops and out-of-range qubits are rejected
+    pub fn from_edges(num_qubits: usize, edges: &[(usize, usize)]) -> Result<Self, RoutingError> {
+        if let Some(&edge) = edges.iter().find(|&&(a, b)| a == b || a.max(b) >= num_qubits) {
+            return Err(RoutingError::InvalidEdge { edge, num_qubits });
+        }
+        Ok(Self::build(num_qubits, edges))
+    }
+
+    fn build(num_qubits: usize, edges: &[(usize, usize)]) -> Self {
+        let mut sorted: Vec<(usize, usize)> = edges.iter().map(|&(a, b)| (a.min(b), a.max(b))).collect();
+        sorted.sort_unstable();
+        sorted.dedup();
+        let mut neighbours = vec![Vec::new(); num_qubits];
+        for &(a, b) in &sorted {
+            neighbours[a].push(b);
+            neighbours[b].push(a);
+        }
+        let distances = (0..num_qubits).map(|q| bfs(&neighbours, q)).collect();
+        Self { num_qubits, edges: sorted, neighbours, distances }
+    }
+
+    // 0 - 1 - ... - (n-1)
+    pub fn line(n: usize) -> Self {
+        let edges: Vec<(usize, usize)> = (1..n).map(|q| (q - 1, q)).collect();
+        Self::build(n, &edges)
+    }
+
+    // Qubit r * cols + c at row r, column c
+    pub fn grid(rows: usize, cols: usize) -> Self {
+        let mut edges = Vec::new();
+        for r in 0..rows {
+            for c in 0..cols {
+                let q = r * cols + c;
+                if c + 1 < cols {
+                    edges.push((q, q + 1));
+                }
+                if r + 1 < rows {
+                    edges.push((q, q + cols));
+                }
+            }
+        }
+        Self::build(rows * cols, &edges)
+    }
+
+    // A heavy-hex lattice of `rows` by `cols` hexagons, as on IBM devices:
+    // a hexagonal lattice with an extra qubit on every edge, so no qubit
+    // has more than three neighbours. It is laid out as rows + 1 horizontal
+    // chains of 4 cols + 3 qubits, neighbouring chains joined by bridge
+    // qubits at every fourth position, alternately starting at 0 and 2.
+    pub fn heavy_hex(rows: usize, cols: usize) -> Self {
+        let chain = 4 * cols + 3;
+        let mut edges = Vec::new();
+        for r in 0..=rows {
+            for p in 1..chain {
+                edges.push((r * chain + p - 1, r * chain + p));
+            }
+        }
+        let mut next = (rows + 1) * chain;
+        for r in 0..rows {
+            for p in (2 * (r % 2)..chain).step_by(4) {
+                edges.push((r * chain + p, next));
+                edges.push((next, (r + 1) * chain + p));
+                next += 1;
+            }
+        }
+        Self::build(next, &edges)
+    }
+
+    pub fn num_qubits(&self) -> usize {
+        self.num_qubits
+    }
+
+    pub fn edges(&self) -> &[(usize, usize)] {
+        &self.edges
+    }
+
+    pub fn neighbours(&self, q: usize) -> &[usize] {
+        &self.neighbours[q]
+    }
+
+    pub fn distance(&self, a: usize, b: usize) -> usize {
+        self.distances[a][b]
+    }
+
+    pub fn are_adjacent(&self, a: usize, b: usize) -> bool {
+        self.distances[a][b] == 1
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.num_qubits == 0 || self.distances[0].iter().all(|&d| d != usize::MAX)
+    }
+
+    // Qubits on a shortest path from a to b, both included, or None if b
+    // is in another component
+    pub fn shortest_path(&self, a: usize, b: usize) -> Option<Vec<usize>> {
+        if self.distances[a][b] == usize::MAX {
+            return None;
+        }
+        // Every qubit on the way is in b's component, so its distance is finite
+        let mut path = vec![a];
+        let mut q = a;
+        while q != b {
+            q = *self.neighbours[q].iter().find(|&&n| self.distances[n][b] + 1 == self.distances[q][b])?;
+            path.push(q);
+        }
+        Some(path)
+    }
+}
+
+fn bfs(neighbours: &[Vec<usize>], source: usize) -> Vec<usize> {
+    let mut dist = vec![usize::MAX; neighbours.len()];
+    dist[source] = 0;
+    let mut queue = VecDeque::from([source]);
+    while let Some(q) = queue.pop_front() {
+        for &n in &neighbours[q] {
+            if dist[n] == usize::MAX {
+                dist[n] = dist[q] + 1;
+                queue.push_back(n);
+            }
+        }
+    }
+    dist
+}
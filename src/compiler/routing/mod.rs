@@ -0,0 +1,8 @@
+// Mapping programs onto devices with limited connectivity: a coupling map
+// of the physical qubits, and a SABRE router that picks an initial layout
+// and inserts SWAPs until every two-qubit gate acts on neighbours.
+pub mod coupling;
+pub mod sabre;
+
+pub use coupling::CouplingMap;
+pub use sabre::{route, RoutingError, RoutingResult};
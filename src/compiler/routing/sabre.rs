@@ -0,0 +1,368 @@
+// SABRE routing (Li, Ding & Xie, "Tackling the qubit mapping problem for
+// NISQ-era quantum devices", 2019). Ops are scheduled from the front layer
+// of their dependency graph; when no front gate acts on adjacent physical
+// qubits, the SWAP on an edge next to a front gate that most reduces the
+// distances of the front layer (and, with a lower weight, of the next few
+// two-qubit gates) is inserted. A decay on recently swapped qubits keeps the
+// router from swapping the same pair back and forth.
+//
+// The initial layout comes from routing the program's two-qubit gates
+// forwards and backwards a few times, each pass starting from the layout
+// the previous one ended with; the trivial layout is kept if that does
+// better. Blocks of IfElse and While are routed on their own and swap the
+// qubits back before they end, so every path leaves the same layout.
+use crate::compiler::ir::{IROp, IRProgram};
+use crate::compiler::routing::coupling::CouplingMap;
+use std::fmt;
+
+const EXTENDED_SET_SIZE: usize = 20;
+const EXTENDED_SET_WEIGHT: f64 = 0.5;
+const DECAY_STEP: f64 = 0.001;
+const DECAY_RESET: usize = 5;
+const LAYOUT_ITERATIONS: usize = 3;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RoutingResult {
+    // The routed program on the device's physical qubits
+    pub program: IRProgram,
+    // Physical qubit of each logical qubit before and after the program
+    pub initial_layout: Vec<usize>,
+    pub final_layout: Vec<usize>,
+    pub swaps: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum RoutingError {
+    TooManyQubits { needed: usize, available: usize },
+    Disconnected,
+    // A custom coupling map edge that is a self-loop or names a qubit the
+    // device does not have
+    InvalidEdge { edge: (usize, usize), num_qubits: usize },
+    // Gates on three or more qubits have to be decomposed first
+    MultiQubitGate { op: usize },
+}
+
+impl fmt::Display for RoutingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RoutingError::TooManyQubits { needed, available } =>
+                write!(f, "program needs {} qubits but the device has {}", needed, available),
+            RoutingError::Disconnected => write!(f, "coupling map is not connected"),
+            RoutingError::InvalidEdge { edge: (a, b), num_qubits } =>
+                write!(f, "bad coupling edge ({}, {}) for {} qubits", a, b, num_qubits),
+            RoutingError::MultiQubitGate { op } =>
+                write!(f, "op {}: gates on more than two qubits must be decomposed before routing", op),
+        }
+    }
+}
+
+impl std::error::Error for RoutingError {}
+
+// Route `prog` onto `map`. The report is also recorded in the program's
+// metadata as "swaps" and "final_layout".
+pub fn route(prog: &IRProgram, map: &CouplingMap) -> Result<RoutingResult, RoutingError> {
+    let available = map.num_qubits();
+    if prog.num_qubits > available {
+        return Err(RoutingError::TooManyQubits { needed: prog.num_qubits, available });
+    }
+    if !map.is_connected() {
+        return Err(RoutingError::Disconnected);
+    }
+    for (i, op) in prog.ops.iter().enumerate() {
+        if too_wide(op) {
+            return Err(RoutingError::MultiQubitGate { op: i });
+        }
+    }
+
+    // Layout search on the two-qubit gates alone
+    let mut skeleton = Vec::new();
+    two_qubit_gates(&prog.ops, &mut skeleton);
+    let mut reversed = skeleton.clone();
+    reversed.reverse();
+    let trivial: Vec<usize> = (0..available).collect();
+    let mut layout = trivial.clone();
+    for _ in 0..LAYOUT_ITERATIONS {
+        for ops in [&skeleton, &reversed] {
+            let mut router = Router::new(map, layout);
+            router.route_block(ops);
+            layout = router.layout;
+        }
+    }
+
+    let mut best: Option<Router> = None;
+    for start in [layout, trivial] {
+        let mut router = Router::new(map, start);
+        router.route_block(&prog.ops);
+        if best.as_ref().is_none_or(|b| router.swaps < b.swaps) {
+            best = Some(router);
+        }
+    }
+    let router = best.unwrap();
+
+    let n = prog.num_qubits;
+    let final_layout = router.layout[..n].to_vec();
+    let mut program = prog.with_ops(router.out);
+    program.num_qubits = available;
+    // Register names refer to logical qubits
+    program.qregs.clear();
+    program.metadata.insert("swaps".to_string(), router.swaps.to_string());
+    program.metadata.insert("final_layout".to_string(), format!("{:?}", final_layout));
+    Ok(RoutingResult { program, initial_layout: router.initial[..n].to_vec(), final_layout, swaps: router.swaps })
+}
+
+fn too_wide(op: &IROp) -> bool {
+    match op {
+        IROp::Barrier(_) => false,
+        IROp::Conditional { op, .. } => too_wide(op),
+        op if op.is_control_flow() => op.nested_ops().into_iter().any(too_wide),
+        op => op.qubits().len() > 2,
+    }
+}
+
+// The qubit pairs that have to be adjacent when `op` runs
+fn interaction(op: &IROp) -> Option<(usize, usize)> {
+    match op {
+        IROp::Conditional { op, .. } => interaction(op),
+        IROp::Barrier(_) => None,
+        op if op.is_control_flow() => None,
+        op => match op.qubits()[..] {
+            [a, b] => Some((a, b)),
+            _ => None,
+        },
+    }
+}
+
+fn two_qubit_gates(ops: &[IROp], out: &mut Vec<IROp>) {
+    for op in ops {
+        if op.is_control_flow() {
+            let nested: Vec<IROp> = op.nested_ops().into_iter().cloned().collect();
+            two_qubit_gates(&nested, out);
+        } else if let Some((a, b)) = interaction(op) {
+            out.push(IROp::CNOT(a, b));
+        }
+    }
+}
+
+struct Router<'a> {
+    map: &'a CouplingMap,
+    initial: Vec<usize>,
+    // Physical qubit of each logical qubit, and the inverse. Logical qubits
+    // beyond the program's are idle ancillas, so both are permutations.
+    layout: Vec<usize>,
+    logical: Vec<usize>,
+    out: Vec<IROp>,
+    swaps: usize,
+    // Swaps inserted in the blocks being routed, to undo at their end
+    inserted: Vec<(usize, usize)>,
+}
+
+impl<'a> Router<'a> {
+    fn new(map: &'a CouplingMap, layout: Vec<usize>) -> Self {
+        let mut logical = vec![0; layout.len()];
+        for (l, &p) in layout.iter().enumerate() {
+            logical[p] = l;
+        }
+        Self { map, initial: layout.clone(), layout, logical, out: Vec::new(), swaps: 0, inserted: Vec::new() }
+    }
+
+    fn swap(&mut self, p: usize, q: usize) {
+        let (a, b) = (self.logical[p], self.logical[q]);
+        self.logical.swap(p, q);
+        self.layout[a] = q;
+        self.layout[b] = p;
+        self.out.push(IROp::SWAP(p, q));
+        self.swaps += 1;
+        self.inserted.push((p, q));
+    }
+
+    fn physical(&self, (a, b): (usize, usize)) -> (usize, usize) {
+        (self.layout[a], self.layout[b])
+    }
+
+    fn executable(&self, op: &IROp) -> bool {
+        interaction(op).is_none_or(|pair| {
+            let (p, q) = self.physical(pair);
+            self.map.are_adjacent(p, q)
+        })
+    }
+
+    fn emit(&mut self, op: &IROp) {
+        match op {
+            IROp::IfElse { clbits, value, then_ops, else_ops } => {
+                let then_ops = self.route_nested(then_ops);
+                let else_ops = self.route_nested(else_ops);
+                self.out.push(IROp::IfElse { clbits: clbits.clone(), value: *value, then_ops, else_ops });
+            }
+            IROp::While { clbits, value, body } => {
+                let body = self.route_nested(body);
+                self.out.push(IROp::While { clbits: clbits.clone(), value: *value, body });
+            }
+            op => {
+                let layout = &self.layout;
+                self.out.push(op.map_qubits(|q| layout[q]));
+            }
+        }
+    }
+
+    // Route a block into its own op list, swapping back to the layout it
+    // started from
+    fn route_nested(&mut self, ops: &[IROp]) -> Vec<IROp> {
+        let outer = std::mem::take(&mut self.out);
+        let mark = self.inserted.len();
+        self.route_block(ops);
+        let undo: Vec<(usize, usize)> = self.inserted[mark..].iter().rev().copied().collect();
+        for (p, q) in undo {
+            self.swap(p, q);
+        }
+        self.inserted.truncate(mark);
+        std::mem::replace(&mut self.out, outer)
+    }
+
+    fn route_block(&mut self, ops: &[IROp]) {
+        let dag = Dag::new(ops, self.layout.len());
+        let mut pending = dag.predecessors.clone();
+        let mut front: Vec<usize> = (0..ops.len()).filter(|&i| pending[i] == 0).collect();
+        let mut decay = vec![1.0; self.layout.len()];
+        let mut stalled = 0;
+        while !front.is_empty() {
+            let (ready, blocked): (Vec<usize>, Vec<usize>) = front.iter().partition(|&&i| self.executable(&ops[i]));
+            if !ready.is_empty() {
+                front = blocked;
+                for i in ready {
+                    self.emit(&ops[i]);
+                    for &j in &dag.successors[i] {
+                        pending[j] -= 1;
+                        if pending[j] == 0 {
+                            front.push(j);
+                        }
+                    }
+                }
+                decay.fill(1.0);
+                stalled = 0;
+                continue;
+            }
+
+            // Bring the first gate together along a shortest path if the
+            // heuristic keeps going in circles
+            if stalled > 10 * self.layout.len() {
+                let (p, q) = self.physical(interaction(&ops[front[0]]).unwrap());
+                // `route` rejects disconnected maps up front
+                let path = self.map.shortest_path(p, q).expect("coupling map is connected");
+                for w in path[..path.len() - 1].windows(2) {
+                    self.swap(w[0], w[1]);
+                }
+                continue;
+            }
+
+            let front_pairs: Vec<(usize, usize)> = front.iter().filter_map(|&i| interaction(&ops[i])).collect();
+            let extended = dag.extended_set(ops, &front, &pending);
+            let mut candidates: Vec<(usize, usize)> = Vec::new();
+            for &pair in &front_pairs {
+                let (p, q) = self.physical(pair);
+                for r in [p, q] {
+                    for &n in self.map.neighbours(r) {
+                        candidates.push((r.min(n), r.max(n)));
+                    }
+                }
+            }
+            candidates.sort_unstable();
+            candidates.dedup();
+            let (p, q) = candidates.into_iter()
+                .map(|(p, q)| (self.score(p, q, &front_pairs, &extended, &decay), (p, q)))
+                .min_by(|a, b| a.0.total_cmp(&b.0))
+                .map(|(_, edge)| edge)
+                .expect("a blocked gate has neighbours to swap with");
+            self.swap(p, q);
+            decay[p] += DECAY_STEP;
+            decay[q] += DECAY_STEP;
+            stalled += 1;
+            if stalled.is_multiple_of(DECAY_RESET) {
+                decay.fill(1.0);
+            }
+        }
+    }
+
+    // Distances of the front layer and the extended set after swapping the
+    // physical qubits p and q
+    fn score(&self, p: usize, q: usize, front: &[(usize, usize)], extended: &[(usize, usize)], decay: &[f64]) -> f64 {
+        let moved = |x: usize| if x == p { q } else if x == q { p } else { x };
+        let cost = |pairs: &[(usize, usize)]| {
+            let total: usize = pairs.iter().map(|&pair| {
+                let (a, b) = self.physical(pair);
+                self.map.distance(moved(a), moved(b))
+            }).sum();
+            total as f64 / pairs.len().max(1) as f64
+        };
+        decay[p].max(decay[q]) * (cost(front) + EXTENDED_SET_WEIGHT * cost(extended))
+    }
+}
+
+// Dependencies between the ops of one block: an op waits for the previous
+// ops on any of its qubits or classical bits. Markers and empty barriers
+// touch every qubit.
+struct Dag {
+    predecessors: Vec<usize>,
+    successors: Vec<Vec<usize>>,
+}
+
+impl Dag {
+    fn new(ops: &[IROp], num_qubits: usize) -> Self {
+        let mut predecessors = vec![0; ops.len()];
+        let mut successors = vec![Vec::new(); ops.len()];
+        let mut last_qubit: Vec<Option<usize>> = vec![None; num_qubits];
+        let mut last_clbit: Vec<Option<usize>> = Vec::new();
+        for (i, op) in ops.iter().enumerate() {
+            let qubits = match op {
+                IROp::Promote | IROp::Demote => (0..num_qubits).collect(),
+                IROp::Barrier(qs) if qs.is_empty() => (0..num_qubits).collect(),
+                op => op.qubits(),
+            };
+            let mut before: Vec<usize> = Vec::new();
+            for q in qubits {
+                before.extend(last_qubit[q]);
+                last_qubit[q] = Some(i);
+            }
+            // Control flow can list a bit twice, as condition and in its body
+            let mut clbits = op.clbits();
+            clbits.sort_unstable();
+            clbits.dedup();
+            for c in clbits {
+                if c >= last_clbit.len() {
+                    last_clbit.resize(c + 1, None);
+                }
+                before.extend(last_clbit[c]);
+                last_clbit[c] = Some(i);
+            }
+            before.sort_unstable();
+            before.dedup();
+            predecessors[i] = before.len();
+            for j in before {
+                successors[j].push(i);
+            }
+        }
+        Self { predecessors, successors }
+    }
+
+    // The next few two-qubit gates behind the front layer, in breadth-first
+    // order
+    fn extended_set(&self, ops: &[IROp], front: &[usize], pending: &[usize]) -> Vec<(usize, usize)> {
+        let mut pending = pending.to_vec();
+        let mut queue: std::collections::VecDeque<usize> = front.iter().copied().collect();
+        let mut out = Vec::new();
+        while let Some(i) = queue.pop_front() {
+            for &j in &self.successors[i] {
+                pending[j] -= 1;
+                if pending[j] == 0 {
+                    if let Some(pair) = interaction(&ops[j]) {
+                        out.push(pair);
+                        if out.len() == EXTENDED_SET_SIZE {
+                            return out;
+                        }
+                    }
+                    queue.push_back(j);
+                }
+            }
+        }
+        out
+    }
+}
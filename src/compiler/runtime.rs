@@ -0,0 +1,25 @@
+use crate::compiler::codegen::compile;
+use crate::compiler::diagnostics::{render_all, Diagnostic};
+use crate::runtime::controller::{RuntimeController, RuntimeError};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum RunError {
+    Compile(Vec<Diagnostic>),
+    Runtime(RuntimeError),
+}
+
+// Compile native-language source and execute it, returning the declared
+// bits followed by one bit per anonymous `measure q;` in program order.
+pub fn try_compile_and_run(src: &str) -> Result<Vec<bool>, RunError> {
+    let program = compile(src).map_err(RunError::Compile)?;
+    RuntimeController::run(&program).map_err(RunError::Runtime)
+}
+
+// Like `try_compile_and_run`, but panics with rendered diagnostics on error
+pub fn compile_and_run(src: &str) -> Vec<bool> {
+    match try_compile_and_run(src) {
+        Ok(bits) => bits,
+        Err(RunError::Compile(errors)) => panic!("{}", render_all(&errors, "<input>", src)),
+        Err(RunError::Runtime(error)) => panic!("runtime error: {}", error),
+    }
+}
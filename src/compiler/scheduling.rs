@@ -0,0 +1,206 @@
+// Arrange a program into moments: layers of ops on disjoint qubits that can
+// be applied at once. Ops are placed as soon as possible (ASAP) or as late
+// as possible (ALAP) given per-gate durations, counted in whatever unit the
+// caller uses (layers by default, or ns / device ticks).
+//
+// Ops take their qubits and clbits for their whole duration. Barriers line
+// their qubits up and `Promote`/`Demote` markers fence the whole program;
+// both take no time and, like other zero-duration ops, get a moment of
+// their own ahead of the ops starting at the same time. A block takes as
+// long as its longest branch (one iteration for `while`).
+use crate::compiler::ir::{IROp, IRProgram};
+use crate::compiler::optimizer::analysis::{AnalysisKind, Analyses};
+use crate::compiler::optimizer::pass_manager::Pass;
+use std::collections::BTreeMap;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Strategy {
+    Asap,
+    Alap,
+}
+
+// Gate durations by `IROp::name`, falling back to `default`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Durations {
+    default: u64,
+    gates: BTreeMap<&'static str, u64>,
+}
+
+impl Durations {
+    pub fn uniform(default: u64) -> Self {
+        Self { default, gates: BTreeMap::new() }
+    }
+
+    pub fn with(mut self, name: &'static str, duration: u64) -> Self {
+        self.gates.insert(name, duration);
+        self
+    }
+
+    pub fn of(&self, op: &IROp) -> u64 {
+        match op {
+            IROp::Barrier(_) | IROp::Promote | IROp::Demote => 0,
+            IROp::Conditional { op, .. } => self.of(op),
+            IROp::IfElse { then_ops, else_ops, .. } => self.block(then_ops).max(self.block(else_ops)),
+            IROp::While { body, .. } => self.block(body),
+            op => *self.gates.get(op.name()).unwrap_or(&self.default),
+        }
+    }
+
+    fn block(&self, ops: &[IROp]) -> u64 {
+        let (num_qubits, num_clbits) = block_size(ops);
+        asap(ops, num_qubits, num_clbits, self).into_iter().map(|(start, duration)| start + duration).max().unwrap_or(0)
+    }
+}
+
+impl Default for Durations {
+    fn default() -> Self {
+        Self::uniform(1)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Moment {
+    pub start: u64,
+    // Of the longest op in the moment
+    pub duration: u64,
+    pub ops: Vec<IROp>,
+}
+
+// A qubit sitting between two ops
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Idle {
+    pub qubit: usize,
+    pub start: u64,
+    pub duration: u64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Schedule {
+    pub num_qubits: usize,
+    pub moments: Vec<Moment>,
+    pub makespan: u64,
+    durations: Durations,
+}
+
+impl Schedule {
+    // The ops moment by moment, a valid ordering of the original program
+    pub fn ops(&self) -> Vec<IROp> {
+        self.moments.iter().flat_map(|m| m.ops.iter().cloned()).collect()
+    }
+
+    // Gaps between consecutive ops on each qubit. Time before a qubit's
+    // first op and after its last one is not counted.
+    pub fn idle_periods(&self) -> Vec<Idle> {
+        let mut busy = vec![Vec::new(); self.num_qubits];
+        for m in &self.moments {
+            for op in &m.ops {
+                for q in op.qubits() {
+                    busy[q].push((m.start, m.start + self.durations.of(op)));
+                }
+            }
+        }
+        let mut idle = Vec::new();
+        for (qubit, mut intervals) in busy.into_iter().enumerate() {
+            intervals.sort_unstable();
+            let mut free = None;
+            for (start, end) in intervals {
+                if let Some(f) = free {
+                    if start > f {
+                        idle.push(Idle { qubit, start: f, duration: start - f });
+                    }
+                }
+                free = Some(free.map_or(end, |f: u64| f.max(end)));
+            }
+        }
+        idle.sort_by_key(|i| (i.start, i.qubit));
+        idle
+    }
+}
+
+pub fn schedule(prog: &IRProgram, strategy: Strategy, durations: &Durations) -> Schedule {
+    let (n, m) = (prog.num_qubits, prog.num_clbits);
+    let times: Vec<(u64, u64)> = match strategy {
+        Strategy::Asap => asap(&prog.ops, n, m, durations),
+        // Dependencies are symmetric, so ALAP is ASAP on the reversed
+        // program, read backwards from the end
+        Strategy::Alap => {
+            let reversed: Vec<IROp> = prog.ops.iter().rev().cloned().collect();
+            let mut times = asap(&reversed, n, m, durations);
+            times.reverse();
+            let makespan = times.iter().map(|&(s, d)| s + d).max().unwrap_or(0);
+            times.into_iter().map(|(s, d)| (makespan - s - d, d)).collect()
+        }
+    };
+    let makespan = times.iter().map(|&(s, d)| s + d).max().unwrap_or(0);
+
+    // Zero-duration ops go first among those starting together so they keep
+    // their place relative to the ops after them
+    let mut order: Vec<usize> = (0..prog.ops.len()).collect();
+    order.sort_by_key(|&i| (times[i].0, times[i].1 != 0, i));
+    let mut moments: Vec<Moment> = Vec::new();
+    for i in order {
+        let (start, duration) = times[i];
+        match moments.last_mut() {
+            Some(last) if duration != 0 && last.duration != 0 && last.start == start => {
+                last.duration = last.duration.max(duration);
+                last.ops.push(prog.ops[i].clone());
+            }
+            _ => moments.push(Moment { start, duration, ops: vec![prog.ops[i].clone()] }),
+        }
+    }
+    Schedule { num_qubits: n, moments, makespan, durations: durations.clone() }
+}
+
+// (start, duration) of every op
+fn asap(ops: &[IROp], num_qubits: usize, num_clbits: usize, durations: &Durations) -> Vec<(u64, u64)> {
+    let mut qubit_free = vec![0; num_qubits];
+    let mut clbit_free = vec![0; num_clbits];
+    ops.iter().map(|op| {
+        let (qs, cs) = match op {
+            IROp::Promote | IROp::Demote => ((0..num_qubits).collect(), (0..num_clbits).collect()),
+            op => (op.qubits(), op.clbits()),
+        };
+        let start = qs.iter().map(|&q| qubit_free[q]).chain(cs.iter().map(|&c| clbit_free[c])).max().unwrap_or(0);
+        let duration = durations.of(op);
+        for q in qs {
+            qubit_free[q] = start + duration;
+        }
+        for c in cs {
+            clbit_free[c] = start + duration;
+        }
+        (start, duration)
+    }).collect()
+}
+
+fn block_size(ops: &[IROp]) -> (usize, usize) {
+    let qubits = ops.iter().flat_map(|op| op.qubits()).max().map_or(0, |q| q + 1);
+    let clbits = ops.iter().flat_map(|op| op.clbits()).max().map_or(0, |c| c + 1);
+    (qubits, clbits)
+}
+
+// Reorders a program moment by moment
+pub struct Reschedule {
+    strategy: Strategy,
+    durations: Durations,
+}
+
+impl Reschedule {
+    pub fn new(strategy: Strategy, durations: Durations) -> Self {
+        Self { strategy, durations }
+    }
+}
+
+impl Pass for Reschedule {
+    fn name(&self) -> &'static str {
+        "reschedule"
+    }
+
+    fn preserves(&self) -> &[AnalysisKind] {
+        &[AnalysisKind::GateCounts, AnalysisKind::Depth]
+    }
+
+    fn run(&mut self, mut prog: IRProgram, _: &Analyses) -> IRProgram {
+        prog.ops = schedule(&prog, self.strategy, &self.durations).ops();
+        prog
+    }
+}
@@ -0,0 +1,518 @@
+// Semantic checks on the parsed AST: every gate must exist and be applied
+// with the right number of parameters and operands, every qubit must be
+// declared exactly once before it is used. User-defined gates are checked
+// against their formal parameters and may only call gates defined earlier.
+use crate::compiler::ast::{BinOp, Condition, Expr, GateCall, GateDef, Program, QubitRef, Stmt};
+use crate::compiler::diagnostics::{Diagnostic, DiagnosticKind};
+use crate::compiler::lexer::Span;
+use std::collections::HashMap;
+
+pub struct GateSig {
+    pub name: &'static str,
+    pub num_params: usize,
+    pub num_qubits: usize,
+}
+
+const fn sig(name: &'static str, num_params: usize, num_qubits: usize) -> GateSig {
+    GateSig { name, num_params, num_qubits }
+}
+
+pub const BUILTIN_GATES: &[GateSig] = &[
+    sig("id", 0, 1),
+    sig("h", 0, 1),
+    sig("x", 0, 1),
+    sig("y", 0, 1),
+    sig("z", 0, 1),
+    sig("s", 0, 1),
+    sig("sdg", 0, 1),
+    sig("t", 0, 1),
+    sig("tdg", 0, 1),
+    sig("rx", 1, 1),
+    sig("ry", 1, 1),
+    sig("rz", 1, 1),
+    sig("cnot", 0, 2),
+    sig("cx", 0, 2),
+    sig("cz", 0, 2),
+    sig("swap", 0, 2),
+    sig("toffoli", 0, 3),
+    sig("ccx", 0, 3),
+];
+
+pub fn builtin_gate(name: &str) -> Option<&'static GateSig> {
+    BUILTIN_GATES.iter().find(|g| g.name == name)
+}
+
+// `for` loops are unrolled at compile time, up to this many iterations each
+pub const MAX_UNROLL: i64 = 1_000_000;
+
+// Constants usable inside gate parameters
+pub fn constant(name: &str) -> Option<f64> {
+    match name {
+        "pi" => Some(std::f64::consts::PI),
+        "tau" => Some(std::f64::consts::TAU),
+        _ => None,
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct QubitSymbol {
+    pub offset: usize,       // index of the first qubit (or bit)
+    pub size: Option<usize>, // Some(n) for registers
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SymbolTable {
+    pub qubits: HashMap<String, QubitSymbol>,
+    pub num_qubits: usize,
+    pub bits: HashMap<String, QubitSymbol>,
+    pub num_clbits: usize,
+    pub gates: HashMap<String, GateDef>,
+    // Loop variables in scope while a `for` body is checked or unrolled
+    pub consts: HashMap<String, f64>,
+}
+
+impl SymbolTable {
+    pub fn declare(&mut self, name: &str, size: Option<usize>, span: Span) -> Result<(), Diagnostic> {
+        self.check_fresh(name, "qubit", span)?;
+        self.qubits.insert(name.to_string(), QubitSymbol { offset: self.num_qubits, size, span });
+        self.num_qubits += size.unwrap_or(1);
+        Ok(())
+    }
+
+    pub fn declare_bits(&mut self, name: &str, size: Option<usize>, span: Span) -> Result<(), Diagnostic> {
+        self.check_fresh(name, "bit", span)?;
+        self.bits.insert(name.to_string(), QubitSymbol { offset: self.num_clbits, size, span });
+        self.num_clbits += size.unwrap_or(1);
+        Ok(())
+    }
+
+    // Qubits and bits share one namespace
+    fn check_fresh(&self, name: &str, kind: &str, span: Span) -> Result<(), Diagnostic> {
+        match self.qubits.get(name).or_else(|| self.bits.get(name)) {
+            Some(prev) => Err(Diagnostic::new(
+                DiagnosticKind::DuplicateDeclaration,
+                format!("{} `{}` is declared more than once", kind, name),
+                span,
+            ).with_note("first declared here", prev.span)),
+            None => Ok(()),
+        }
+    }
+
+    // Resolve a reference to qubit indices. A bare register name expands to
+    // the whole register when `allow_register` is set (e.g. `measure r;`).
+    pub fn resolve(&self, qref: &QubitRef, allow_register: bool) -> Result<Vec<usize>, Diagnostic> {
+        let index = self.index(qref)?;
+        resolve_in(&self.qubits, "qubit", DiagnosticKind::UndeclaredQubit, qref, index, allow_register)
+    }
+
+    // Same as `resolve`, for classical bits
+    pub fn resolve_bits(&self, bref: &QubitRef, allow_register: bool) -> Result<Vec<usize>, Diagnostic> {
+        let index = self.index(bref)?;
+        resolve_in(&self.bits, "bit", DiagnosticKind::UndeclaredBit, bref, index, allow_register)
+    }
+
+    // Fold an index expression to a non-negative integer
+    fn index(&self, qref: &QubitRef) -> Result<Option<usize>, Diagnostic> {
+        let Some(expr) = &qref.index else { return Ok(None) };
+        let value = self.fold(expr)?;
+        if value < 0.0 || value.fract() != 0.0 {
+            return Err(Diagnostic::new(
+                DiagnosticKind::IndexOutOfRange,
+                format!("index of `{}` evaluates to {}, which is not a non-negative integer", qref.name, value),
+                expr.span(),
+            ));
+        }
+        Ok(Some(value as usize))
+    }
+
+    // Check and evaluate a constant expression over the loop variables in scope
+    pub fn fold(&self, expr: &Expr) -> Result<f64, Diagnostic> {
+        let scope: Vec<String> = self.consts.keys().cloned().collect();
+        check_expr(expr, &scope)?;
+        Ok(eval_expr(expr, &self.consts))
+    }
+
+    // The integer values of `for i in first..last`. The iteration count is
+    // checked on the folded values, so huge bounds cannot overflow.
+    pub fn loop_range(&self, first: &Expr, last: &Expr) -> Result<std::ops::Range<i64>, Diagnostic> {
+        let mut bounds = [0f64; 2];
+        for (bound, expr) in bounds.iter_mut().zip([first, last]) {
+            let value = self.fold(expr)?;
+            if value.fract() != 0.0 {
+                return Err(Diagnostic::new(
+                    DiagnosticKind::InvalidValue,
+                    format!("loop bound evaluates to {}, which is not an integer", value),
+                    expr.span(),
+                ));
+            }
+            *bound = value;
+        }
+        let iterations = bounds[1] - bounds[0];
+        if iterations > MAX_UNROLL as f64 {
+            return Err(Diagnostic::new(
+                DiagnosticKind::Unsupported,
+                format!("loop has {} iterations, more than the unrolling limit of {}", iterations, MAX_UNROLL),
+                first.span().to(last.span()),
+            ));
+        }
+        if iterations <= 0.0 {
+            return Ok(0..0);
+        }
+        if bounds[0] < i64::MIN as f64 || bounds[1] > i64::MAX as f64 {
+            return Err(Diagnostic::new(
+                DiagnosticKind::Unsupported,
+                "loop bounds do not fit in a 64-bit integer",
+                first.span().to(last.span()),
+            ));
+        }
+        Ok(bounds[0] as i64..bounds[1] as i64)
+    }
+
+    // (parameters, qubits) of a built-in or user-defined gate
+    pub fn gate_signature(&self, name: &str) -> Option<(usize, usize)> {
+        match builtin_gate(name) {
+            Some(g) => Some((g.num_params, g.num_qubits)),
+            None => self.gates.get(name).map(|def| (def.params.len(), def.qubits.len())),
+        }
+    }
+}
+
+fn resolve_in(
+    symbols: &HashMap<String, QubitSymbol>,
+    kind: &str,
+    undeclared: DiagnosticKind,
+    qref: &QubitRef,
+    index: Option<usize>,
+    allow_register: bool,
+) -> Result<Vec<usize>, Diagnostic> {
+    let sym = symbols.get(&qref.name).ok_or_else(|| Diagnostic::new(
+        undeclared,
+        format!("use of undeclared {} `{}`", kind, qref.name),
+        qref.span,
+    ))?;
+    match (sym.size, index) {
+        (None, None) => Ok(vec![sym.offset]),
+        (None, Some(_)) => Err(Diagnostic::new(
+            DiagnosticKind::IndexOutOfRange,
+            format!("`{}` is a single {} and cannot be indexed", qref.name, kind),
+            qref.span,
+        ).with_note("declared here", sym.span)),
+        (Some(size), Some(i)) if i < size => Ok(vec![sym.offset + i]),
+        (Some(size), Some(i)) => Err(Diagnostic::new(
+            DiagnosticKind::IndexOutOfRange,
+            format!("index {} is out of range for register `{}` of size {}", i, qref.name, size),
+            qref.span,
+        ).with_note("declared here", sym.span)),
+        (Some(size), None) if allow_register => Ok((sym.offset..sym.offset + size).collect()),
+        (Some(_), None) => Err(Diagnostic::new(
+            DiagnosticKind::WrongArity,
+            format!("register `{}` must be indexed here", qref.name),
+            qref.span,
+        )),
+    }
+}
+
+// `scope` holds the parameter names of the enclosing gate definition
+pub fn check_expr(expr: &Expr, scope: &[String]) -> Result<(), Diagnostic> {
+    match expr {
+        Expr::Number(..) => Ok(()),
+        Expr::Ident(name, span) => {
+            if scope.contains(name) || constant(name).is_some() {
+                Ok(())
+            } else {
+                Err(Diagnostic::new(
+                    DiagnosticKind::UnknownIdentifier,
+                    format!("unknown identifier `{}` in constant expression", name),
+                    *span,
+                ))
+            }
+        }
+        Expr::Neg(inner, _) => check_expr(inner, scope),
+        Expr::Binary(_, lhs, rhs, _) => {
+            check_expr(lhs, scope)?;
+            check_expr(rhs, scope)
+        }
+    }
+}
+
+// Evaluate a checked expression; `env` holds gate parameters or loop variables
+pub fn eval_expr(expr: &Expr, env: &HashMap<String, f64>) -> f64 {
+    match expr {
+        Expr::Number(v, _) => *v,
+        Expr::Ident(name, _) => env.get(name).copied().or_else(|| constant(name)).unwrap_or(0.0),
+        Expr::Neg(inner, _) => -eval_expr(inner, env),
+        Expr::Binary(op, lhs, rhs, _) => {
+            let (a, b) = (eval_expr(lhs, env), eval_expr(rhs, env));
+            match op {
+                BinOp::Add => a + b,
+                BinOp::Sub => a - b,
+                BinOp::Mul => a * b,
+                BinOp::Div => a / b,
+            }
+        }
+    }
+}
+
+fn plural(n: usize, word: &str) -> String {
+    if n == 1 { format!("{} {}", n, word) } else { format!("{} {}s", n, word) }
+}
+
+// The gate exists and gets the right number of parameters and operands
+fn check_signature(call: &GateCall, symbols: &SymbolTable, scope: &[String]) -> Result<(), Diagnostic> {
+    let (num_params, num_qubits) = symbols.gate_signature(&call.name).ok_or_else(|| {
+        let name_span = Span { end: call.span.start + call.name.len(), ..call.span };
+        Diagnostic::new(DiagnosticKind::UnknownGate, format!("unknown gate `{}`", call.name), name_span)
+    })?;
+    if call.params.len() != num_params {
+        return Err(Diagnostic::new(
+            DiagnosticKind::WrongArity,
+            format!("gate `{}` takes {}, got {}",
+                call.name, plural(num_params, "parameter"), call.params.len()),
+            call.span,
+        ));
+    }
+    if call.args.len() != num_qubits {
+        return Err(Diagnostic::new(
+            DiagnosticKind::WrongArity,
+            format!("gate `{}` acts on {}, got {}",
+                call.name, plural(num_qubits, "qubit"), call.args.len()),
+            call.span,
+        ));
+    }
+    for p in &call.params {
+        check_expr(p, scope)?;
+    }
+    Ok(())
+}
+
+fn duplicate_operand(arg: &QubitRef, gate: &str) -> Diagnostic {
+    Diagnostic::new(
+        DiagnosticKind::DuplicateOperand,
+        format!("qubit `{}` is used more than once in `{}`", arg.name, gate),
+        arg.span,
+    )
+}
+
+pub fn check_gate(call: &GateCall, symbols: &SymbolTable) -> Result<Vec<usize>, Diagnostic> {
+    let scope: Vec<String> = symbols.consts.keys().cloned().collect();
+    check_signature(call, symbols, &scope)?;
+    let mut qubits = Vec::with_capacity(call.args.len());
+    for arg in &call.args {
+        let q = symbols.resolve(arg, false)?[0];
+        if qubits.contains(&q) {
+            return Err(duplicate_operand(arg, &call.name));
+        }
+        qubits.push(q);
+    }
+    Ok(qubits)
+}
+
+pub fn check_gate_def(def: &GateDef, symbols: &SymbolTable) -> Result<(), Diagnostic> {
+    if builtin_gate(&def.name).is_some() {
+        return Err(Diagnostic::new(
+            DiagnosticKind::DuplicateDeclaration,
+            format!("`{}` is a built-in gate and cannot be redefined", def.name),
+            def.span,
+        ));
+    }
+    if let Some(prev) = symbols.gates.get(&def.name) {
+        return Err(Diagnostic::new(
+            DiagnosticKind::DuplicateDeclaration,
+            format!("gate `{}` is defined more than once", def.name),
+            def.span,
+        ).with_note("first defined here", prev.span));
+    }
+    for names in [&def.params, &def.qubits] {
+        for (i, (name, span)) in names.iter().enumerate() {
+            if let Some((_, prev)) = names[..i].iter().find(|(n, _)| n == name) {
+                return Err(Diagnostic::new(
+                    DiagnosticKind::DuplicateDeclaration,
+                    format!("`{}` is declared more than once in gate `{}`", name, def.name),
+                    *span,
+                ).with_note("first declared here", *prev));
+            }
+        }
+    }
+    let scope: Vec<String> = def.params.iter().map(|(name, _)| name.clone()).collect();
+    for call in &def.body {
+        check_signature(call, symbols, &scope)?;
+        for (i, arg) in call.args.iter().enumerate() {
+            if !def.qubits.iter().any(|(name, _)| *name == arg.name) {
+                return Err(Diagnostic::new(
+                    DiagnosticKind::UndeclaredQubit,
+                    format!("`{}` is not an argument of gate `{}`", arg.name, def.name),
+                    arg.span,
+                ));
+            }
+            if arg.index.is_some() {
+                return Err(Diagnostic::new(
+                    DiagnosticKind::IndexOutOfRange,
+                    format!("gate argument `{}` cannot be indexed", arg.name),
+                    arg.span,
+                ));
+            }
+            if call.args[..i].iter().any(|prev| prev.name == arg.name) {
+                return Err(duplicate_operand(arg, &call.name));
+            }
+        }
+    }
+    Ok(())
+}
+
+// Resolve `measure qubits -> bits`, which must name the same number of each
+pub fn check_measure_into(qubits: &[QubitRef], bits: &[QubitRef], span: Span, symbols: &SymbolTable)
+    -> Result<Vec<(usize, usize)>, Diagnostic>
+{
+    let mut qs = Vec::new();
+    for q in qubits {
+        qs.extend(symbols.resolve(q, true)?);
+    }
+    let mut cs = Vec::new();
+    for b in bits {
+        cs.extend(symbols.resolve_bits(b, true)?);
+    }
+    if qs.len() != cs.len() {
+        return Err(Diagnostic::new(
+            DiagnosticKind::WrongArity,
+            format!("measure writes {} into {}", plural(qs.len(), "qubit"), plural(cs.len(), "bit")),
+            span,
+        ));
+    }
+    Ok(qs.into_iter().zip(cs).collect())
+}
+
+// Resolve a condition to (bits, value): the condition holds when the bits,
+// read little-endian, equal the value
+pub fn check_condition(cond: &Condition, symbols: &SymbolTable) -> Result<(Vec<usize>, u64), Diagnostic> {
+    let clbits = symbols.resolve_bits(&cond.bits, true)?;
+    let value = match cond.value {
+        Some(v) => v,
+        None if clbits.len() == 1 => 1,
+        None => return Err(Diagnostic::new(
+            DiagnosticKind::WrongArity,
+            format!("register `{}` must be compared against a value", cond.bits.name),
+            cond.span,
+        )),
+    };
+    if clbits.len() < 64 && value >> clbits.len() != 0 {
+        return Err(Diagnostic::new(
+            DiagnosticKind::IndexOutOfRange,
+            format!("{} does not fit in {}", value, plural(clbits.len(), "bit")),
+            cond.span,
+        ));
+    }
+    if !cond.negated {
+        return Ok((clbits, value));
+    }
+    if clbits.len() != 1 {
+        return Err(Diagnostic::new(
+            DiagnosticKind::Unsupported,
+            "negated conditions are only supported on single bits",
+            cond.span,
+        ));
+    }
+    Ok((clbits, 1 - value))
+}
+
+// Check the whole program, collecting every error instead of stopping at the first
+pub fn check(prog: &Program) -> Result<SymbolTable, Vec<Diagnostic>> {
+    let mut symbols = SymbolTable::default();
+    let mut errors = Vec::new();
+    check_block(&prog.statements, true, &mut symbols, &mut errors);
+    if errors.is_empty() { Ok(symbols) } else { Err(errors) }
+}
+
+fn check_block(stmts: &[Stmt], top_level: bool, symbols: &mut SymbolTable, errors: &mut Vec<Diagnostic>) {
+    for stmt in stmts {
+        let declaration_span = match stmt {
+            Stmt::QubitDecl(decls) | Stmt::BitDecl(decls) => Some(decls[0].span),
+            Stmt::GateDef(def) => Some(def.span),
+            _ => None,
+        };
+        if let (Some(span), false) = (declaration_span, top_level) {
+            errors.push(Diagnostic::new(
+                DiagnosticKind::Unsupported,
+                "declarations are only allowed at the top level",
+                span,
+            ));
+            continue;
+        }
+        match stmt {
+            Stmt::QubitDecl(decls) => {
+                for d in decls {
+                    if let Err(e) = symbols.declare(&d.name, d.size, d.span) {
+                        errors.push(e);
+                    }
+                }
+            }
+            Stmt::BitDecl(decls) => {
+                for d in decls {
+                    if let Err(e) = symbols.declare_bits(&d.name, d.size, d.span) {
+                        errors.push(e);
+                    }
+                }
+            }
+            Stmt::Gate(call) => {
+                if let Err(e) = check_gate(call, symbols) {
+                    errors.push(e);
+                }
+            }
+            Stmt::Measure(args, _) | Stmt::Reset(args, _) => {
+                for arg in args {
+                    if let Err(e) = symbols.resolve(arg, true) {
+                        errors.push(e);
+                    }
+                }
+            }
+            Stmt::MeasureInto(qubits, bits, span) => {
+                if let Err(e) = check_measure_into(qubits, bits, *span, symbols) {
+                    errors.push(e);
+                }
+            }
+            Stmt::If(cond, then_body, else_body, _) => {
+                if let Err(e) = check_condition(cond, symbols) {
+                    errors.push(e);
+                }
+                check_block(then_body, false, symbols, errors);
+                check_block(else_body, false, symbols, errors);
+            }
+            Stmt::While(cond, body, _) => {
+                if let Err(e) = check_condition(cond, symbols) {
+                    errors.push(e);
+                }
+                check_block(body, false, symbols, errors);
+            }
+            Stmt::For(var, first, last, body, _) => {
+                let range = match symbols.loop_range(first, last) {
+                    Ok(range) => range,
+                    Err(e) => {
+                        errors.push(e);
+                        continue;
+                    }
+                };
+                // Check every iteration so indices are bounds-checked with
+                // real values, but stop at the first one that fails
+                let shadowed = symbols.consts.get(var).copied();
+                let before = errors.len();
+                for i in range {
+                    symbols.consts.insert(var.clone(), i as f64);
+                    check_block(body, false, symbols, errors);
+                    if errors.len() > before {
+                        break;
+                    }
+                }
+                match shadowed {
+                    Some(v) => symbols.consts.insert(var.clone(), v),
+                    None => symbols.consts.remove(var),
+                };
+            }
+            Stmt::GateDef(def) => match check_gate_def(def, symbols) {
+                Ok(()) => {
+                    symbols.gates.insert(def.name.clone(), def.clone());
+                }
+                Err(e) => errors.push(e),
+            },
+        }
+    }
+}
@@ -0,0 +1,267 @@
+// The norm equation t†t = xi for xi in Z[sqrt2], following Ross & Selinger,
+// "Optimal ancilla-free Clifford+T approximation of z-rotations" (2016),
+// appendix C. xi is split into primes of Z[sqrt2] through the integer
+// factorization of its norm, each prime is split once more in Z[omega],
+// and t is the product of one factor from each pair. Norms are limited to
+// 62 bits and the Z[omega] gcds to primes below 2^50, so i128 arithmetic
+// never overflows; candidates beyond that are reported as unsolvable.
+use crate::compiler::synthesis::ring::{ZOmega, ZRoot2};
+
+const MAX_NORM: u64 = 1 << 62;
+const MAX_SPLIT_PRIME: u64 = 1 << 50;
+
+// Some t in Z[omega] with t†t = xi, if one exists and is found cheaply
+pub fn solve_norm_equation(xi: ZRoot2) -> Option<ZOmega> {
+    if xi.is_zero() {
+        return Some(ZOmega::zero());
+    }
+    if !xi.is_doubly_positive() {
+        return None;
+    }
+    let n = u64::try_from(xi.norm()).ok().filter(|&n| n < MAX_NORM)?;
+    let mut rem = xi;
+    let mut t = ZOmega::one();
+    for (p, _) in factor(n) {
+        match p % 8 {
+            // sqrt2 = delta† delta up to a unit, with delta = 1 + omega
+            2 => {
+                while rem.divisible_by_sqrt2() {
+                    rem = rem.div_sqrt2();
+                    t = t * ZOmega::new([1, 1, 0, 0]);
+                }
+            }
+            // p stays prime in Z[sqrt2] and splits in Z[omega]
+            3 | 5 => {
+                let tau = if p % 8 == 5 {
+                    let (a, b) = cornacchia(1, p)?;
+                    ZOmega::new([a, 0, b, 0])
+                } else {
+                    // a + b i sqrt2, with i sqrt2 = omega + omega^3
+                    let (a, b) = cornacchia(2, p)?;
+                    ZOmega::new([a, b, 0, b])
+                };
+                while let Some(q) = rem.div_exact(ZRoot2::from_int(p as i128)) {
+                    rem = q;
+                    t = t * tau;
+                }
+            }
+            // p = eta eta• in Z[sqrt2]
+            _ => {
+                let x = sqrt_mod(2, p)?;
+                let eta = ZRoot2::gcd(ZRoot2::from_int(p as i128), ZRoot2::new(x as i128, 1));
+                let gaussian = if p % 8 == 1 {
+                    if p >= MAX_SPLIT_PRIME {
+                        return None;
+                    }
+                    let (a, b) = cornacchia(1, p)?;
+                    Some(ZOmega::new([a, 0, b, 0]))
+                } else {
+                    None
+                };
+                for eta in [eta, eta.conj()] {
+                    let mut count = 0;
+                    while let Some(q) = rem.div_exact(eta) {
+                        rem = q;
+                        count += 1;
+                    }
+                    match gaussian {
+                        // eta splits further as tau† tau
+                        Some(g) => {
+                            let tau = ZOmega::gcd(g, ZOmega::from_root2(eta));
+                            for _ in 0..count {
+                                t = t * tau;
+                            }
+                        }
+                        // eta stays prime in Z[omega], so it needs an even power
+                        None => {
+                            if count % 2 == 1 {
+                                return None;
+                            }
+                            for _ in 0..count / 2 {
+                                t = t * ZOmega::from_root2(eta);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+    // What is left is a doubly positive unit lambda^(2j)
+    let unit = xi.div_exact(t.norm_sq())?;
+    if unit.norm() != 1 || !unit.is_doubly_positive() {
+        return None;
+    }
+    let mut j = 0;
+    let mut u = unit;
+    while u != ZRoot2::from_int(1) {
+        if u.to_f64() > 1.0 {
+            u = u * ZRoot2::lambda_pow(-2);
+            j += 1;
+        } else {
+            u = u * ZRoot2::lambda_pow(2);
+            j -= 1;
+        }
+    }
+    Some(t * ZOmega::from_root2(ZRoot2::lambda_pow(j)))
+}
+
+// x^2 + d y^2 = p for a prime p, by Cornacchia's algorithm
+fn cornacchia(d: u64, p: u64) -> Option<(i128, i128)> {
+    let mut r = sqrt_mod(p - d, p)?;
+    if 2 * r < p {
+        r = p - r;
+    }
+    let (mut a, mut b) = (p, r);
+    while (b as u128) * (b as u128) >= p as u128 {
+        (a, b) = (b, a % b);
+    }
+    let rest = p - b * b;
+    if !rest.is_multiple_of(d) {
+        return None;
+    }
+    let y = isqrt(rest / d);
+    (y * y == rest / d).then_some((b as i128, y as i128))
+}
+
+fn isqrt(n: u64) -> u64 {
+    let mut x = (n as f64).sqrt() as u64;
+    while x * x > n {
+        x -= 1;
+    }
+    while (x + 1) * (x + 1) <= n {
+        x += 1;
+    }
+    x
+}
+
+fn mul_mod(a: u64, b: u64, m: u64) -> u64 {
+    ((a as u128 * b as u128) % m as u128) as u64
+}
+
+fn pow_mod(mut a: u64, mut e: u64, m: u64) -> u64 {
+    let mut r = 1 % m;
+    a %= m;
+    while e > 0 {
+        if e & 1 == 1 {
+            r = mul_mod(r, a, m);
+        }
+        a = mul_mod(a, a, m);
+        e >>= 1;
+    }
+    r
+}
+
+// Square root of `a` modulo the odd prime `p` (Tonelli-Shanks)
+fn sqrt_mod(a: u64, p: u64) -> Option<u64> {
+    let a = a % p;
+    if a == 0 {
+        return Some(0);
+    }
+    if pow_mod(a, (p - 1) / 2, p) != 1 {
+        return None;
+    }
+    let (mut q, mut s) = (p - 1, 0);
+    while q % 2 == 0 {
+        q /= 2;
+        s += 1;
+    }
+    let z = (2..p).find(|&z| pow_mod(z, (p - 1) / 2, p) == p - 1)?;
+    let (mut m, mut c, mut t, mut r) = (s, pow_mod(z, q, p), pow_mod(a, q, p), pow_mod(a, q.div_ceil(2), p));
+    while t != 1 {
+        let mut i = 0;
+        let mut t2 = t;
+        while t2 != 1 {
+            t2 = mul_mod(t2, t2, p);
+            i += 1;
+        }
+        let b = pow_mod(c, 1 << (m - i - 1), p);
+        m = i;
+        c = mul_mod(b, b, p);
+        t = mul_mod(t, c, p);
+        r = mul_mod(r, b, p);
+    }
+    Some(r)
+}
+
+// Deterministic Miller-Rabin for 64-bit integers
+fn is_prime(n: u64) -> bool {
+    if n < 2 {
+        return false;
+    }
+    const BASES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+    if let Some(&p) = BASES.iter().find(|&&p| n.is_multiple_of(p)) {
+        return n == p;
+    }
+    let (mut d, mut s) = (n - 1, 0);
+    while d % 2 == 0 {
+        d /= 2;
+        s += 1;
+    }
+    BASES.iter().all(|&a| {
+        let mut x = pow_mod(a, d, n);
+        if x == 1 || x == n - 1 {
+            return true;
+        }
+        for _ in 1..s {
+            x = mul_mod(x, x, n);
+            if x == n - 1 {
+                return true;
+            }
+        }
+        false
+    })
+}
+
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+// A nontrivial factor of the odd composite `n` (Pollard's rho)
+fn pollard_rho(n: u64) -> u64 {
+    for c in 1.. {
+        let f = |x: u64| (mul_mod(x, x, n) + c) % n;
+        let (mut x, mut y, mut d) = (2, 2, 1);
+        while d == 1 {
+            x = f(x);
+            y = f(f(y));
+            d = gcd(x.abs_diff(y), n);
+        }
+        if d != n {
+            return d;
+        }
+    }
+    unreachable!()
+}
+
+// Prime factorization as (prime, exponent) pairs in increasing order
+pub fn factor(n: u64) -> Vec<(u64, u32)> {
+    let mut primes = Vec::new();
+    let mut stack = vec![n];
+    while let Some(m) = stack.pop() {
+        if m == 1 {
+            continue;
+        }
+        if m % 2 == 0 {
+            primes.push(2);
+            stack.push(m / 2);
+        } else if is_prime(m) {
+            primes.push(m);
+        } else {
+            let d = pollard_rho(m);
+            stack.push(d);
+            stack.push(m / d);
+        }
+    }
+    primes.sort_unstable();
+    let mut out: Vec<(u64, u32)> = Vec::new();
+    for p in primes {
+        match out.last_mut() {
+            Some((q, e)) if *q == p => *e += 1,
+            _ => out.push((p, 1)),
+        }
+    }
+    out
+}
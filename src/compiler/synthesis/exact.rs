@@ -0,0 +1,233 @@
+// Exact synthesis of single-qubit Clifford+T unitaries, i.e. of unitaries
+// with entries in Z[1/sqrt2, i], into Matsumoto-Amano normal form
+//
+//   (T | e) (HT | SHT)* C,   C a Clifford,
+//
+// which is unique and has the least possible number of T gates.
+use crate::compiler::ir::IROp;
+use crate::compiler::synthesis::ring::{ZOmega, ZRoot2};
+use crate::math::complex::Complex;
+use std::f64::consts::FRAC_1_SQRT_2;
+
+// entries / sqrt2^k
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ExactUnitary {
+    pub entries: [[ZOmega; 2]; 2],
+    pub k: u32,
+}
+
+impl ExactUnitary {
+    pub fn new(entries: [[ZOmega; 2]; 2], k: u32) -> Self {
+        let mut u = Self { entries, k };
+        u.reduce();
+        u
+    }
+
+    pub fn identity() -> Self {
+        Self::diagonal(0)
+    }
+
+    pub fn h() -> Self {
+        let (one, zero) = (ZOmega::one(), ZOmega::zero());
+        Self::new([[one, one], [one, zero - one]], 1)
+    }
+
+    pub fn s() -> Self {
+        Self::diagonal(2)
+    }
+
+    pub fn t() -> Self {
+        Self::diagonal(1)
+    }
+
+    // diag(1, omega^j)
+    fn diagonal(j: i32) -> Self {
+        Self::new([[ZOmega::one(), ZOmega::zero()], [ZOmega::zero(), ZOmega::omega_pow(j)]], 0)
+    }
+
+    // The product of a Clifford+T circuit on one qubit, or None if `ops`
+    // has another gate
+    pub fn from_ops(ops: &[IROp]) -> Option<Self> {
+        let mut u = Self::identity();
+        for op in ops {
+            let g = match op {
+                IROp::I(_) => Self::identity(),
+                IROp::H(_) => Self::h(),
+                IROp::S(_) => Self::s(),
+                IROp::Sdg(_) => Self::diagonal(6),
+                IROp::T(_) => Self::t(),
+                IROp::Tdg(_) => Self::diagonal(7),
+                IROp::Z(_) => Self::diagonal(4),
+                IROp::X(_) => Self::h().mul(&Self::diagonal(4)).mul(&Self::h()),
+                IROp::Y(_) => Self::h().mul(&Self::diagonal(4)).mul(&Self::h()).mul(&Self::diagonal(4)),
+                _ => return None,
+            };
+            u = g.mul(&u);
+        }
+        Some(u)
+    }
+
+    pub fn mul(&self, o: &Self) -> Self {
+        let (a, b) = (&self.entries, &o.entries);
+        let e = |i: usize, j: usize| a[i][0] * b[0][j] + a[i][1] * b[1][j];
+        Self::new([[e(0, 0), e(0, 1)], [e(1, 0), e(1, 1)]], self.k + o.k)
+    }
+
+    pub fn adjoint(&self) -> Self {
+        let e = &self.entries;
+        Self::new([[e[0][0].adj(), e[1][0].adj()], [e[0][1].adj(), e[1][1].adj()]], self.k)
+    }
+
+    // Divide out common factors of sqrt2
+    fn reduce(&mut self) {
+        while self.k > 0 && self.entries.iter().flatten().all(|x| x.divisible_by_sqrt2()) {
+            for x in self.entries.iter_mut().flatten() {
+                *x = x.div_sqrt2();
+            }
+            self.k -= 1;
+        }
+    }
+
+    // Equal up to a power of omega, the only global phases Clifford+T has
+    pub fn equal_up_to_phase(&self, o: &Self) -> bool {
+        self.k == o.k && (0..8).any(|j| {
+            let w = ZOmega::omega_pow(j);
+            self.entries.iter().flatten().zip(o.entries.iter().flatten()).all(|(x, y)| w * *x == *y)
+        })
+    }
+
+    pub fn to_matrix(&self) -> [[Complex; 2]; 2] {
+        let scale = FRAC_1_SQRT_2.powi(self.k as i32);
+        self.entries.map(|row| row.map(|x| {
+            let z = x.to_complex();
+            Complex::new(z.re * scale, z.im * scale)
+        }))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Syllable {
+    T,
+    HT,
+    SHT,
+}
+
+impl Syllable {
+    fn unitary(&self) -> ExactUnitary {
+        match self {
+            Syllable::T => ExactUnitary::t(),
+            Syllable::HT => ExactUnitary::h().mul(&ExactUnitary::t()),
+            Syllable::SHT => ExactUnitary::s().mul(&ExactUnitary::h()).mul(&ExactUnitary::t()),
+        }
+    }
+}
+
+// syllables[0] syllables[1] ... clifford as an operator product; only the
+// first syllable can be a plain T
+#[derive(Clone, Debug, PartialEq)]
+pub struct NormalForm {
+    pub syllables: Vec<Syllable>,
+    // The Clifford as H/S gates in circuit order
+    pub clifford: Vec<IROp>,
+}
+
+impl NormalForm {
+    pub fn t_count(&self) -> usize {
+        self.syllables.len()
+    }
+
+    // The gates on qubit `q` in circuit order, i.e. the operator product
+    // read from the right
+    pub fn ops(&self, q: usize) -> Vec<IROp> {
+        let mut ops: Vec<IROp> = self.clifford.iter().map(|op| op.map_qubits(|_| q)).collect();
+        for s in self.syllables.iter().rev() {
+            ops.push(IROp::T(q));
+            match s {
+                Syllable::T => {}
+                Syllable::HT => ops.push(IROp::H(q)),
+                Syllable::SHT => ops.extend([IROp::H(q), IROp::S(q)]),
+            }
+        }
+        ops
+    }
+}
+
+// The 24 single-qubit Cliffords up to phase, each with an H/S word in
+// circuit order
+fn cliffords() -> Vec<(ExactUnitary, Vec<IROp>)> {
+    let mut group = vec![(ExactUnitary::identity(), Vec::new())];
+    let mut i = 0;
+    while i < group.len() {
+        for (g, op) in [(ExactUnitary::h(), IROp::H(0)), (ExactUnitary::s(), IROp::S(0))] {
+            let u = g.mul(&group[i].0);
+            if !group.iter().any(|(v, _)| v.equal_up_to_phase(&u)) {
+                let mut word = group[i].1.clone();
+                word.push(op);
+                group.push((u, word));
+            }
+        }
+        i += 1;
+    }
+    group
+}
+
+fn find_clifford<'a>(table: &'a [(ExactUnitary, Vec<IROp>)], u: &ExactUnitary) -> Option<&'a (ExactUnitary, Vec<IROp>)> {
+    table.iter().find(|(c, _)| c.equal_up_to_phase(u))
+}
+
+// Least denominator exponent of the Bloch-sphere rotation of `u`, i.e. of
+// M_ij = tr(sigma_i u sigma_j u†) / 2, whose entries lie in Z[1/sqrt2]. It
+// is 0 exactly for the Cliffords.
+fn bloch_sde(u: &ExactUnitary) -> u32 {
+    let (zero, one, i) = (ZOmega::zero(), ZOmega::one(), ZOmega::i());
+    let paulis = [[[zero, one], [one, zero]], [[zero, zero - i], [i, zero]], [[one, zero], [zero, zero - one]]];
+    let v = &u.entries;
+    let vdg = [[v[0][0].adj(), v[1][0].adj()], [v[0][1].adj(), v[1][1].adj()]];
+    let mul = |a: &[[ZOmega; 2]; 2], b: &[[ZOmega; 2]; 2]| {
+        let e = |r: usize, c: usize| a[r][0] * b[0][c] + a[r][1] * b[1][c];
+        [[e(0, 0), e(0, 1)], [e(1, 0), e(1, 1)]]
+    };
+    // M = N / sqrt2^(2k + 2), with N in Z[sqrt2]
+    let mut entries: Vec<ZRoot2> = Vec::new();
+    for a in &paulis {
+        for b in &paulis {
+            let p = mul(&mul(a, v), &mul(b, &vdg));
+            let tr = p[0][0] + p[1][1];
+            entries.push(ZRoot2::new(tr.c[0], tr.c[1]));
+        }
+    }
+    let mut k = 2 * u.k + 2;
+    while k > 0 && entries.iter().all(|x| x.divisible_by_sqrt2()) {
+        for x in entries.iter_mut() {
+            *x = x.div_sqrt2();
+        }
+        k -= 1;
+    }
+    k
+}
+
+// Matsumoto-Amano normal form of `u` (up to global phase). Stripping the
+// right syllable off the left lowers the Bloch-sphere sde by exactly one
+// (Giles & Selinger, "Remarks on Matsumoto and Amano's normal form", 2013),
+// so the syllables are found one at a time and a Clifford is left over.
+pub fn exact_synthesis(u: &ExactUnitary) -> NormalForm {
+    let mut rest = *u;
+    let mut syllables = Vec::new();
+    let mut sde = bloch_sde(&rest);
+    while sde > 0 {
+        let (s, next, next_sde) = [Syllable::T, Syllable::HT, Syllable::SHT]
+            .into_iter()
+            .filter(|s| *s != Syllable::T || syllables.is_empty())
+            .map(|s| {
+                let next = s.unitary().adjoint().mul(&rest);
+                (s, next, bloch_sde(&next))
+            })
+            .find(|(_, _, k)| *k < sde)
+            .expect("not a Clifford+T unitary");
+        syllables.push(s);
+        rest = next;
+        sde = next_sde;
+    }
+    let clifford = find_clifford(&cliffords(), &rest).expect("sde 0 means Clifford").1.clone();
+    NormalForm { syllables, clifford }
+}
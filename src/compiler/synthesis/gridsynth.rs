@@ -0,0 +1,143 @@
+// Clifford+T approximation of z-rotations in the style of Ross & Selinger's
+// gridsynth. RZ(theta) = diag(z, z*) with z = e^{-i theta/2} is approximated
+// by an exact unitary
+//
+//   U = 1/sqrt2^k [[u, -t†], [t, u†]],   u, t in Z[omega],
+//
+// with ||U - RZ(theta)|| <= epsilon. That holds iff u / sqrt2^k lies in the
+// epsilon-region, the slice of the unit disk with Re(u z*) >= 1 - eps^2/2,
+// while u• / sqrt2^k (sqrt2 -> -sqrt2) has to lie in the unit disk for t to
+// exist. For growing k the candidates u = alpha + i beta, alpha and beta in
+// Z[sqrt2], come from one-dimensional grid problems: first alpha over the
+// x-range of the slice, then beta over the y-range left at that x. The first
+// candidate whose norm equation t†t = 2^k - |u|^2 is solvable wins, and its
+// Matsumoto-Amano normal form is the circuit.
+//
+// Unlike gridsynth this does not reduce the slice's skew with grid
+// operators, so the number of alphas per k grows like eps^(-1/2). Combined
+// with the 62-bit norms in `diophantine`, that is fine down to an epsilon of
+// roughly 1e-5.
+use crate::compiler::synthesis::diophantine::solve_norm_equation;
+use crate::compiler::synthesis::exact::{exact_synthesis, ExactUnitary, NormalForm};
+use crate::compiler::synthesis::ring::{ZOmega, ZRoot2};
+use crate::math::complex::Complex;
+use std::f64::consts::{PI, SQRT_2};
+
+const MAX_K: u32 = 31;
+
+// All x in Z[sqrt2] with x in [x0, x1] and x• in [y0, y1]. The problem is
+// first scaled by a power of lambda = 1 + sqrt2 (which scales x• by the
+// inverse) until both intervals have about the same width, so the loop
+// over the sqrt2 coefficient only visits O(1 + number of solutions) values.
+pub fn grid_points(x0: f64, x1: f64, y0: f64, y1: f64) -> Vec<ZRoot2> {
+    if x0 > x1 || y0 > y1 {
+        return Vec::new();
+    }
+    let lambda = 1.0 + SQRT_2;
+    let m = ((y1 - y0).max(1e-300) / (x1 - x0).max(1e-300)).ln() / (2.0 * lambda.ln());
+    // Far apart widths only happen on slivers, where a bounded scale is enough
+    let m = (m.round() as i32).clamp(-24, 24);
+    // lambda^m x and (lambda^m x)• = (-1/lambda)^m x•
+    let scale = lambda.powi(m);
+    let (x0, x1) = (x0 * scale, x1 * scale);
+    let dual = (-1.0 / lambda).powi(m);
+    let (y0, y1) = if dual > 0.0 { (y0 * dual, y1 * dual) } else { (y1 * dual, y0 * dual) };
+    let back = ZRoot2::lambda_pow(-m);
+
+    // x = a + b sqrt2, x• = a - b sqrt2
+    let mut out = Vec::new();
+    let b_lo = ((x0 - y1) / (2.0 * SQRT_2)).ceil() as i128;
+    let b_hi = ((x1 - y0) / (2.0 * SQRT_2)).floor() as i128;
+    for b in b_lo..=b_hi {
+        let r = b as f64 * SQRT_2;
+        let a_lo = (x0 - r).max(y0 + r).ceil() as i128;
+        let a_hi = (x1 - r).min(y1 + r).floor() as i128;
+        for a in a_lo..=a_hi {
+            out.push(ZRoot2::new(a, b) * back);
+        }
+    }
+    out
+}
+
+// The epsilon-region's x-range: the ends of its chord, widened to -1 or 1
+// if the arc passes through them
+fn region_x_range(z: Complex, epsilon: f64) -> (f64, f64) {
+    let arg = z.im.atan2(z.re);
+    let delta = (1.0 - epsilon * epsilon / 2.0).clamp(-1.0, 1.0).acos();
+    let ends = [(arg - delta).cos(), (arg + delta).cos()];
+    let on_arc = |angle: f64| {
+        let d = (angle - arg).rem_euclid(2.0 * PI);
+        d <= delta || d >= 2.0 * PI - delta
+    };
+    let lo = if on_arc(PI) { -1.0 } else { ends[0].min(ends[1]) };
+    let hi = if on_arc(0.0) { 1.0 } else { ends[0].max(ends[1]) };
+    (lo, hi)
+}
+
+// Clifford+T circuit within `epsilon` of RZ(theta) (operator norm, up to
+// global phase), or None if epsilon is too small for this implementation.
+// The unitaries above all have determinant 1, which misses the ones that
+// are only close to RZ(theta) up to a phase like e^{i pi/8}, so each k also
+// tries RZ(theta - pi/4) followed by T.
+pub fn approximate_rz(theta: f64, epsilon: f64) -> Option<NormalForm> {
+    for k in 0..=MAX_K {
+        if let Some(u) = candidate(theta, epsilon, k) {
+            return Some(exact_synthesis(&u));
+        }
+        if let Some(u) = candidate(theta - PI / 4.0, epsilon, k) {
+            return Some(exact_synthesis(&u.mul(&ExactUnitary::t())));
+        }
+    }
+    None
+}
+
+// The first candidate with denominator sqrt2^k whose norm equation can be
+// solved
+fn candidate(theta: f64, epsilon: f64, k: u32) -> Option<ExactUnitary> {
+    let z = Complex::new((theta / 2.0).cos(), -(theta / 2.0).sin());
+    let c = 1.0 - epsilon * epsilon / 2.0;
+    let (x_lo, x_hi) = region_x_range(z, epsilon);
+    let s = SQRT_2.powi(k as i32);
+    for alpha in grid_points(s * x_lo, s * x_hi, -s, s) {
+        let x = alpha.to_f64() / s;
+        // The y-range of the slice at x
+        let r = (1.0 - x * x).max(0.0).sqrt();
+        let (mut y_lo, mut y_hi) = (-r, r);
+        let needed = c - x * z.re;
+        if z.im > 1e-15 {
+            y_lo = y_lo.max(needed / z.im);
+        } else if z.im < -1e-15 {
+            y_hi = y_hi.min(needed / z.im);
+        } else if needed > 0.0 {
+            continue;
+        }
+        let ab = alpha.conj().to_f64();
+        if ab * ab > s * s {
+            continue;
+        }
+        let rb = (s * s - ab * ab).sqrt();
+        for beta in grid_points(s * y_lo, s * y_hi, -rb, rb) {
+            let xi = ZRoot2::from_int(1 << k) - (alpha * alpha + beta * beta);
+            let Some(t) = solve_norm_equation(xi) else { continue };
+            let u = ZOmega::from_root2(alpha) + ZOmega::i() * ZOmega::from_root2(beta);
+            let exact = ExactUnitary::new([[u, -t.adj()], [t, u.adj()]], k);
+            if rz_distance(&exact, theta) <= epsilon {
+                return Some(exact);
+            }
+        }
+    }
+    None
+}
+
+// ||U - RZ(theta)|| minimized over global phases. U RZ† is e^{i psi} V
+// with V in SU(2), whose eigenvalues e^{±i phi} come from its trace; the
+// best phase leaves the smaller of |1 - e^{i phi}| and |1 + e^{i phi}|.
+pub fn rz_distance(u: &ExactUnitary, theta: f64) -> f64 {
+    let m = u.to_matrix();
+    let z = Complex::new((theta / 2.0).cos(), -(theta / 2.0).sin());
+    let tr = z.conj().mul(&m[0][0]).add(&z.mul(&m[1][1]));
+    let det = m[0][0].mul(&m[1][1]).sub(&m[0][1].mul(&m[1][0]));
+    let psi = det.im.atan2(det.re) / 2.0;
+    let cos_phi = (Complex::new(psi.cos(), -psi.sin()).mul(&tr).re / 2.0).clamp(-1.0, 1.0);
+    (2.0 - 2.0 * cos_phi.abs()).max(0.0).sqrt()
+}
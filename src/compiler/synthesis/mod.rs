@@ -0,0 +1,12 @@
+// Single-qubit gate synthesis over Clifford+T: exact synthesis of
+// unitaries over Z[1/sqrt2, i], number-theoretic approximation of
+// z-rotations, and Solovay-Kitaev for arbitrary unitaries.
+pub mod diophantine;
+pub mod exact;
+pub mod gridsynth;
+pub mod ring;
+pub mod solovay_kitaev;
+
+pub use exact::{exact_synthesis, ExactUnitary, NormalForm, Syllable};
+pub use gridsynth::approximate_rz;
+pub use solovay_kitaev::SolovayKitaev;
@@ -0,0 +1,256 @@
+// The rings behind Clifford+T synthesis. Single-qubit Clifford+T unitaries
+// are exactly the unitaries with entries in Z[1/sqrt2, i], which are
+// stored as elements of Z[omega] (omega = e^{i pi/4}) over a power of sqrt2.
+// Z[sqrt2] is the real subring. Both are Euclidean, which the norm
+// equation solver in `diophantine` relies on.
+use crate::math::complex::Complex;
+use std::f64::consts::SQRT_2;
+use std::ops::{Add, Mul, Neg, Sub};
+
+// a + b sqrt2
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ZRoot2 {
+    pub a: i128,
+    pub b: i128,
+}
+
+impl ZRoot2 {
+    pub fn new(a: i128, b: i128) -> Self {
+        Self { a, b }
+    }
+
+    pub fn from_int(a: i128) -> Self {
+        Self { a, b: 0 }
+    }
+
+    // The unit lambda = 1 + sqrt2 to the power `m`
+    pub fn lambda_pow(m: i32) -> Self {
+        let unit = if m >= 0 { Self::new(1, 1) } else { Self::new(-1, 1) };
+        (0..m.unsigned_abs()).fold(Self::from_int(1), |acc, _| acc * unit)
+    }
+
+    // sqrt2 -> -sqrt2
+    pub fn conj(&self) -> Self {
+        Self::new(self.a, -self.b)
+    }
+
+    pub fn norm(&self) -> i128 {
+        self.a * self.a - 2 * self.b * self.b
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        self.a as f64 + self.b as f64 * SQRT_2
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.a == 0 && self.b == 0
+    }
+
+    // Exact sign test, which floats get wrong when a and b nearly cancel
+    pub fn is_nonnegative(&self) -> bool {
+        match (self.a >= 0, self.b >= 0) {
+            (true, true) => true,
+            (false, false) => false,
+            (true, false) => self.a * self.a >= 2 * self.b * self.b,
+            (false, true) => self.a * self.a <= 2 * self.b * self.b,
+        }
+    }
+
+    pub fn is_doubly_positive(&self) -> bool {
+        self.is_nonnegative() && self.conj().is_nonnegative()
+    }
+
+    pub fn divisible_by_sqrt2(&self) -> bool {
+        self.a % 2 == 0
+    }
+
+    pub fn div_sqrt2(&self) -> Self {
+        Self::new(self.b, self.a / 2)
+    }
+
+    // self / d if d divides self
+    pub fn div_exact(&self, d: ZRoot2) -> Option<Self> {
+        let (num, n) = (*self * d.conj(), d.norm());
+        if n == 0 || num.a % n != 0 || num.b % n != 0 {
+            return None;
+        }
+        Some(Self::new(num.a / n, num.b / n))
+    }
+
+    // Euclidean remainder
+    fn rem(&self, d: ZRoot2) -> Self {
+        let (num, n) = (*self * d.conj(), d.norm());
+        let q = Self::new(div_round(num.a, n), div_round(num.b, n));
+        *self - q * d
+    }
+
+    pub fn gcd(mut x: ZRoot2, mut y: ZRoot2) -> ZRoot2 {
+        while !y.is_zero() {
+            let r = x.rem(y);
+            x = y;
+            y = r;
+        }
+        x
+    }
+}
+
+// Nearest integer to x / n
+pub fn div_round(x: i128, n: i128) -> i128 {
+    let (x, n) = if n < 0 { (-x, -n) } else { (x, n) };
+    (2 * x + n).div_euclid(2 * n)
+}
+
+impl Add for ZRoot2 {
+    type Output = Self;
+    fn add(self, o: Self) -> Self {
+        Self::new(self.a + o.a, self.b + o.b)
+    }
+}
+
+impl Sub for ZRoot2 {
+    type Output = Self;
+    fn sub(self, o: Self) -> Self {
+        Self::new(self.a - o.a, self.b - o.b)
+    }
+}
+
+impl Mul for ZRoot2 {
+    type Output = Self;
+    fn mul(self, o: Self) -> Self {
+        Self::new(self.a * o.a + 2 * self.b * o.b, self.a * o.b + self.b * o.a)
+    }
+}
+
+// c[0] + c[1] omega + c[2] omega^2 + c[3] omega^3, with omega^4 = -1
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ZOmega {
+    pub c: [i128; 4],
+}
+
+impl ZOmega {
+    pub fn new(c: [i128; 4]) -> Self {
+        Self { c }
+    }
+
+    pub fn zero() -> Self {
+        Self::new([0; 4])
+    }
+
+    pub fn one() -> Self {
+        Self::new([1, 0, 0, 0])
+    }
+
+    pub fn i() -> Self {
+        Self::new([0, 0, 1, 0])
+    }
+
+    pub fn omega_pow(k: i32) -> Self {
+        let k = k.rem_euclid(8) as usize;
+        let mut c = [0; 4];
+        c[k % 4] = if k < 4 { 1 } else { -1 };
+        Self::new(c)
+    }
+
+    // sqrt2 = omega - omega^3
+    pub fn from_root2(x: ZRoot2) -> Self {
+        Self::new([x.a, x.b, 0, -x.b])
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.c == [0; 4]
+    }
+
+    // Complex conjugate
+    pub fn adj(&self) -> Self {
+        let c = self.c;
+        Self::new([c[0], -c[3], -c[2], -c[1]])
+    }
+
+    // sqrt2 -> -sqrt2, i.e. omega -> -omega
+    pub fn bullet(&self) -> Self {
+        let c = self.c;
+        Self::new([c[0], -c[1], c[2], -c[3]])
+    }
+
+    // |z|^2, which lies in Z[sqrt2]
+    pub fn norm_sq(&self) -> ZRoot2 {
+        let p = self.adj() * *self;
+        debug_assert!(p.c[2] == 0 && p.c[3] == -p.c[1]);
+        ZRoot2::new(p.c[0], p.c[1])
+    }
+
+    // Product of all four Galois conjugates, an integer
+    fn field_norm(&self) -> i128 {
+        let n = self.norm_sq();
+        n.norm()
+    }
+
+    pub fn divisible_by_sqrt2(&self) -> bool {
+        let c = self.c;
+        (c[0] - c[2]) % 2 == 0 && (c[1] - c[3]) % 2 == 0
+    }
+
+    // z / sqrt2 = z (omega - omega^3) / 2
+    pub fn div_sqrt2(&self) -> Self {
+        let c = self.c;
+        Self::new([(c[1] - c[3]) / 2, (c[2] + c[0]) / 2, (c[3] + c[1]) / 2, (c[2] - c[0]) / 2])
+    }
+
+    fn rem(&self, d: ZOmega) -> Self {
+        let other = d.adj() * d.bullet() * d.bullet().adj();
+        let n = d.field_norm();
+        let num = *self * other;
+        let q = Self::new(num.c.map(|x| div_round(x, n)));
+        *self - q * d
+    }
+
+    pub fn gcd(mut x: ZOmega, mut y: ZOmega) -> ZOmega {
+        while !y.is_zero() {
+            let r = x.rem(y);
+            x = y;
+            y = r;
+        }
+        x
+    }
+
+    pub fn to_complex(&self) -> Complex {
+        let h = std::f64::consts::FRAC_1_SQRT_2;
+        let c = self.c.map(|x| x as f64);
+        Complex::new(c[0] + (c[1] - c[3]) * h, c[2] + (c[1] + c[3]) * h)
+    }
+}
+
+impl Add for ZOmega {
+    type Output = Self;
+    fn add(self, o: Self) -> Self {
+        Self::new([0, 1, 2, 3].map(|i| self.c[i] + o.c[i]))
+    }
+}
+
+impl Sub for ZOmega {
+    type Output = Self;
+    fn sub(self, o: Self) -> Self {
+        Self::new([0, 1, 2, 3].map(|i| self.c[i] - o.c[i]))
+    }
+}
+
+impl Neg for ZOmega {
+    type Output = Self;
+    fn neg(self) -> Self {
+        Self::new(self.c.map(|x| -x))
+    }
+}
+
+impl Mul for ZOmega {
+    type Output = Self;
+    fn mul(self, o: Self) -> Self {
+        let mut c = [0; 4];
+        for i in 0..4 {
+            for j in 0..4 {
+                let p = self.c[i] * o.c[j];
+                if i + j < 4 { c[i + j] += p } else { c[i + j - 4] -= p }
+            }
+        }
+        Self::new(c)
+    }
+}
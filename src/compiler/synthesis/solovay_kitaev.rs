@@ -0,0 +1,245 @@
+// Solovay-Kitaev approximation of arbitrary single-qubit unitaries over
+// Clifford+T (Dawson & Nielsen, "The Solovay-Kitaev algorithm", 2005).
+//
+// The net holds every Clifford+T unitary up to a given T-count, listed
+// through Matsumoto-Amano normal forms so there are no duplicates. Level 0
+// picks the nearest net element; level n corrects the level n-1 result
+// U' by writing the remainder U U'† as a balanced group commutator
+// V W V† W† and approximating V and W at level n-1.
+//
+// SU(2) elements are unit quaternions q, standing for
+// q0 I - i (q1 X + q2 Y + q3 Z), so the Hamilton product is the matrix
+// product.
+use crate::compiler::ir::IROp;
+use crate::compiler::optimizer::peephole::peephole_block;
+use crate::compiler::synthesis::exact::{NormalForm, Syllable};
+use crate::math::complex::Complex;
+use std::f64::consts::FRAC_1_SQRT_2;
+
+pub type Quaternion = [f64; 4];
+
+const MAX_DEPTH: usize = 6;
+
+fn mul(a: &Quaternion, b: &Quaternion) -> Quaternion {
+    [
+        a[0] * b[0] - a[1] * b[1] - a[2] * b[2] - a[3] * b[3],
+        a[0] * b[1] + a[1] * b[0] + a[2] * b[3] - a[3] * b[2],
+        a[0] * b[2] - a[1] * b[3] + a[2] * b[0] + a[3] * b[1],
+        a[0] * b[3] + a[1] * b[2] - a[2] * b[1] + a[3] * b[0],
+    ]
+}
+
+fn inverse(q: &Quaternion) -> Quaternion {
+    [q[0], -q[1], -q[2], -q[3]]
+}
+
+// Rotation of the Bloch sphere by `angle` about the unit vector `axis`
+fn rotation(angle: f64, axis: [f64; 3]) -> Quaternion {
+    let (c, s) = ((angle / 2.0).cos(), (angle / 2.0).sin());
+    [c, s * axis[0], s * axis[1], s * axis[2]]
+}
+
+// Angle in [0, pi] and axis of q, taking the sign that gives the smaller angle
+fn angle_axis(q: &Quaternion) -> (f64, [f64; 3]) {
+    let q = if q[0] < 0.0 { q.map(|x| -x) } else { *q };
+    let norm = (q[1] * q[1] + q[2] * q[2] + q[3] * q[3]).sqrt();
+    let angle = 2.0 * norm.atan2(q[0]);
+    if norm < 1e-15 {
+        return (0.0, [0.0, 0.0, 1.0]);
+    }
+    (angle, [q[1] / norm, q[2] / norm, q[3] / norm])
+}
+
+// The SU(2) element of a unitary, dropping its global phase
+pub fn to_quaternion(u: &[[Complex; 2]; 2]) -> Quaternion {
+    let det = u[0][0].mul(&u[1][1]).sub(&u[0][1].mul(&u[1][0]));
+    let half = det.im.atan2(det.re) / 2.0;
+    let scale = Complex::new(half.cos(), -half.sin());
+    let m = u.map(|row| row.map(|x| scale.mul(&x)));
+    // [[q0 - i q3, -q2 - i q1], [q2 - i q1, q0 + i q3]]
+    let q = [
+        (m[0][0].re + m[1][1].re) / 2.0,
+        -(m[0][1].im + m[1][0].im) / 2.0,
+        (m[1][0].re - m[0][1].re) / 2.0,
+        (m[1][1].im - m[0][0].im) / 2.0,
+    ];
+    let norm = q.iter().map(|x| x * x).sum::<f64>().sqrt();
+    q.map(|x| x / norm)
+}
+
+// Operator-norm distance up to global phase: U V† has eigenvalues e^{±i phi}
+// with cos(phi) = p.q, and the better of the phases 1 and -1 leaves
+// sqrt(2 - 2 |cos(phi)|)
+pub fn distance(p: &Quaternion, q: &Quaternion) -> f64 {
+    let dot: f64 = p.iter().zip(q).map(|(a, b)| a * b).sum();
+    (2.0 - 2.0 * dot.abs().min(1.0)).max(0.0).sqrt()
+}
+
+fn gate(op: &IROp) -> Quaternion {
+    let s = FRAC_1_SQRT_2;
+    let (c8, s8) = ((std::f64::consts::PI / 8.0).cos(), (std::f64::consts::PI / 8.0).sin());
+    match op {
+        IROp::H(_) => [0.0, s, 0.0, s],
+        IROp::S(_) => [s, 0.0, 0.0, s],
+        IROp::T(_) => [c8, 0.0, 0.0, s8],
+        _ => unreachable!("net words only use H, S and T"),
+    }
+}
+
+fn product(ops: &[IROp]) -> Quaternion {
+    ops.iter().fold([1.0, 0.0, 0.0, 0.0], |acc, op| mul(&gate(op), &acc))
+}
+
+fn invert_ops(ops: &[IROp]) -> Vec<IROp> {
+    ops.iter().rev().map(|op| op.inverse().unwrap()).collect()
+}
+
+pub struct SolovayKitaev {
+    net: Vec<(Quaternion, Vec<IROp>)>,
+}
+
+impl SolovayKitaev {
+    // Net of all 24 (3 * 2^max_t - 2) Clifford+T unitaries with at most
+    // `max_t` T gates
+    pub fn new(max_t: usize) -> Self {
+        let cliffords: Vec<Vec<IROp>> = clifford_words();
+        let mut forms: Vec<Vec<Syllable>> = vec![Vec::new(), vec![Syllable::T]];
+        let mut frontier = forms.clone();
+        for _ in 0..max_t {
+            let mut next = Vec::new();
+            for form in &frontier {
+                for s in [Syllable::HT, Syllable::SHT] {
+                    let mut longer = form.clone();
+                    longer.push(s);
+                    next.push(longer);
+                }
+            }
+            forms.extend(next.iter().cloned());
+            frontier = next;
+        }
+        let mut net = Vec::new();
+        for syllables in forms.into_iter().filter(|f| f.len() <= max_t) {
+            for clifford in &cliffords {
+                let ops = NormalForm { syllables: syllables.clone(), clifford: clifford.clone() }.ops(0);
+                net.push((product(&ops), ops));
+            }
+        }
+        Self { net }
+    }
+
+    pub fn net_size(&self) -> usize {
+        self.net.len()
+    }
+
+    fn nearest(&self, q: &Quaternion) -> (Quaternion, Vec<IROp>) {
+        let (p, ops) = self.net.iter()
+            .min_by(|a, b| distance(&a.0, q).total_cmp(&distance(&b.0, q)))
+            .expect("net is never empty");
+        (*p, ops.clone())
+    }
+
+    // Level-`depth` approximation of q, with its quaternion
+    fn approximate_at(&self, q: &Quaternion, depth: usize) -> (Quaternion, Vec<IROp>) {
+        if depth == 0 {
+            return self.nearest(q);
+        }
+        let (prev, prev_ops) = self.approximate_at(q, depth - 1);
+        let (v, w) = balanced_commutator(&mul(q, &inverse(&prev)));
+        let (vq, v_ops) = self.approximate_at(&v, depth - 1);
+        let (wq, w_ops) = self.approximate_at(&w, depth - 1);
+        let result = mul(&mul(&mul(&mul(&vq, &wq), &inverse(&vq)), &inverse(&wq)), &prev);
+        // The commutator only helps once the net is fine enough, so a
+        // coarse net could make things worse
+        if distance(&result, q) >= distance(&prev, q) {
+            return (prev, prev_ops);
+        }
+        // V W V† W† U', in circuit order U' first
+        let mut ops = prev_ops;
+        ops.extend(invert_ops(&w_ops));
+        ops.extend(invert_ops(&v_ops));
+        ops.extend(w_ops);
+        ops.extend(v_ops);
+        (result, ops)
+    }
+
+    // Clifford+T gates on qubit 0 for `u` at recursion level `depth`
+    pub fn approximate(&self, u: &[[Complex; 2]; 2], depth: usize) -> Vec<IROp> {
+        peephole_block(self.approximate_at(&to_quaternion(u), depth).1)
+    }
+
+    // The shallowest approximation within `epsilon`, or the deepest one
+    // tried if none gets there
+    pub fn compile(&self, u: &[[Complex; 2]; 2], epsilon: f64) -> Vec<IROp> {
+        let q = to_quaternion(u);
+        let mut best = Vec::new();
+        for depth in 0..=MAX_DEPTH {
+            let (p, ops) = self.approximate_at(&q, depth);
+            best = ops;
+            if distance(&p, &q) <= epsilon {
+                break;
+            }
+        }
+        peephole_block(best)
+    }
+}
+
+// The 24 single-qubit Cliffords as H/S words
+fn clifford_words() -> Vec<Vec<IROp>> {
+    let mut words: Vec<(Quaternion, Vec<IROp>)> = vec![([1.0, 0.0, 0.0, 0.0], Vec::new())];
+    let mut i = 0;
+    while i < words.len() {
+        for op in [IROp::H(0), IROp::S(0)] {
+            let q = mul(&gate(&op), &words[i].0);
+            if words.iter().all(|(p, _)| distance(p, &q) > 1e-9) {
+                let mut word = words[i].1.clone();
+                word.push(op);
+                words.push((q, word));
+            }
+        }
+        i += 1;
+    }
+    words.into_iter().map(|(_, w)| w).collect()
+}
+
+// V and W with V W V† W† = delta, both rotations by the same angle phi
+// chosen so that a commutator of X and Y rotations by phi has delta's angle
+fn balanced_commutator(delta: &Quaternion) -> (Quaternion, Quaternion) {
+    let (theta, axis) = angle_axis(delta);
+    // sin(theta/2) = 2 sin^2(phi/2) sqrt(1 - sin^4(phi/2))
+    let st = (theta / 2.0).sin();
+    let s2 = ((1.0 - (1.0 - st * st).max(0.0).sqrt()) / 2.0).sqrt();
+    let phi = 2.0 * s2.sqrt().asin();
+    let v = rotation(phi, [1.0, 0.0, 0.0]);
+    let w = rotation(phi, [0.0, 1.0, 0.0]);
+    let (_, comm_axis) = angle_axis(&mul(&mul(&mul(&v, &w), &inverse(&v)), &inverse(&w)));
+    // S turns the commutator's axis into delta's
+    let s = similarity(comm_axis, axis);
+    let conj = |x: &Quaternion| mul(&mul(&s, x), &inverse(&s));
+    (conj(&v), conj(&w))
+}
+
+// A rotation taking the unit vector `from` to `to`
+fn similarity(from: [f64; 3], to: [f64; 3]) -> Quaternion {
+    let dot = from[0] * to[0] + from[1] * to[1] + from[2] * to[2];
+    let cross = [
+        from[1] * to[2] - from[2] * to[1],
+        from[2] * to[0] - from[0] * to[2],
+        from[0] * to[1] - from[1] * to[0],
+    ];
+    let norm = cross.iter().map(|x| x * x).sum::<f64>().sqrt();
+    if norm < 1e-12 {
+        if dot > 0.0 {
+            return [1.0, 0.0, 0.0, 0.0];
+        }
+        // Half turn about any axis perpendicular to `from`
+        let other = if from[0].abs() < 0.9 { [1.0, 0.0, 0.0] } else { [0.0, 1.0, 0.0] };
+        let perp = [
+            from[1] * other[2] - from[2] * other[1],
+            from[2] * other[0] - from[0] * other[2],
+            from[0] * other[1] - from[1] * other[0],
+        ];
+        let n = perp.iter().map(|x| x * x).sum::<f64>().sqrt();
+        return rotation(std::f64::consts::PI, perp.map(|x| x / n));
+    }
+    rotation(dot.clamp(-1.0, 1.0).acos(), cross.map(|x| x / norm))
+}
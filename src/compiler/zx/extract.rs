@@ -0,0 +1,226 @@
+// Circuit extraction (Backens, Kissinger, Miller-Bakewell, van de Wetering &
+// Wolffs, "There and back again", 2021). Gates are peeled off the output
+// side of a graph-like diagram: the spiders next to the outputs form the
+// frontier, their phases become Z rotations, edges between them become
+// CZs, and Gaussian elimination on the frontier's biadjacency matrix
+// (one CNOT per row operation) exposes frontier spiders with a single
+// neighbor, which then move the frontier one step towards the inputs.
+use crate::compiler::ir::IROp;
+use crate::compiler::optimizer::peephole::z_rotation;
+use crate::compiler::zx::graph::{is_pauli, EdgeKind, VertexKind, ZXGraph};
+use crate::compiler::zx::rules::pivot;
+use std::collections::BTreeSet;
+use std::f64::consts::PI;
+
+// The circuit the diagram represents (up to global phase), or None if the
+// extraction gets stuck, which can only happen when the diagram has no
+// gflow
+pub fn extract_circuit(mut g: ZXGraph) -> Option<Vec<IROp>> {
+    g.to_graph_like();
+    let n = g.outputs().len();
+    let outputs = g.outputs().to_vec();
+    let inputs = g.inputs().to_vec();
+    separate_outputs(&mut g);
+    let mut frontier: Vec<Option<usize>> = outputs.iter().map(|&o| Some(g.neighbors(o)[0])).collect();
+    // Input qubit that ends up on each output wire
+    let mut source: Vec<Option<usize>> = vec![None; n];
+    // Gates from the outputs backwards
+    let mut rev = Vec::new();
+
+    while frontier.iter().any(|f| f.is_some()) {
+        clean_frontier(&mut g, &frontier, &outputs, &mut rev);
+
+        // Frontier spiders touching an input are either finished wires or
+        // get their input pushed back behind a fresh spider
+        for q in 0..n {
+            let Some(v) = frontier[q] else { continue };
+            let ns: Vec<usize> = g.neighbors(v).into_iter().filter(|&w| w != outputs[q]).collect();
+            let ins: Vec<usize> = ns.iter().copied().filter(|&w| g.is_boundary(w)).collect();
+            if ins.len() == 1 && ns.len() == 1 {
+                if g.edge(v, ins[0]) == Some(EdgeKind::Hadamard) {
+                    rev.push(IROp::H(q));
+                }
+                source[q] = Some(inputs.iter().position(|&i| i == ins[0])?);
+                frontier[q] = None;
+            } else {
+                for i in ins {
+                    unfuse_boundary(&mut g, i);
+                }
+            }
+        }
+        let live: Vec<usize> = (0..n).filter(|&q| frontier[q].is_some()).collect();
+        if live.is_empty() {
+            break;
+        }
+
+        let neighbors: BTreeSet<usize> = live.iter()
+            .flat_map(|&q| g.neighbors(frontier[q].unwrap()))
+            .filter(|&w| !g.is_boundary(w))
+            .collect();
+
+        // A phase gadget next to the frontier is pivoted into it
+        if let Some((q, axle)) = gadget_next_to_frontier(&g, &frontier, &neighbors) {
+            let v = frontier[q].unwrap();
+            if !is_pauli(g.phase(axle)) {
+                return None;
+            }
+            frontier[q] = Some(unfuse_boundary(&mut g, outputs[q]));
+            for w in g.neighbors(axle) {
+                if g.is_boundary(w) {
+                    unfuse_boundary(&mut g, w);
+                }
+            }
+            pivot(&mut g, v, axle);
+            continue;
+        }
+
+        let columns: Vec<usize> = neighbors.into_iter().collect();
+        let mut rows: Vec<Vec<bool>> = live.iter()
+            .map(|&q| {
+                let v = frontier[q].unwrap();
+                columns.iter().map(|&w| g.edge(v, w).is_some()).collect()
+            })
+            .collect();
+        let single = |row: &Vec<bool>| row.iter().filter(|&&b| b).count() == 1;
+        if !rows.iter().any(single) {
+            // Row r2 += row r1 is a CNOT with control r2 and target r1
+            for (r1, r2) in eliminate(&mut rows) {
+                let (a, b) = (live[r2], live[r1]);
+                let (va, vb) = (frontier[a].unwrap(), frontier[b].unwrap());
+                for w in g.neighbors(vb) {
+                    if w != outputs[b] {
+                        g.connect(va, w, EdgeKind::Hadamard);
+                    }
+                }
+                rev.push(IROp::CNOT(a, b));
+            }
+        }
+        let mut taken = BTreeSet::new();
+        for (r, row) in rows.iter().enumerate() {
+            if !single(row) {
+                continue;
+            }
+            let w = columns[row.iter().position(|&b| b).unwrap()];
+            // Two rows may share their only neighbor before elimination
+            if !taken.insert(w) {
+                continue;
+            }
+            let q = live[r];
+            let v = frontier[q].unwrap();
+            rev.push(IROp::H(q));
+            g.remove_vertex(v);
+            g.connect(w, outputs[q], EdgeKind::Simple);
+            frontier[q] = Some(w);
+        }
+        if taken.is_empty() {
+            return None;
+        }
+    }
+
+    // Route each input qubit to the wire the diagram connected it to
+    let mut ops = Vec::new();
+    let mut wires: Vec<usize> = (0..n).collect();
+    for q in 0..n {
+        let want = source[q]?;
+        if wires[q] != want {
+            let w = wires.iter().position(|&x| x == want)?;
+            ops.push(IROp::SWAP(q, w));
+            wires.swap(q, w);
+        }
+    }
+    ops.extend(rev.into_iter().rev());
+    Some(ops)
+}
+
+// Give every output its own spider, so no spider touches two outputs and
+// no output is wired straight to an input
+fn separate_outputs(g: &mut ZXGraph) {
+    let outputs = g.outputs().to_vec();
+    for &o in &outputs {
+        let v = g.neighbors(o)[0];
+        if g.is_boundary(v) {
+            let x = unfuse_boundary(g, o);
+            unfuse_boundary_from(g, v, x);
+        } else if g.neighbors(v).into_iter().filter(|w| outputs.contains(w)).count() > 1 {
+            unfuse_boundary(g, o);
+        }
+    }
+}
+
+// Put a phase-free spider between boundary `b` and its neighbor and return
+// it. The new spider reaches the neighbor through a Hadamard edge, so it
+// can take part in pivots.
+fn unfuse_boundary(g: &mut ZXGraph, b: usize) -> usize {
+    let v = g.neighbors(b)[0];
+    unfuse_boundary_from(g, b, v)
+}
+
+fn unfuse_boundary_from(g: &mut ZXGraph, b: usize, v: usize) -> usize {
+    let kind = g.edge(b, v).unwrap();
+    g.remove_edge(b, v);
+    let x = g.add_vertex(VertexKind::Z, 0.0);
+    g.set_edge(b, x, kind.toggled());
+    g.set_edge(x, v, EdgeKind::Hadamard);
+    x
+}
+
+// Hadamards on the output edges, then frontier phases, then CZs between
+// frontier spiders
+fn clean_frontier(g: &mut ZXGraph, frontier: &[Option<usize>], outputs: &[usize], rev: &mut Vec<IROp>) {
+    for (q, v) in frontier.iter().enumerate() {
+        let Some(v) = *v else { continue };
+        if g.edge(v, outputs[q]) == Some(EdgeKind::Hadamard) {
+            rev.push(IROp::H(q));
+            g.set_edge(v, outputs[q], EdgeKind::Simple);
+        }
+    }
+    for (q, v) in frontier.iter().enumerate() {
+        let Some(v) = *v else { continue };
+        if g.phase(v) != 0.0 {
+            rev.extend(z_rotation(q, g.phase(v) * PI).into_iter().rev());
+            g.set_phase(v, 0.0);
+        }
+    }
+    for (q1, v1) in frontier.iter().enumerate() {
+        let Some(v1) = *v1 else { continue };
+        for (q2, v2) in frontier.iter().enumerate().skip(q1 + 1) {
+            let Some(v2) = *v2 else { continue };
+            if g.edge(v1, v2).is_some() {
+                rev.push(IROp::CZ(q1, q2));
+                g.remove_edge(v1, v2);
+            }
+        }
+    }
+}
+
+// A frontier qubit and a neighboring gadget axle, if there is one
+fn gadget_next_to_frontier(g: &ZXGraph, frontier: &[Option<usize>], neighbors: &BTreeSet<usize>) -> Option<(usize, usize)> {
+    neighbors.iter().copied()
+        .filter(|&w| g.neighbors(w).into_iter().any(|l| g.kind(l) == VertexKind::Z && g.degree(l) == 1))
+        .find_map(|axle| {
+            frontier.iter().position(|v| v.is_some_and(|v| g.edge(v, axle).is_some())).map(|q| (q, axle))
+        })
+}
+
+// Reduce `rows` over GF(2) so every pivot column has a single 1, returning
+// the row additions (source, target) in order. Rows are never swapped, as
+// a swap would cost three CNOTs.
+fn eliminate(rows: &mut [Vec<bool>]) -> Vec<(usize, usize)> {
+    let mut ops = Vec::new();
+    let mut used = vec![false; rows.len()];
+    let width = rows.first().map_or(0, |r| r.len());
+    for col in 0..width {
+        let Some(p) = (0..rows.len()).find(|&r| !used[r] && rows[r][col]) else { continue };
+        used[p] = true;
+        for r in 0..rows.len() {
+            if r != p && rows[r][col] {
+                let source = rows[p].clone();
+                for (x, y) in rows[r].iter_mut().zip(source) {
+                    *x ^= y;
+                }
+                ops.push((p, r));
+            }
+        }
+    }
+    ops
+}
@@ -0,0 +1,359 @@
+// ZX-diagrams as simple graphs. Vertices are boundaries (circuit inputs and
+// outputs) or Z/X spiders with a phase; edges are plain wires or Hadamard
+// edges. Phases are kept in units of pi, in [0, 2), and snapped to exact
+// multiples of 1/4 so Clifford+T phases compare exactly. Scalars are not
+// tracked: every rewrite holds up to a non-zero factor, which for a unitary
+// diagram is a global phase.
+use crate::compiler::codegen::toffoli_ops;
+use crate::compiler::ir::IROp;
+use crate::compiler::zx::rules::fuse_spiders;
+use std::collections::BTreeMap;
+use std::f64::consts::PI;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VertexKind {
+    Boundary,
+    Z,
+    X,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EdgeKind {
+    Simple,
+    Hadamard,
+}
+
+impl EdgeKind {
+    // Two edges in a row through a phase-free degree-2 spider
+    pub fn then(self, other: EdgeKind) -> EdgeKind {
+        if self == other { EdgeKind::Simple } else { EdgeKind::Hadamard }
+    }
+
+    pub fn toggled(self) -> EdgeKind {
+        self.then(EdgeKind::Hadamard)
+    }
+}
+
+pub fn normalize(phase: f64) -> f64 {
+    let p = phase.rem_euclid(2.0);
+    let quarters = (p * 4.0).round();
+    let p = if (p * 4.0 - quarters).abs() < 1e-9 { quarters / 4.0 } else { p };
+    if p >= 2.0 { 0.0 } else { p }
+}
+
+pub fn is_pauli(phase: f64) -> bool {
+    phase == 0.0 || phase == 1.0
+}
+
+pub fn is_clifford(phase: f64) -> bool {
+    is_pauli(phase) || phase == 0.5 || phase == 1.5
+}
+
+// Odd multiples of 1/4, i.e. one T gate each
+pub fn is_t_like(phase: f64) -> bool {
+    phase == 0.25 || phase == 0.75 || phase == 1.25 || phase == 1.75
+}
+
+// Ops that `ZXGraph::from_circuit` can translate
+pub fn is_supported(op: &IROp) -> bool {
+    match op {
+        IROp::CU3(..) => false,
+        IROp::MCX(controls, _) => (1..=2).contains(&controls.len()),
+        op => op.is_unitary(),
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Vertex {
+    kind: VertexKind,
+    phase: f64,
+    neighbors: BTreeMap<usize, EdgeKind>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ZXGraph {
+    vertices: BTreeMap<usize, Vertex>,
+    next_id: usize,
+    inputs: Vec<usize>,
+    outputs: Vec<usize>,
+}
+
+impl ZXGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Diagram of a unitary circuit on `num_qubits` qubits, or None if it
+    // contains an op without a translation (measurements, control flow,
+    // CU3, MCX with more than two controls)
+    pub fn from_circuit(num_qubits: usize, ops: &[IROp]) -> Option<ZXGraph> {
+        let mut g = ZXGraph::new();
+        let inputs: Vec<usize> = (0..num_qubits).map(|_| g.add_vertex(VertexKind::Boundary, 0.0)).collect();
+        let mut builder = Builder { last: inputs.clone(), hadamard: vec![false; num_qubits], g };
+        for op in ops {
+            if !builder.gate(op) {
+                return None;
+            }
+        }
+        let Builder { mut g, last, hadamard } = builder;
+        let outputs: Vec<usize> = (0..num_qubits).map(|_| g.add_vertex(VertexKind::Boundary, 0.0)).collect();
+        for q in 0..num_qubits {
+            g.connect(last[q], outputs[q], if hadamard[q] { EdgeKind::Hadamard } else { EdgeKind::Simple });
+        }
+        g.inputs = inputs;
+        g.outputs = outputs;
+        Some(g)
+    }
+
+    pub fn inputs(&self) -> &[usize] {
+        &self.inputs
+    }
+
+    pub fn outputs(&self) -> &[usize] {
+        &self.outputs
+    }
+
+    pub fn vertices(&self) -> Vec<usize> {
+        self.vertices.keys().copied().collect()
+    }
+
+    pub fn num_vertices(&self) -> usize {
+        self.vertices.len()
+    }
+
+    pub fn num_edges(&self) -> usize {
+        self.vertices.values().map(|v| v.neighbors.len()).sum::<usize>() / 2
+    }
+
+    pub fn contains(&self, v: usize) -> bool {
+        self.vertices.contains_key(&v)
+    }
+
+    pub fn kind(&self, v: usize) -> VertexKind {
+        self.vertices[&v].kind
+    }
+
+    pub fn phase(&self, v: usize) -> f64 {
+        self.vertices[&v].phase
+    }
+
+    pub fn set_phase(&mut self, v: usize, phase: f64) {
+        self.vertex_mut(v).phase = normalize(phase);
+    }
+
+    pub fn add_to_phase(&mut self, v: usize, phase: f64) {
+        let p = self.phase(v) + phase;
+        self.set_phase(v, p);
+    }
+
+    pub fn neighbors(&self, v: usize) -> Vec<usize> {
+        self.vertices[&v].neighbors.keys().copied().collect()
+    }
+
+    pub fn degree(&self, v: usize) -> usize {
+        self.vertices[&v].neighbors.len()
+    }
+
+    pub fn edge(&self, u: usize, v: usize) -> Option<EdgeKind> {
+        self.vertices[&u].neighbors.get(&v).copied()
+    }
+
+    pub fn is_boundary(&self, v: usize) -> bool {
+        self.kind(v) == VertexKind::Boundary
+    }
+
+    // A Z spider with no boundary among its neighbors
+    pub fn is_interior(&self, v: usize) -> bool {
+        self.kind(v) == VertexKind::Z && self.neighbors(v).iter().all(|&w| !self.is_boundary(w))
+    }
+
+    // Spiders with a phase that needs a T gate
+    pub fn t_count(&self) -> usize {
+        self.vertices.values().filter(|v| v.kind != VertexKind::Boundary && is_t_like(v.phase)).count()
+    }
+
+    pub fn add_vertex(&mut self, kind: VertexKind, phase: f64) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.vertices.insert(id, Vertex { kind, phase: normalize(phase), neighbors: BTreeMap::new() });
+        id
+    }
+
+    pub fn remove_vertex(&mut self, v: usize) {
+        if let Some(vertex) = self.vertices.remove(&v) {
+            for w in vertex.neighbors.keys() {
+                self.vertex_mut(*w).neighbors.remove(&v);
+            }
+        }
+    }
+
+    pub fn remove_edge(&mut self, u: usize, v: usize) {
+        self.vertex_mut(u).neighbors.remove(&v);
+        self.vertex_mut(v).neighbors.remove(&u);
+    }
+
+    // Overwrite the edge between `u` and `v`, ignoring any existing one
+    pub fn set_edge(&mut self, u: usize, v: usize, kind: EdgeKind) {
+        self.vertex_mut(u).neighbors.insert(v, kind);
+        self.vertex_mut(v).neighbors.insert(u, kind);
+    }
+
+    // Add an edge, resolving parallel edges and self-loops so the graph
+    // stays simple. Between two spiders an edge either lets them fuse (plain
+    // for equal colours, Hadamard otherwise) or not: two fusing edges act as
+    // one, two non-fusing edges cancel (Hopf rule), and one of each leaves
+    // the fusing edge plus a pi phase from the self-loop left after fusion.
+    pub fn connect(&mut self, u: usize, v: usize, kind: EdgeKind) {
+        if u == v {
+            if kind == EdgeKind::Hadamard {
+                self.add_to_phase(u, 1.0);
+            }
+            return;
+        }
+        let Some(old) = self.edge(u, v) else {
+            self.set_edge(u, v, kind);
+            return;
+        };
+        let fusing = if self.kind(u) == self.kind(v) { EdgeKind::Simple } else { EdgeKind::Hadamard };
+        if old == kind {
+            if kind != fusing {
+                self.remove_edge(u, v);
+            }
+        } else {
+            self.set_edge(u, v, fusing);
+            self.add_to_phase(u, 1.0);
+        }
+    }
+
+    // Merge spider `v` into `u` (same colour, joined by a plain edge)
+    pub fn fuse(&mut self, u: usize, v: usize) {
+        let vertex = self.vertices.remove(&v).expect("fused vertex exists");
+        for w in vertex.neighbors.keys() {
+            self.vertex_mut(*w).neighbors.remove(&v);
+        }
+        self.add_to_phase(u, vertex.phase);
+        for (w, kind) in vertex.neighbors {
+            if w != u {
+                self.connect(u, w, kind);
+            }
+        }
+    }
+
+    // Bring the diagram to graph-like form: only Z spiders, joined to each
+    // other by Hadamard edges. X spiders change colour by toggling their
+    // edges, then every plain edge between Z spiders is fused away.
+    pub fn to_graph_like(&mut self) {
+        for v in self.vertices() {
+            if self.kind(v) != VertexKind::X {
+                continue;
+            }
+            for w in self.neighbors(v) {
+                let kind = self.edge(v, w).unwrap().toggled();
+                self.set_edge(v, w, kind);
+            }
+            self.vertex_mut(v).kind = VertexKind::Z;
+        }
+        fuse_spiders(self);
+    }
+
+    fn vertex_mut(&mut self, v: usize) -> &mut Vertex {
+        self.vertices.get_mut(&v).expect("vertex exists")
+    }
+}
+
+// Lays gates down left to right, one spider per gate leg. Hadamards are
+// not spiders: they flip the type of the next edge on their wire.
+struct Builder {
+    g: ZXGraph,
+    last: Vec<usize>,
+    hadamard: Vec<bool>,
+}
+
+impl Builder {
+    fn spider(&mut self, q: usize, kind: VertexKind, phase: f64) -> usize {
+        let v = self.g.add_vertex(kind, phase);
+        let edge = if self.hadamard[q] { EdgeKind::Hadamard } else { EdgeKind::Simple };
+        self.g.connect(self.last[q], v, edge);
+        self.last[q] = v;
+        self.hadamard[q] = false;
+        v
+    }
+
+    fn z(&mut self, q: usize, phase: f64) {
+        self.spider(q, VertexKind::Z, phase);
+    }
+
+    fn x(&mut self, q: usize, phase: f64) {
+        self.spider(q, VertexKind::X, phase);
+    }
+
+    fn all(&mut self, ops: &[IROp]) -> bool {
+        ops.iter().all(|op| self.gate(op))
+    }
+
+    fn gate(&mut self, op: &IROp) -> bool {
+        match *op {
+            IROp::I(_) => {}
+            IROp::H(q) => self.hadamard[q] = !self.hadamard[q],
+            IROp::X(q) => self.x(q, 1.0),
+            IROp::Y(q) => {
+                self.z(q, 1.0);
+                self.x(q, 1.0);
+            }
+            IROp::Z(q) => self.z(q, 1.0),
+            IROp::S(q) => self.z(q, 0.5),
+            IROp::Sdg(q) => self.z(q, -0.5),
+            IROp::T(q) => self.z(q, 0.25),
+            IROp::Tdg(q) => self.z(q, -0.25),
+            IROp::SX(q) => self.x(q, 0.5),
+            IROp::SXdg(q) => self.x(q, -0.5),
+            IROp::RZ(q, a) => self.z(q, a / PI),
+            IROp::RX(q, a) => self.x(q, a / PI),
+            // RY = S RX S†
+            IROp::RY(q, a) => {
+                self.z(q, -0.5);
+                self.x(q, a / PI);
+                self.z(q, 0.5);
+            }
+            IROp::U3(q, theta, phi, lambda) => {
+                self.z(q, lambda / PI);
+                self.gate(&IROp::RY(q, theta));
+                self.z(q, phi / PI);
+            }
+            IROp::CNOT(c, t) => {
+                let a = self.spider(c, VertexKind::Z, 0.0);
+                let b = self.spider(t, VertexKind::X, 0.0);
+                self.g.connect(a, b, EdgeKind::Simple);
+            }
+            IROp::CZ(p, q) => {
+                let a = self.spider(p, VertexKind::Z, 0.0);
+                let b = self.spider(q, VertexKind::Z, 0.0);
+                self.g.connect(a, b, EdgeKind::Hadamard);
+            }
+            IROp::SWAP(p, q) => {
+                self.last.swap(p, q);
+                self.hadamard.swap(p, q);
+            }
+            IROp::CPhase(p, q, a) => {
+                return self.all(&[
+                    IROp::RZ(p, a / 2.0), IROp::RZ(q, a / 2.0),
+                    IROp::CNOT(p, q), IROp::RZ(q, -a / 2.0), IROp::CNOT(p, q),
+                ]);
+            }
+            IROp::Toffoli(a, b, c) => return self.all(&toffoli_ops(a, b, c)),
+            IROp::CCZ(a, b, c) => {
+                let mut ops = vec![IROp::H(c)];
+                ops.extend(toffoli_ops(a, b, c));
+                ops.push(IROp::H(c));
+                return self.all(&ops);
+            }
+            IROp::MCX(ref controls, t) => match controls[..] {
+                [c] => return self.gate(&IROp::CNOT(c, t)),
+                [a, b] => return self.gate(&IROp::Toffoli(a, b, t)),
+                _ => return false,
+            },
+            _ => return false,
+        }
+        true
+    }
+}
@@ -0,0 +1,10 @@
+// ZX-calculus diagrams for circuit optimization. A circuit becomes a graph
+// of spiders (`graph`), the rewrite rules in `rules` shrink it and move
+// non-Clifford phases together, and `extract` turns it back into gates.
+pub mod extract;
+pub mod graph;
+pub mod rules;
+
+pub use extract::extract_circuit;
+pub use graph::{EdgeKind, VertexKind, ZXGraph};
+pub use rules::full_reduce;
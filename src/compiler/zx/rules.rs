@@ -0,0 +1,257 @@
+// Rewrite rules on graph-like diagrams, following Duncan, Kissinger,
+// Perdrix & van de Wetering (2020) and Kissinger & van de Wetering (2020).
+// Each rule function applies its rewrite until no match is left and
+// returns how often it fired. All rules keep the diagram graph-like and
+// preserve gflow, so `extract` can always turn the result into a circuit.
+//
+// A phase gadget is a degree-1 "leaf" spider hanging off a Pauli "axle"
+// spider; the axle's other neighbors are the gadget's targets. The rules
+// below avoid moving non-Pauli phases onto axles, so gadgets stay intact
+// until `fuse_gadgets` merges them.
+use crate::compiler::zx::graph::{is_clifford, is_pauli, EdgeKind, VertexKind, ZXGraph};
+use std::collections::{BTreeMap, BTreeSet};
+
+// Simplify as far as the rules go: Clifford spiders are removed wherever
+// possible, the remaining non-Clifford phases are moved into phase gadgets
+// and gadgets acting on the same qubits are merged.
+pub fn full_reduce(g: &mut ZXGraph) {
+    g.to_graph_like();
+    interior_clifford_simp(g);
+    pivot_gadgets(g);
+    loop {
+        interior_clifford_simp(g);
+        let fused = fuse_gadgets(g);
+        interior_clifford_simp(g);
+        let pivoted = pivot_gadgets(g);
+        if fused + pivoted == 0 {
+            break;
+        }
+    }
+}
+
+// Identity removal, pivoting and local complementation until none applies
+pub fn interior_clifford_simp(g: &mut ZXGraph) -> usize {
+    let mut total = 0;
+    loop {
+        let n = remove_ids(g) + pivot_paulis(g) + local_complement(g);
+        if n == 0 {
+            return total;
+        }
+        total += n;
+    }
+}
+
+fn is_leaf(g: &ZXGraph, v: usize) -> bool {
+    g.kind(v) == VertexKind::Z && g.degree(v) == 1
+}
+
+// Whether `v` has a leaf neighbor other than `except`
+fn has_leaf(g: &ZXGraph, v: usize, except: usize) -> bool {
+    g.neighbors(v).into_iter().any(|w| w != except && is_leaf(g, w))
+}
+
+fn all_hadamard(g: &ZXGraph, v: usize) -> bool {
+    g.neighbors(v).into_iter().all(|w| g.edge(v, w) == Some(EdgeKind::Hadamard))
+}
+
+// Merge every pair of Z spiders joined by a plain edge
+pub fn fuse_spiders(g: &mut ZXGraph) -> usize {
+    let mut count = 0;
+    loop {
+        let pair = g.vertices().into_iter()
+            .filter(|&u| g.kind(u) == VertexKind::Z)
+            .find_map(|u| {
+                g.neighbors(u).into_iter()
+                    .find(|&w| g.kind(w) == VertexKind::Z && g.edge(u, w) == Some(EdgeKind::Simple))
+                    .map(|w| (u, w))
+            });
+        let Some((u, v)) = pair else { return count };
+        g.fuse(u, v);
+        count += 1;
+    }
+}
+
+// A phase-free spider between two Hadamard edges is a plain wire, so its
+// neighbors fuse
+pub fn remove_ids(g: &mut ZXGraph) -> usize {
+    let mut count = 0;
+    loop {
+        let found = g.vertices().into_iter().find_map(|v| {
+            if !g.is_interior(v) || g.phase(v) != 0.0 || g.degree(v) != 2 || !all_hadamard(g, v) {
+                return None;
+            }
+            let ns = g.neighbors(v);
+            // Fusing an axle into another spider would spoil its gadget
+            if ns.iter().any(|&w| has_leaf(g, w, v)) {
+                return None;
+            }
+            Some((v, ns[0], ns[1]))
+        });
+        let Some((v, a, b)) = found else { return count };
+        g.remove_vertex(v);
+        g.connect(a, b, EdgeKind::Simple);
+        if g.edge(a, b) == Some(EdgeKind::Simple) {
+            g.fuse(a, b);
+        }
+        count += 1;
+    }
+}
+
+// Remove a spider with phase ±pi/2: its neighborhood is complemented and
+// every neighbor picks up the opposite phase
+pub fn local_complement(g: &mut ZXGraph) -> usize {
+    let mut count = 0;
+    loop {
+        let found = g.vertices().into_iter().find(|&v| {
+            let p = g.phase(v);
+            (p == 0.5 || p == 1.5)
+                && g.degree(v) >= 2
+                && g.is_interior(v)
+                && all_hadamard(g, v)
+                && g.neighbors(v).into_iter().all(|w| !has_leaf(g, w, v))
+        });
+        let Some(v) = found else { return count };
+        let phase = g.phase(v);
+        let ns = g.neighbors(v);
+        g.remove_vertex(v);
+        for (i, &a) in ns.iter().enumerate() {
+            g.add_to_phase(a, -phase);
+            for &b in &ns[i + 1..] {
+                g.connect(a, b, EdgeKind::Hadamard);
+            }
+        }
+        count += 1;
+    }
+}
+
+// Remove two adjacent Pauli spiders. Writing A for the neighbors of `u`
+// only, B for those of `v` only and C for the shared ones, the edges
+// between A, B and C are complemented and A gets v's phase, B gets u's
+// phase and C gets both plus pi.
+pub fn pivot(g: &mut ZXGraph, u: usize, v: usize) {
+    let nu: BTreeSet<usize> = g.neighbors(u).into_iter().filter(|&w| w != v).collect();
+    let nv: BTreeSet<usize> = g.neighbors(v).into_iter().filter(|&w| w != u).collect();
+    let both: Vec<usize> = nu.intersection(&nv).copied().collect();
+    let only_u: Vec<usize> = nu.difference(&nv).copied().collect();
+    let only_v: Vec<usize> = nv.difference(&nu).copied().collect();
+    let (pu, pv) = (g.phase(u), g.phase(v));
+    g.remove_vertex(u);
+    g.remove_vertex(v);
+    for (xs, ys) in [(&only_u, &only_v), (&only_u, &both), (&only_v, &both)] {
+        for &x in xs {
+            for &y in ys {
+                g.connect(x, y, EdgeKind::Hadamard);
+            }
+        }
+    }
+    for &w in &only_u {
+        g.add_to_phase(w, pv);
+    }
+    for &w in &only_v {
+        g.add_to_phase(w, pu);
+    }
+    for &w in &both {
+        g.add_to_phase(w, pu + pv + 1.0);
+    }
+}
+
+// Leaves are left to the gadget rules: removing a Clifford leaf with
+// lcomp or a pivot is sound but can destroy the diagram's gflow
+fn pivotable(g: &ZXGraph, v: usize) -> bool {
+    g.degree(v) >= 2 && g.is_interior(v) && all_hadamard(g, v)
+}
+
+pub fn pivot_paulis(g: &mut ZXGraph) -> usize {
+    let mut count = 0;
+    loop {
+        let found = g.vertices().into_iter()
+            .filter(|&u| is_pauli(g.phase(u)) && pivotable(g, u))
+            .find_map(|u| {
+                g.neighbors(u).into_iter()
+                    .find(|&v| v > u && is_pauli(g.phase(v)) && pivotable(g, v))
+                    .map(|v| (u, v))
+            });
+        let Some((u, v)) = found else { return count };
+        pivot(g, u, v);
+        count += 1;
+    }
+}
+
+// Pivot a Pauli spider `u` with a non-Clifford neighbor `v` after moving
+// v's phase out into a new gadget, which removes `u` from the diagram
+pub fn pivot_gadgets(g: &mut ZXGraph) -> usize {
+    let mut count = 0;
+    loop {
+        let found = g.vertices().into_iter()
+            .filter(|&u| {
+                is_pauli(g.phase(u)) && pivotable(g, u) && g.degree(u) >= 2 && !has_leaf(g, u, usize::MAX)
+            })
+            .find_map(|u| {
+                g.neighbors(u).into_iter()
+                    .find(|&v| !is_clifford(g.phase(v)) && pivotable(g, v) && g.degree(v) >= 2)
+                    .map(|v| (u, v))
+            });
+        let Some((u, v)) = found else { return count };
+        let leaf = g.add_vertex(VertexKind::Z, g.phase(v));
+        let axle = g.add_vertex(VertexKind::Z, 0.0);
+        g.set_phase(v, 0.0);
+        g.connect(v, axle, EdgeKind::Hadamard);
+        g.connect(axle, leaf, EdgeKind::Hadamard);
+        pivot(g, u, v);
+        count += 1;
+    }
+}
+
+// Gadgets as (axle, leaf) pairs grouped by their target sets
+fn gadgets(g: &ZXGraph) -> BTreeMap<Vec<usize>, Vec<(usize, usize)>> {
+    let mut groups: BTreeMap<Vec<usize>, Vec<(usize, usize)>> = BTreeMap::new();
+    let mut seen = BTreeSet::new();
+    for leaf in g.vertices() {
+        if !is_leaf(g, leaf) || is_clifford(g.phase(leaf)) {
+            continue;
+        }
+        let axle = g.neighbors(leaf)[0];
+        if !is_pauli(g.phase(axle)) || !pivotable(g, axle) || !seen.insert(axle) || has_leaf(g, axle, leaf) {
+            continue;
+        }
+        let targets: Vec<usize> = g.neighbors(axle).into_iter().filter(|&w| w != leaf).collect();
+        groups.entry(targets).or_default().push((axle, leaf));
+    }
+    groups
+}
+
+// A pi on the axle flips the sign of the leaf's phase
+fn gadget_phase(g: &ZXGraph, (axle, leaf): (usize, usize)) -> f64 {
+    if g.phase(axle) == 1.0 { -g.phase(leaf) } else { g.phase(leaf) }
+}
+
+// Gadgets with the same targets add up; a gadget on a single target is
+// just a phase on it
+pub fn fuse_gadgets(g: &mut ZXGraph) -> usize {
+    let mut count = 0;
+    loop {
+        let found = gadgets(g).into_iter().find(|(targets, group)| match targets[..] {
+            [target] => !has_leaf(g, target, usize::MAX),
+            _ => group.len() >= 2,
+        });
+        let Some((targets, group)) = found else { return count };
+        if let [target] = targets[..] {
+            let phase = gadget_phase(g, group[0]);
+            g.add_to_phase(target, phase);
+            g.remove_vertex(group[0].0);
+            g.remove_vertex(group[0].1);
+        } else {
+            let total: f64 = group.iter().map(|&gadget| gadget_phase(g, gadget)).sum();
+            let (axle, leaf) = group[0];
+            g.set_phase(axle, 0.0);
+            g.set_phase(leaf, total);
+            // A gadget with no phase left is the identity
+            let keep = if g.phase(leaf) == 0.0 { 0 } else { 1 };
+            for &(other_axle, other_leaf) in &group[keep..] {
+                g.remove_vertex(other_axle);
+                g.remove_vertex(other_leaf);
+            }
+        }
+        count += 1;
+    }
+}
@@ -2,3 +2,5 @@ pub mod statevector;
 pub mod math;
 pub mod algorithms;
 pub mod tableau;
+pub mod compiler;
+pub mod runtime;
@@ -1,61 +1,237 @@
-use crate::compiler::ir::IROp;
+use crate::compiler::ir::{IROp, IRProgram};
 use crate::runtime::{tableau_backend::TableauSimulator, statevector_backend::StatevectorSimulator};
+use crate::runtime::stabilizer_sum_backend::StabilizerSumSimulator;
+use std::fmt;
 
+// Guard against `while` loops whose condition never changes
+pub const MAX_LOOP_ITERATIONS: usize = 100_000;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RuntimeError {
+    // A `while` loop ran MAX_LOOP_ITERATIONS times without its condition
+    // becoming false
+    LoopLimit { iterations: usize },
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RuntimeError::LoopLimit { iterations } => write!(f, "while loop exceeded {} iterations", iterations),
+        }
+    }
+}
+
+impl std::error::Error for RuntimeError {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum BackendType {
     Tableau,
+    // The tableau plus a sum over its stabilizer basis, entered by
+    // injecting a magic state
+    StabilizerSum,
     Statevector,
     RankDecomposition,
 }
 
+// How much of a run each backend handled
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ExecutionStats {
+    pub tableau_ops: usize,
+    pub statevector_ops: usize,
+    pub stabilizer_sum_ops: usize,
+    pub magic_states: usize,
+    pub promotions: usize,
+    pub demotions: usize,
+}
+
 pub struct RuntimeController {
     backend_type: BackendType,
     tableau: Option<TableauSimulator>,
+    stabilizer_sum: Option<StabilizerSumSimulator>,
     statevector: Option<StatevectorSimulator>,
+    clbits: Vec<bool>,
+    stats: ExecutionStats,
 }
 
 impl RuntimeController {
-    pub fn new(num_qubits: usize) -> Self {
+    pub fn new(num_qubits: usize, num_clbits: usize) -> Self {
         Self {
             backend_type: BackendType::Tableau,
             tableau: Some(TableauSimulator::new(num_qubits)),
+            stabilizer_sum: None,
             statevector: None,
+            clbits: vec![false; num_clbits],
+            stats: ExecutionStats::default(),
         }
     }
 
-    pub fn execute(&mut self, ops: &[IROp]) {
+    // Run a whole program from |0...0> and return the classical register
+    pub fn run(program: &IRProgram) -> Result<Vec<bool>, RuntimeError> {
+        let mut controller = Self::new(program.num_qubits, program.num_clbits);
+        controller.execute(&program.ops)?;
+        Ok(controller.clbits)
+    }
+
+    pub fn clbits(&self) -> &[bool] {
+        &self.clbits
+    }
+
+    pub fn backend_type(&self) -> &BackendType {
+        &self.backend_type
+    }
+
+    pub fn stats(&self) -> ExecutionStats {
+        self.stats
+    }
+
+    // Stops at the first error, leaving the state as it was at that point
+    pub fn execute(&mut self, ops: &[IROp]) -> Result<(), RuntimeError> {
         for op in ops {
             match op {
-                IROp::H(q) | IROp::S(q) | IROp::CNOT(_, _) => self.apply_clifford(op),
-                IROp::T(q) | IROp::RZ(q, _) => self.handle_nonclifford(op),
-                _ => {}
+                IROp::Measure(q, c) => self.clbits[*c] = self.measure(*q),
+                IROp::Barrier(_) => {}
+                IROp::Promote => {
+                    if self.backend_type == BackendType::Tableau {
+                        self.promote_to_rank_decomposition();
+                    }
+                }
+                IROp::Demote => self.try_demote(),
+                IROp::Conditional { clbits, value, op } => {
+                    if self.register_value(clbits) == *value {
+                        self.execute(std::slice::from_ref(op))?;
+                    }
+                }
+                IROp::IfElse { clbits, value, then_ops, else_ops } => {
+                    if self.register_value(clbits) == *value {
+                        self.execute(then_ops)?;
+                    } else {
+                        self.execute(else_ops)?;
+                    }
+                }
+                IROp::While { clbits, value, body } => {
+                    let mut iterations = 0;
+                    while self.register_value(clbits) == *value {
+                        if iterations == MAX_LOOP_ITERATIONS {
+                            return Err(RuntimeError::LoopLimit { iterations });
+                        }
+                        iterations += 1;
+                        self.execute(body)?;
+                    }
+                }
+                IROp::MagicState(_) => self.inject(op),
+                IROp::Reset(_) => self.apply_clifford(op),
+                op if op.is_clifford() => self.apply_clifford(op),
+                _ => self.handle_nonclifford(op),
+            }
+        }
+        Ok(())
+    }
+
+    // Little-endian integer value of a group of classical bits
+    pub fn register_value(&self, clbits: &[usize]) -> u64 {
+        clbits.iter().enumerate().fold(0, |acc, (i, &c)| acc | ((self.clbits[c] as u64) << i))
+    }
+
+    fn measure(&mut self, qubit: usize) -> bool {
+        match self.backend_type {
+            BackendType::Tableau => {
+                self.stats.tableau_ops += 1;
+                self.tableau.as_mut().unwrap().measure_z(qubit)
+            }
+            BackendType::StabilizerSum => {
+                self.stats.stabilizer_sum_ops += 1;
+                let outcome = self.stabilizer_sum.as_mut().unwrap().measure_z(qubit);
+                self.leave_stabilizer_sum();
+                outcome
+            }
+            _ => {
+                self.stats.statevector_ops += 1;
+                self.statevector.as_mut().unwrap().measure_qubit(qubit)
             }
         }
     }
 
     fn apply_clifford(&mut self, op: &IROp) {
-        if let BackendType::Tableau = self.backend_type {
-            self.tableau.as_mut().unwrap().apply_ir(op);
-        } else {
-            self.statevector.as_mut().unwrap().apply_ir(op);
+        match self.backend_type {
+            BackendType::Tableau => {
+                self.stats.tableau_ops += 1;
+                self.tableau.as_mut().unwrap().apply_ir(op);
+            }
+            BackendType::StabilizerSum => self.apply_stabilizer_sum(op),
+            _ => {
+                self.stats.statevector_ops += 1;
+                self.statevector.as_mut().unwrap().apply_ir(op);
+            }
         }
     }
 
     fn handle_nonclifford(&mut self, op: &IROp) {
-        if matches!(self.backend_type, BackendType::Tableau) {
-            self.promote_to_rank_decomposition();
+        match self.backend_type {
+            BackendType::StabilizerSum if StabilizerSumSimulator::supports(op) => return self.apply_stabilizer_sum(op),
+            BackendType::Tableau | BackendType::StabilizerSum => self.promote_to_rank_decomposition(),
+            _ => {}
         }
+        self.stats.statevector_ops += 1;
         self.statevector.as_mut().unwrap().apply_ir(op);
     }
 
+    // Magic states stay next to the tableau instead of promoting it. Once
+    // promoted, they are just another non-Clifford op.
+    fn inject(&mut self, op: &IROp) {
+        if self.backend_type == BackendType::Tableau {
+            let tableau = self.tableau.take().unwrap();
+            self.stabilizer_sum = Some(StabilizerSumSimulator::from(tableau));
+            self.backend_type = BackendType::StabilizerSum;
+        }
+        if self.backend_type == BackendType::StabilizerSum {
+            self.stats.magic_states += 1;
+            self.apply_stabilizer_sum(op);
+        } else {
+            self.handle_nonclifford(op);
+        }
+    }
+
+    fn apply_stabilizer_sum(&mut self, op: &IROp) {
+        self.stats.stabilizer_sum_ops += 1;
+        self.stabilizer_sum.as_mut().unwrap().apply_ir(op);
+        if matches!(op, IROp::Reset(_)) {
+            self.leave_stabilizer_sum();
+        }
+    }
+
+    // Measurements merge terms, and a single one is a stabilizer state
+    fn leave_stabilizer_sum(&mut self) {
+        if let Some(tableau) = self.stabilizer_sum.as_ref().unwrap().to_tableau() {
+            self.tableau = Some(tableau);
+            self.stabilizer_sum = None;
+            self.backend_type = BackendType::Tableau;
+        }
+    }
+
+    // Go back to the tableau if the state is a stabilizer state again
+    fn try_demote(&mut self) {
+        if matches!(self.backend_type, BackendType::Tableau | BackendType::StabilizerSum) {
+            return;
+        }
+        let state = &self.statevector.as_ref().unwrap().state;
+        if let Some(tableau) = TableauSimulator::from_statevector(state) {
+            self.tableau = Some(tableau);
+            self.statevector = None;
+            self.backend_type = BackendType::Tableau;
+            self.stats.demotions += 1;
+        }
+    }
+
     fn promote_to_rank_decomposition(&mut self) {
-        // Convert tableau stabilizers into statevector amplitudes
-        let vec = self.tableau.as_ref().unwrap().to_statevector();
+        // Convert tableau stabilizers (and any terms over them) into
+        // statevector amplitudes
+        let vec = match self.stabilizer_sum.take() {
+            Some(sum) => sum.to_statevector(),
+            None => self.tableau.as_ref().unwrap().to_statevector(),
+        };
         self.statevector = Some(StatevectorSimulator::from(vec));
         self.tableau = None;
         self.backend_type = BackendType::RankDecomposition;
-    }
-
-    fn maybe_demote(&mut self) {
-        // TODO: heuristic based on gate locality / entanglement entropy
+        self.stats.promotions += 1;
     }
 }
@@ -0,0 +1,4 @@
+pub mod controller;
+pub mod stabilizer_sum_backend;
+pub mod statevector_backend;
+pub mod tableau_backend;
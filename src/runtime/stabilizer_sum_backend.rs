@@ -0,0 +1,291 @@
+// Clifford+T states kept as a sum over the stabilizer basis of a tableau,
+// the pure-state case of Yoder's generalized stabilizer formalism:
+//
+//   |psi> = sum_x c_x D^x |phi>
+//
+// |phi> is the tableau's stabilizer state and D^x the product of the
+// destabilizers selected by the bits of x. The D^x |phi> are orthonormal,
+// and a Clifford conjugates destabilizers into the destabilizers of the
+// updated tableau, so Clifford gates leave the coefficients alone. A phase
+// gate diag(1, e^{it}) = a I + b Z splits each term in two. Measurements
+// either filter the terms or move the tableau to the collapsed state and
+// re-express the terms there, which is where they merge again. So every
+// magic state at most doubles the number of terms until it is measured.
+use crate::compiler::ir::IROp;
+use crate::math::complex::Complex;
+use crate::runtime::tableau_backend::TableauSimulator;
+use crate::tableau::simulator::g;
+use rand::Rng;
+use std::collections::BTreeMap;
+use std::f64::consts::{FRAC_1_SQRT_2, FRAC_PI_4};
+
+// Terms below this weight are dropped
+const PRUNE: f64 = 1e-14;
+
+// i^0 .. i^3
+const I_POWERS: [Complex; 4] = [
+    Complex { re: 1.0, im: 0.0 },
+    Complex { re: 0.0, im: 1.0 },
+    Complex { re: -1.0, im: 0.0 },
+    Complex { re: 0.0, im: -1.0 },
+];
+
+// i^phase times a Pauli string. Products of tableau rows need not be
+// Hermitian, so the phase is a power of i rather than a sign.
+#[derive(Clone, Debug)]
+struct Pauli {
+    x: Vec<bool>,
+    z: Vec<bool>,
+    phase: usize,
+}
+
+impl Pauli {
+    fn identity(n: usize) -> Self {
+        Pauli { x: vec![false; n], z: vec![false; n], phase: 0 }
+    }
+
+    fn z_on(n: usize, qubit: usize) -> Self {
+        let mut p = Self::identity(n);
+        p.z[qubit] = true;
+        p
+    }
+
+    fn row(tableau: &TableauSimulator, row: usize) -> Self {
+        let (x, z) = (0..tableau.num_qubits()).map(|q| tableau.pauli(row, q)).unzip();
+        Pauli { x, z, phase: 2 * tableau.phase(row) as usize }
+    }
+
+    // self = self * other
+    fn mul(&mut self, other: &Pauli) {
+        let mut exponent = (self.phase + other.phase) as i64;
+        for q in 0..self.x.len() {
+            exponent += g(self.x[q], self.z[q], other.x[q], other.z[q]);
+            self.x[q] ^= other.x[q];
+            self.z[q] ^= other.z[q];
+        }
+        self.phase = exponent.rem_euclid(4) as usize;
+    }
+
+    fn anticommutes(&self, other: &Pauli) -> bool {
+        (0..self.x.len()).filter(|&q| (self.x[q] && other.z[q]) ^ (self.z[q] && other.x[q])).count() % 2 == 1
+    }
+}
+
+// Rows 0..n are the destabilizers D_j and n..2n the stabilizers S_j
+fn frame(tableau: &TableauSimulator) -> Vec<Pauli> {
+    (0..2 * tableau.num_qubits()).map(|row| Pauli::row(tableau, row)).collect()
+}
+
+// Write p = i^k D^a S^b. D_j only anticommutes with S_j, so a_j says
+// whether p anticommutes with S_j and b_j whether it does with D_j.
+fn decompose(frame: &[Pauli], p: &Pauli) -> (Vec<bool>, Vec<bool>, usize) {
+    let n = frame.len() / 2;
+    let a: Vec<bool> = (0..n).map(|j| p.anticommutes(&frame[n + j])).collect();
+    let b: Vec<bool> = (0..n).map(|j| p.anticommutes(&frame[j])).collect();
+    let mut product = Pauli::identity(n);
+    for j in (0..n).filter(|&j| a[j]) {
+        product.mul(&frame[j]);
+    }
+    for j in (0..n).filter(|&j| b[j]) {
+        product.mul(&frame[n + j]);
+    }
+    debug_assert!(product.x == p.x && product.z == p.z, "tableau rows do not span the Paulis");
+    (a, b, (p.phase + 4 - product.phase) % 4)
+}
+
+// D^x as a single Pauli
+fn destabilizer_product(frame: &[Pauli], x: &[bool]) -> Pauli {
+    let mut product = Pauli::identity(x.len());
+    for j in (0..x.len()).filter(|&j| x[j]) {
+        product.mul(&frame[j]);
+    }
+    product
+}
+
+fn parity(a: &[bool], b: &[bool]) -> bool {
+    a.iter().zip(b).filter(|(&u, &v)| u && v).count() % 2 == 1
+}
+
+fn xor(a: &[bool], b: &[bool]) -> Vec<bool> {
+    a.iter().zip(b).map(|(&u, &v)| u ^ v).collect()
+}
+
+fn add_term(terms: &mut BTreeMap<Vec<bool>, Complex>, x: Vec<bool>, c: Complex) {
+    let entry = terms.entry(x).or_insert_with(Complex::zero);
+    *entry = entry.add(&c);
+}
+
+fn weight(terms: &BTreeMap<Vec<bool>, Complex>) -> f64 {
+    terms.values().map(|c| c.magnitude2()).sum()
+}
+
+pub struct StabilizerSumSimulator {
+    tableau: TableauSimulator,
+    terms: BTreeMap<Vec<bool>, Complex>,
+}
+
+impl From<TableauSimulator> for StabilizerSumSimulator {
+    fn from(tableau: TableauSimulator) -> Self {
+        let mut terms = BTreeMap::new();
+        terms.insert(vec![false; tableau.num_qubits()], Complex::one());
+        Self { tableau, terms }
+    }
+}
+
+impl StabilizerSumSimulator {
+    pub fn new(num_qubits: usize) -> Self {
+        Self::from(TableauSimulator::new(num_qubits))
+    }
+
+    pub fn num_qubits(&self) -> usize {
+        self.tableau.num_qubits()
+    }
+
+    pub fn num_terms(&self) -> usize {
+        self.terms.len()
+    }
+
+    // Ops this backend runs without going to the statevector
+    pub fn supports(op: &IROp) -> bool {
+        op.is_clifford() || matches!(op, IROp::Reset(_) | IROp::MagicState(_) | IROp::T(_) | IROp::Tdg(_) | IROp::RZ(..))
+    }
+
+    pub fn apply_ir(&mut self, op: &IROp) {
+        match *op {
+            IROp::Reset(q) => self.reset(q),
+            IROp::MagicState(q) => self.prepare_magic(q),
+            ref op if op.is_clifford() => self.tableau.apply_ir(op),
+            IROp::T(q) => self.apply_phase(q, FRAC_PI_4),
+            IROp::Tdg(q) => self.apply_phase(q, -FRAC_PI_4),
+            // RZ(t) is diag(1, e^{it}) up to global phase
+            IROp::RZ(q, angle) => self.apply_phase(q, angle),
+            ref other => panic!("stabilizer sum backend cannot apply `{}`", other),
+        }
+    }
+
+    // diag(1, e^{i angle}) = (1 + w)/2 I + (1 - w)/2 Z, and Z_q D^x |phi> =
+    // i^k (-1)^{b.x} D^{x+a} |phi> for Z_q = i^k D^a S^b
+    pub fn apply_phase(&mut self, qubit: usize, angle: f64) {
+        let frame = frame(&self.tableau);
+        let (a, b, k) = decompose(&frame, &Pauli::z_on(self.num_qubits(), qubit));
+        let w = Complex::new(angle.cos(), angle.sin());
+        let keep = Complex::new((1.0 + w.re) / 2.0, w.im / 2.0);
+        let flip = Complex::new((1.0 - w.re) / 2.0, -w.im / 2.0).mul(&I_POWERS[k]);
+        let mut terms = BTreeMap::new();
+        for (x, c) in &self.terms {
+            add_term(&mut terms, x.clone(), c.mul(&keep));
+            let sign = if parity(&b, x) { -1.0 } else { 1.0 };
+            add_term(&mut terms, xor(x, &a), c.mul(&flip).mul(&Complex::new(sign, 0.0)));
+        }
+        self.terms = terms;
+        self.normalize();
+    }
+
+    // Reset to |T> = T H|0>
+    pub fn prepare_magic(&mut self, qubit: usize) {
+        self.reset(qubit);
+        self.tableau.apply_h(qubit);
+        self.apply_phase(qubit, FRAC_PI_4);
+    }
+
+    pub fn reset(&mut self, qubit: usize) {
+        if self.measure_z(qubit) {
+            self.tableau.apply_ir(&IROp::X(qubit));
+        }
+    }
+
+    pub fn measure_z(&mut self, qubit: usize) -> bool {
+        let rows = frame(&self.tableau);
+        let (a, b, k) = decompose(&rows, &Pauli::z_on(self.num_qubits(), qubit));
+        let mut rng = rand::thread_rng();
+
+        // +-Z_q is in the stabilizer group, so it is diagonal in this basis
+        // with eigenvalue (-1)^{k/2 + b.x} on D^x |phi>
+        if !a.contains(&true) {
+            let is_one = |x: &[bool]| (k == 2) ^ parity(&b, x);
+            let p1: f64 = self.terms.iter().filter(|(x, _)| is_one(x)).map(|(_, c)| c.magnitude2()).sum();
+            let outcome = rng.gen::<f64>() < p1 / weight(&self.terms);
+            self.terms.retain(|x, _| is_one(x) == outcome);
+            self.normalize();
+            return outcome;
+        }
+
+        // Otherwise some stabilizer S_p anticommutes with Z_q. The projector
+        // P onto the outcome gives P D^x |phi> = D^x S_p^{b.x} P |phi>, and
+        // sqrt(2) P |phi> is the collapsed tableau's state, so each term
+        // moves to the collapsed frame as one Pauli.
+        let p = a.iter().position(|&v| v).unwrap();
+        let collapse = |outcome: bool| {
+            let mut tableau = self.tableau.clone();
+            tableau.collapse_z(qubit, outcome);
+            let collapsed = frame(&tableau);
+            let mut terms = BTreeMap::new();
+            for (x, c) in &self.terms {
+                let mut pauli = destabilizer_product(&rows, x);
+                if parity(&b, x) {
+                    pauli.mul(&rows[self.num_qubits() + p]);
+                }
+                let (y, _, k) = decompose(&collapsed, &pauli);
+                add_term(&mut terms, y, c.mul(&I_POWERS[k]).mul(&Complex::new(FRAC_1_SQRT_2, 0.0)));
+            }
+            (tableau, terms)
+        };
+        let zero = collapse(false);
+        let outcome = rng.gen::<f64>() >= weight(&zero.1) / weight(&self.terms);
+        let (tableau, terms) = if outcome { collapse(true) } else { zero };
+        self.tableau = tableau;
+        self.terms = terms;
+        self.normalize();
+        outcome
+    }
+
+    // The tableau of the state, if it is down to a single term: D^x |phi> is
+    // |phi> with the signs of the stabilizers that anticommute with D^x
+    // flipped, which is what conjugating by D^x does.
+    pub fn to_tableau(&self) -> Option<TableauSimulator> {
+        let [x] = self.terms.keys().collect::<Vec<_>>()[..] else { return None };
+        let pauli = destabilizer_product(&frame(&self.tableau), x);
+        let mut tableau = self.tableau.clone();
+        for q in 0..self.num_qubits() {
+            match (pauli.x[q], pauli.z[q]) {
+                (true, false) => tableau.apply_ir(&IROp::X(q)),
+                (false, true) => tableau.apply_ir(&IROp::Z(q)),
+                (true, true) => tableau.apply_ir(&IROp::Y(q)),
+                (false, false) => {}
+            }
+        }
+        Some(tableau)
+    }
+
+    // Dense amplitudes, up to global phase
+    pub fn to_statevector(&self) -> Vec<Complex> {
+        let phi = self.tableau.to_statevector();
+        let frame = frame(&self.tableau);
+        let mut psi = vec![Complex::zero(); phi.len()];
+        for (x, c) in &self.terms {
+            let pauli = destabilizer_product(&frame, x);
+            let (mut xmask, mut zmask, mut ys) = (0usize, 0usize, 0);
+            for q in 0..self.num_qubits() {
+                xmask |= (pauli.x[q] as usize) << q;
+                zmask |= (pauli.z[q] as usize) << q;
+                ys += (pauli.x[q] && pauli.z[q]) as usize;
+            }
+            // A qubit with both bits set is Y = iXZ
+            let factor = c.mul(&I_POWERS[(pauli.phase + ys) % 4]);
+            for (basis, amp) in phi.iter().enumerate() {
+                let sign = if (basis & zmask).count_ones() % 2 == 1 { -1.0 } else { 1.0 };
+                psi[basis ^ xmask] = psi[basis ^ xmask].add(&amp.mul(&factor).mul(&Complex::new(sign, 0.0)));
+            }
+        }
+        psi
+    }
+
+    fn normalize(&mut self) {
+        let total = weight(&self.terms);
+        self.terms.retain(|_, c| c.magnitude2() > PRUNE * total);
+        let norm = weight(&self.terms).sqrt();
+        for c in self.terms.values_mut() {
+            *c = Complex::new(c.re / norm, c.im / norm);
+        }
+    }
+}
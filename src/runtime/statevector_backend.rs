@@ -0,0 +1,59 @@
+use crate::compiler::ir::IROp;
+use crate::math::complex::Complex;
+use crate::statevector::gates::Gates;
+
+pub use crate::statevector::simulator::StatevectorSimulator;
+
+impl From<Vec<Complex>> for StatevectorSimulator {
+    fn from(state: Vec<Complex>) -> Self {
+        let num_qubits = state.len().trailing_zeros() as usize;
+        assert_eq!(state.len(), 1 << num_qubits, "statevector length must be a power of two");
+        Self { num_qubits, state }
+    }
+}
+
+impl StatevectorSimulator {
+    // Apply any unitary IR op, a reset or a magic-state preparation
+    pub fn apply_ir(&mut self, op: &IROp) {
+        match *op {
+            IROp::I(_) | IROp::Barrier(_) | IROp::Promote | IROp::Demote => {}
+            IROp::X(q) => self.apply_single_qubit_gate(q, Gates::x()),
+            IROp::Y(q) => self.apply_single_qubit_gate(q, Gates::y()),
+            IROp::Z(q) => self.apply_single_qubit_gate(q, Gates::z()),
+            IROp::H(q) => self.apply_single_qubit_gate(q, Gates::h()),
+            IROp::S(q) => self.apply_single_qubit_gate(q, Gates::s()),
+            IROp::Sdg(q) => self.apply_single_qubit_gate(q, Gates::sdg()),
+            IROp::SX(q) => self.apply_single_qubit_gate(q, Gates::sx()),
+            IROp::SXdg(q) => self.apply_single_qubit_gate(q, Gates::sxdg()),
+            IROp::T(q) => self.apply_single_qubit_gate(q, Gates::t()),
+            IROp::Tdg(q) => self.apply_single_qubit_gate(q, Gates::tdg()),
+            IROp::RX(q, a) => self.apply_single_qubit_gate(q, Gates::rx(a)),
+            IROp::RY(q, a) => self.apply_single_qubit_gate(q, Gates::ry(a)),
+            IROp::RZ(q, a) => self.apply_single_qubit_gate(q, Gates::rz(a)),
+            IROp::U3(q, t, p, l) => self.apply_single_qubit_gate(q, Gates::u3(t, p, l)),
+            IROp::CNOT(c, t) => self.apply_cnot(c, t),
+            IROp::CZ(c, t) => self.apply_controlled_matrix(c, t, Gates::z()),
+            IROp::CPhase(c, t, a) => self.apply_controlled_matrix(c, t, Gates::phase(a)),
+            IROp::CU3(c, q, t, p, l) => self.apply_controlled_matrix(c, q, Gates::u3(t, p, l)),
+            IROp::SWAP(a, b) => {
+                self.apply_cnot(a, b);
+                self.apply_cnot(b, a);
+                self.apply_cnot(a, b);
+            }
+            IROp::Toffoli(a, b, t) => self.apply_toffoli(a, b, t),
+            IROp::CCZ(a, b, t) => {
+                self.apply_single_qubit_gate(t, Gates::h());
+                self.apply_toffoli(a, b, t);
+                self.apply_single_qubit_gate(t, Gates::h());
+            }
+            IROp::MCX(ref controls, t) => self.apply_mcx(controls, t),
+            IROp::Reset(q) => self.reset_qubit(q),
+            IROp::MagicState(q) => {
+                self.reset_qubit(q);
+                self.apply_single_qubit_gate(q, Gates::h());
+                self.apply_single_qubit_gate(q, Gates::t());
+            }
+            IROp::Measure(..) | IROp::Conditional { .. } | IROp::IfElse { .. } | IROp::While { .. } => panic!("`{}` is handled by the runtime controller", op),
+        }
+    }
+}
@@ -0,0 +1,189 @@
+use crate::compiler::ir::IROp;
+use crate::math::complex::Complex;
+use std::f64::consts::FRAC_PI_2;
+
+pub use crate::tableau::simulator::Tableau as TableauSimulator;
+
+// Number of quarter turns in a Clifford rotation angle, in 0..4
+fn quarter_turns(angle: f64) -> usize {
+    ((angle / FRAC_PI_2).round() as i64).rem_euclid(4) as usize
+}
+
+impl TableauSimulator {
+    fn apply_s_power(&mut self, q: usize, k: usize) {
+        for _ in 0..k % 4 {
+            self.apply_s(q);
+        }
+    }
+
+    fn apply_rx_quarter(&mut self, q: usize, k: usize) {
+        self.apply_h(q);
+        self.apply_s_power(q, k);
+        self.apply_h(q);
+    }
+
+    // RY = S RX Sdg
+    fn apply_ry_quarter(&mut self, q: usize, k: usize) {
+        self.apply_s_power(q, 3);
+        self.apply_rx_quarter(q, k);
+        self.apply_s(q);
+    }
+
+    // Apply a Clifford IR op (up to global phase); panics on non-Cliffords,
+    // which the controller routes elsewhere.
+    pub fn apply_ir(&mut self, op: &IROp) {
+        match *op {
+            IROp::I(_) | IROp::Barrier(_) | IROp::Promote | IROp::Demote => {}
+            IROp::H(q) => self.apply_h(q),
+            IROp::S(q) => self.apply_s(q),
+            IROp::Sdg(q) => self.apply_s_power(q, 3),
+            IROp::Z(q) => self.apply_s_power(q, 2),
+            IROp::X(q) => self.apply_rx_quarter(q, 2),
+            IROp::Y(q) => {
+                self.apply_s_power(q, 2);
+                self.apply_rx_quarter(q, 2);
+            }
+            IROp::SX(q) => self.apply_rx_quarter(q, 1),
+            IROp::SXdg(q) => self.apply_rx_quarter(q, 3),
+            IROp::RZ(q, a) => self.apply_s_power(q, quarter_turns(a)),
+            IROp::RX(q, a) => self.apply_rx_quarter(q, quarter_turns(a)),
+            IROp::RY(q, a) => self.apply_ry_quarter(q, quarter_turns(a)),
+            IROp::U3(q, theta, phi, lambda) => {
+                self.apply_s_power(q, quarter_turns(lambda));
+                self.apply_ry_quarter(q, quarter_turns(theta));
+                self.apply_s_power(q, quarter_turns(phi));
+            }
+            IROp::CNOT(c, t) => self.apply_cnot(c, t),
+            IROp::CZ(c, t) => {
+                self.apply_h(t);
+                self.apply_cnot(c, t);
+                self.apply_h(t);
+            }
+            IROp::CPhase(c, t, a) => {
+                if quarter_turns(a) == 2 {
+                    self.apply_ir(&IROp::CZ(c, t));
+                }
+            }
+            IROp::SWAP(a, b) => {
+                self.apply_cnot(a, b);
+                self.apply_cnot(b, a);
+                self.apply_cnot(a, b);
+            }
+            IROp::Reset(q) => {
+                if self.measure_z(q) {
+                    self.apply_ir(&IROp::X(q));
+                }
+            }
+            ref other => panic!("tableau backend cannot apply non-Clifford op `{}`", other),
+        }
+    }
+
+    // The tableau of `state` if it is a stabilizer state (up to global phase).
+    // A stabilizer state is uniform over an affine subspace x0 + span(b_j)
+    // with phases i^(sum c_j a_j) (-1)^(sum Q_jl a_j a_l) in the coordinates
+    // a of the span, so it is prepared by: H on one pivot bit per b_j, CNOTs
+    // from the pivot into the rest of b_j, S^c_j and CZ for the phases, and
+    // finally X on the bits of x0.
+    pub fn from_statevector(state: &[Complex]) -> Option<TableauSimulator> {
+        const TOL: f64 = 1e-9;
+        let n = state.len().trailing_zeros() as usize;
+        let norm2: f64 = state.iter().map(|a| a.magnitude2()).sum();
+        let support: Vec<usize> = (0..state.len()).filter(|&x| state[x].magnitude2() > TOL * norm2).collect();
+        if !support.len().is_power_of_two() {
+            return None;
+        }
+        let uniform = norm2 / support.len() as f64;
+        if support.iter().any(|&x| (state[x].magnitude2() - uniform).abs() > TOL * norm2) {
+            return None;
+        }
+
+        // Basis of the offsets in reduced row echelon form: each vector owns
+        // its highest bit (the pivot) and no other vector has that bit set
+        let x0 = support[0];
+        let mut basis: Vec<usize> = Vec::new();
+        for &x in &support {
+            let mut v = x ^ x0;
+            for &b in &basis {
+                if v & pivot(b) != 0 {
+                    v ^= b;
+                }
+            }
+            if v != 0 {
+                for b in basis.iter_mut() {
+                    if *b & pivot(v) != 0 {
+                        *b ^= v;
+                    }
+                }
+                basis.push(v);
+                basis.sort_unstable_by(|a, b| b.cmp(a));
+            }
+        }
+        if 1usize << basis.len() != support.len() {
+            return None;
+        }
+
+        // Phase of each support point relative to x0, as a power of i
+        let quarter = |x: usize| -> Option<u32> {
+            let r = state[x].div(&state[x0]);
+            [(1.0, 0.0), (0.0, 1.0), (-1.0, 0.0), (0.0, -1.0)].iter()
+                .position(|&(re, im)| (r.re - re).abs() < 1e-6 && (r.im - im).abs() < 1e-6)
+                .map(|k| k as u32)
+        };
+        let k = basis.len();
+        let mut linear = vec![0u32; k];
+        for j in 0..k {
+            linear[j] = quarter(x0 ^ basis[j])?;
+        }
+        let mut quadratic = vec![vec![false; k]; k];
+        for j in 0..k {
+            for l in j + 1..k {
+                let e = (quarter(x0 ^ basis[j] ^ basis[l])? + 8 - linear[j] - linear[l]) % 4;
+                match e {
+                    0 => {}
+                    2 => quadratic[j][l] = true,
+                    _ => return None,
+                }
+            }
+        }
+        for &x in &support {
+            let a: Vec<bool> = basis.iter().map(|&b| (x ^ x0) & pivot(b) != 0).collect();
+            let mut e: u32 = (0..k).filter(|&j| a[j]).map(|j| linear[j]).sum();
+            for j in 0..k {
+                for l in j + 1..k {
+                    if a[j] && a[l] && quadratic[j][l] {
+                        e += 2;
+                    }
+                }
+            }
+            if quarter(x)? != e % 4 {
+                return None;
+            }
+        }
+
+        let mut ops = Vec::new();
+        let pivots: Vec<usize> = basis.iter().map(|&b| pivot(b).trailing_zeros() as usize).collect();
+        for (&b, &p) in basis.iter().zip(&pivots) {
+            ops.push(IROp::H(p));
+            ops.extend((0..n).filter(|&t| t != p && b >> t & 1 == 1).map(|t| IROp::CNOT(p, t)));
+        }
+        for j in 0..k {
+            ops.extend((0..linear[j]).map(|_| IROp::S(pivots[j])));
+            for l in j + 1..k {
+                if quadratic[j][l] {
+                    ops.push(IROp::CZ(pivots[j], pivots[l]));
+                }
+            }
+        }
+        ops.extend((0..n).filter(|&q| x0 >> q & 1 == 1).map(IROp::X));
+
+        let mut tableau = TableauSimulator::new(n);
+        for op in &ops {
+            tableau.apply_ir(op);
+        }
+        Some(tableau)
+    }
+}
+
+fn pivot(v: usize) -> usize {
+    1 << (usize::BITS - 1 - v.leading_zeros())
+}
@@ -26,4 +26,49 @@ impl Gates {
         [[Complex::one(), Complex::zero()],
          [Complex::zero(), Complex::new(0.0, 1.0)]]
     }
+    pub fn sdg() -> [[Complex; 2]; 2] {
+        [[Complex::one(), Complex::zero()],
+         [Complex::zero(), Complex::new(0.0, -1.0)]]
+    }
+    pub fn t() -> [[Complex; 2]; 2] {
+        Self::phase(std::f64::consts::FRAC_PI_4)
+    }
+    pub fn tdg() -> [[Complex; 2]; 2] {
+        Self::phase(-std::f64::consts::FRAC_PI_4)
+    }
+    pub fn sx() -> [[Complex; 2]; 2] {
+        [[Complex::new(0.5, 0.5), Complex::new(0.5, -0.5)],
+         [Complex::new(0.5, -0.5), Complex::new(0.5, 0.5)]]
+    }
+    pub fn sxdg() -> [[Complex; 2]; 2] {
+        [[Complex::new(0.5, -0.5), Complex::new(0.5, 0.5)],
+         [Complex::new(0.5, 0.5), Complex::new(0.5, -0.5)]]
+    }
+    // diag(1, e^{i lambda})
+    pub fn phase(lambda: f64) -> [[Complex; 2]; 2] {
+        [[Complex::one(), Complex::zero()],
+         [Complex::zero(), Complex::new(lambda.cos(), lambda.sin())]]
+    }
+    pub fn rx(theta: f64) -> [[Complex; 2]; 2] {
+        let (c, s) = ((theta / 2.0).cos(), (theta / 2.0).sin());
+        [[Complex::new(c, 0.0), Complex::new(0.0, -s)],
+         [Complex::new(0.0, -s), Complex::new(c, 0.0)]]
+    }
+    pub fn ry(theta: f64) -> [[Complex; 2]; 2] {
+        let (c, s) = ((theta / 2.0).cos(), (theta / 2.0).sin());
+        [[Complex::new(c, 0.0), Complex::new(-s, 0.0)],
+         [Complex::new(s, 0.0), Complex::new(c, 0.0)]]
+    }
+    pub fn rz(theta: f64) -> [[Complex; 2]; 2] {
+        let (c, s) = ((theta / 2.0).cos(), (theta / 2.0).sin());
+        [[Complex::new(c, -s), Complex::zero()],
+         [Complex::zero(), Complex::new(c, s)]]
+    }
+    // OpenQASM U(theta, phi, lambda)
+    pub fn u3(theta: f64, phi: f64, lambda: f64) -> [[Complex; 2]; 2] {
+        let (c, s) = ((theta / 2.0).cos(), (theta / 2.0).sin());
+        [[Complex::new(c, 0.0), Complex::new(-lambda.cos() * s, -lambda.sin() * s)],
+         [Complex::new(phi.cos() * s, phi.sin() * s),
+          Complex::new((phi + lambda).cos() * c, (phi + lambda).sin() * c)]]
+    }
 }
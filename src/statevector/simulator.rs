@@ -1,5 +1,5 @@
 use crate::math::complex::Complex;
-
+use rand::Rng;
 
 pub struct StatevectorSimulator {
     pub num_qubits: usize,
@@ -38,14 +38,14 @@ impl StatevectorSimulator {
 
     pub fn apply_controlled_gate<F>(&mut self, control: usize, target: usize, op: F)
     where F: Fn(&mut Complex, &mut Complex) {
-        // stub: conditional amplitude swap/update
+        // conditional amplitude swap/update
         let stride = 1 << target;
         for blockstart in (0..self.state.len()).step_by(2*stride){
           for i in 0..stride {
             let idx0 = blockstart + i;
             let idx1 = idx0 + stride;
 
-            if(i >> control)&1 == 1{
+            if(idx0 >> control)&1 == 1{
               let (left, right) = self.state.split_at_mut(idx1);
               op(&mut left[idx0], &mut right[0]);
             }
@@ -93,4 +93,54 @@ impl StatevectorSimulator {
       }
     }
 
+    // Flip `target` on every basis state where all `controls` are set
+    pub fn apply_mcx(&mut self, controls: &[usize], target: usize) {
+      let mask = controls.iter().fold(0usize, |m, c| m | (1 << c));
+      let stride = 1 << target;
+      for blockstart in (0..self.state.len()).step_by(2*stride){
+          for i in 0..stride {
+              let idx0 = blockstart + i;
+              if idx0 & mask == mask {
+                  self.state.swap(idx0, idx0 + stride);
+              }
+          }
+      }
+    }
+
+    pub fn apply_controlled_matrix(&mut self, control: usize, target: usize, matrix: [[Complex; 2]; 2]) {
+        self.apply_controlled_gate(control, target, |a0, a1| {
+            let (old0, old1) = (*a0, *a1);
+            *a0 = old0.mul(&matrix[0][0]).add(&old1.mul(&matrix[0][1]));
+            *a1 = old0.mul(&matrix[1][0]).add(&old1.mul(&matrix[1][1]));
+        });
+    }
+
+    // Probability that `qubit` reads 1
+    pub fn probability_one(&self, qubit: usize) -> f64 {
+        self.state.iter().enumerate()
+            .filter(|(i, _)| (i >> qubit) & 1 == 1)
+            .map(|(_, amp)| amp.magnitude2())
+            .sum()
+    }
+
+    // Projective Z measurement: sample an outcome, collapse and renormalize
+    pub fn measure_qubit(&mut self, qubit: usize) -> bool {
+        let p1 = self.probability_one(qubit);
+        let outcome = rand::thread_rng().gen::<f64>() < p1;
+        let norm = if outcome { p1 } else { 1.0 - p1 }.sqrt();
+        for (i, amp) in self.state.iter_mut().enumerate() {
+            if ((i >> qubit) & 1 == 1) == outcome {
+                *amp = Complex::new(amp.re / norm, amp.im / norm);
+            } else {
+                *amp = Complex::zero();
+            }
+        }
+        outcome
+    }
+
+    pub fn reset_qubit(&mut self, qubit: usize) {
+        if self.measure_qubit(qubit) {
+            self.apply_single_qubit_gate(qubit, crate::statevector::gates::Gates::x());
+        }
+    }
 }
@@ -1,5 +1,9 @@
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum CliffordGate {
     H(usize),
     S(usize),
+    Sdg(usize),
+    X(usize),
+    Z(usize),
     CNOT(usize, usize),
 }
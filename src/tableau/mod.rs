@@ -1,3 +1,4 @@
 pub mod gates;
 pub mod simulator;
-pub mod utils;
\ No newline at end of file
+pub mod synthesis;
+pub mod utils;
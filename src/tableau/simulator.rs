@@ -1,6 +1,8 @@
+use crate::math::complex::Complex;
 use rand::Rng;
 use std::fmt;
 
+#[derive(Clone)]
 pub struct Row{
     xmask: Vec<u64>,
     zmask: Vec<u64>,
@@ -9,6 +11,20 @@ pub struct Row{
 
 }
 
+impl Row {
+    fn zero(chunks: usize) -> Self {
+        Row { xmask: vec![0; chunks], zmask: vec![0; chunks], phase: false }
+    }
+
+    fn x(&self, qubit: usize) -> bool {
+        self.xmask[qubit / 64] >> (qubit % 64) & 1 == 1
+    }
+
+    fn z(&self, qubit: usize) -> bool {
+        self.zmask[qubit / 64] >> (qubit % 64) & 1 == 1
+    }
+}
+
 impl fmt::Debug for Row {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "Row {{ ")?;
@@ -30,164 +46,225 @@ impl fmt::Debug for Row {
         write!(f, "phase: {} }}", self.phase)
     }
 }
+// The CHP tableau of Aaronson & Gottesman (2004). Rows 0..n are the
+// destabilizers, rows n..2n the stabilizer generators and row 2n is
+// scratch space for deterministic measurements. Each row is a Pauli
+// product (-1)^phase X^x Z^z with the X and Z bits packed 64 to a word.
+#[derive(Clone)]
 pub struct Tableau {
     num_qubits: usize,
-    // Row-major: 2n rows, n+1 columns (X|Z|phase)
-    // Could store as Vec<Vec<bool>>, but might later pack into bitmasks for speed.
     data: Vec<Row>,
 }
 
 impl Tableau {
+    // |0...0>: destabilizers X_i and stabilizers Z_i
     pub fn new(num_qubits: usize) -> Self {
-        let chunks = (num_qubits + 63) / 64;
-
-        let mut data: Vec<Row> = (0..2 * num_qubits)
-            .map(|_| Row {
-                xmask: vec![0; chunks],
-                zmask: vec![0; chunks],
-                phase: false,
-            })
-            .collect();
-
-        // Set the first n rows to Z stabilizers
+        let chunks = num_qubits.div_ceil(64);
+        let mut data = vec![Row::zero(chunks); 2 * num_qubits + 1];
         for q in 0..num_qubits {
-            let chunk = q / 64;
-            let bit = q % 64;
-            data[q].zmask[chunk] |= 1 << bit;
+            let (chunk, mask) = (q / 64, 1u64 << (q % 64));
+            data[q].xmask[chunk] |= mask;
+            data[num_qubits + q].zmask[chunk] |= mask;
         }
-
         Self { num_qubits, data }
     }
 
+    // The tableau of the identity Clifford, which is the one of |0...0>.
+    // Applying gates to it tracks the images of X_i (row i) and Z_i (row
+    // n + i), i.e. the whole Clifford.
+    pub fn identity(num_qubits: usize) -> Self {
+        Self::new(num_qubits)
+    }
+
+    pub fn num_qubits(&self) -> usize {
+        self.num_qubits
+    }
+
+    // (x, z) bits of `row` on `qubit`
+    pub fn pauli(&self, row: usize, qubit: usize) -> (bool, bool) {
+        (self.data[row].x(qubit), self.data[row].z(qubit))
+    }
+
+    pub fn phase(&self, row: usize) -> bool {
+        self.data[row].phase
+    }
+
+    // Gates conjugate every row but the scratch one
+
     pub fn apply_h(&mut self, qubit: usize) {
-        let chunk = qubit / 64;
-        let bit = qubit % 64;
-        let mask = 1u64 << bit;
-        
-        for row in 0..(2 * self.num_qubits) {
-            let x_set = (self.data[row].xmask[chunk] & mask) != 0;
-            let z_set = (self.data[row].zmask[chunk] & mask) != 0;
-            
-            // Update phase if both X and Z are set
-            if x_set && z_set {
-                self.data[row].phase ^= true;
-            }
-            
-            // Swap the bits
-            if x_set != z_set { // Only need to change if they're different
-                self.data[row].xmask[chunk] ^= mask;
-                self.data[row].zmask[chunk] ^= mask;
-            }
+        let (chunk, mask) = (qubit / 64, 1u64 << (qubit % 64));
+        for row in &mut self.data[..2 * self.num_qubits] {
+            let x = row.xmask[chunk] & mask;
+            let z = row.zmask[chunk] & mask;
+            // Y -> -Y, X <-> Z
+            row.phase ^= x & z != 0;
+            row.xmask[chunk] ^= x ^ z;
+            row.zmask[chunk] ^= x ^ z;
         }
     }
 
     pub fn apply_s(&mut self, qubit: usize) {
-        let chunk = qubit / 64;
-        let bit = qubit % 64;
-        let mask = 1u64<<bit;
-
-        for row in 0..(2*self.num_qubits){
-            let x_set = (self.data[row].xmask[chunk] & mask) != 0;
-            let z_set = (self.data[row].zmask[chunk] & mask) != 0;
-
-            // (x=1,z=0) -> (x=1,z=1)
-            if x_set && !z_set{
-                self.data[row].zmask[chunk] |= mask;
-            } 
-            // (x=1,z=1) -> (x=1,z=0) phase flip
-            else if x_set && z_set{
-                self.data[row].zmask[chunk] ^= mask;
-                self.data[row].phase ^= true;
-            }
+        let (chunk, mask) = (qubit / 64, 1u64 << (qubit % 64));
+        for row in &mut self.data[..2 * self.num_qubits] {
+            let x = row.xmask[chunk] & mask;
+            let z = row.zmask[chunk] & mask;
+            // X -> Y, Y -> -X
+            row.phase ^= x & z != 0;
+            row.zmask[chunk] ^= x;
         }
     }
 
     pub fn apply_cnot(&mut self, control: usize, target: usize) {
-        let c_chunk = control / 64;
-        let c_bit = control % 64;
-        let c_mask = 1u64 << c_bit;
-
-        let t_chunk = target / 64;
-        let t_bit = target % 64;
-        let t_mask = 1u64 << t_bit;
-
-        for row in 0..(2*self.num_qubits){
-            let c_x_set = (self.data[row].xmask[c_chunk] & c_mask) != 0;
-            let c_z_set = (self.data[row].zmask[c_chunk] & c_mask) != 0;
-            let t_x_set = (self.data[row].xmask[t_chunk] & t_mask) != 0;
-            let t_z_set = (self.data[row].zmask[t_chunk] & t_mask) != 0;
-
-            if c_x_set{
-                //toggle target x
-                self.data[row].xmask[t_chunk] ^= t_mask;
-            }
-            if c_z_set{
-                //toggle target z
-                self.data[row].zmask[t_chunk] ^= t_mask;
+        for row in &mut self.data[..2 * self.num_qubits] {
+            let (xc, zc) = (row.x(control), row.z(control));
+            let (xt, zt) = (row.x(target), row.z(target));
+            // phase picks up x_c z_t (x_t ^ z_c ^ 1)
+            row.phase ^= xc && zt && xt == zc;
+            if xc {
+                row.xmask[target / 64] ^= 1 << (target % 64);
             }
-            // Target → Control transformations  
-            if t_z_set {
-                self.data[row].zmask[c_chunk] ^= c_mask;
-            }
-            if t_x_set {
-                self.data[row].xmask[c_chunk] ^= c_mask;
+            if zt {
+                row.zmask[control / 64] ^= 1 << (control % 64);
             }
         }
     }
 
+    // Measure Z on `qubit`, collapsing the state on a random outcome. If a
+    // stabilizer p anticommutes with Z_qubit, the outcome is uniform: every
+    // other row anticommuting with it is multiplied by p, p becomes its own
+    // destabilizer and (-1)^outcome Z_qubit takes its place. Otherwise
+    // +-Z_qubit is in the stabilizer group, and its sign is found by
+    // multiplying together, in the scratch row, the stabilizers whose
+    // destabilizers anticommute with Z_qubit.
     pub fn measure_z(&mut self, qubit: usize) -> bool {
-        let chunk = qubit / 64;
-        let bit = qubit % 64;
-        let mask = 1u64 << bit;
-        let mut rng = rand::thread_rng();
-        
-        // Look for anticommuting stabilizer
-        for row in 0..(2 * self.num_qubits) {
-            let x = (self.data[row].xmask[chunk] & mask) != 0;
-            if x {
-                // Found anticommuting stabilizer, return random outcome
-                let outcome = rng.gen_bool(0.5);
-                
-                // Replace this row with Z_qubit stabilizer
-                for i in 0..self.data[row].xmask.len() {
-                    self.data[row].xmask[i] = 0;
-                    self.data[row].zmask[i] = 0;
-                }
-                self.data[row].zmask[chunk] = mask;
-                self.data[row].phase = outcome;
-                
-                // Eliminate X_qubit from other stabilizers using this row
-                for other_row in 0..(2 * self.num_qubits) {
-                    if other_row != row {
-                        let other_x = (self.data[other_row].xmask[chunk] & mask) != 0;
-                        if other_x {
-                            // XOR this row into other_row to eliminate X_qubit
-                            for i in 0..self.data[row].xmask.len() {
-                                self.data[other_row].xmask[i] ^= self.data[row].xmask[i];
-                                self.data[other_row].zmask[i] ^= self.data[row].zmask[i];
-                            }
-                            self.data[other_row].phase ^= self.data[row].phase;
-                        }
-                    }
-                }
-                
-                return outcome;
+        let n = self.num_qubits;
+        if (n..2 * n).any(|row| self.data[row].x(qubit)) {
+            let outcome = rand::thread_rng().gen_bool(0.5);
+            self.collapse_z(qubit, outcome);
+            return outcome;
+        }
+
+        let scratch = 2 * n;
+        self.data[scratch] = Row::zero(self.data[scratch].xmask.len());
+        for row in 0..n {
+            if self.data[row].x(qubit) {
+                self.rowsum(scratch, row + n);
             }
         }
-        
-        // No anticommuting stabilizer found - deterministic outcome
-        // Check if any stabilizer has Z on this qubit with odd phase
-        for row in 0..(2 * self.num_qubits) {
-            let z = (self.data[row].zmask[chunk] & mask) != 0;
-            if z && self.data[row].phase {
-                return true;  // -Z stabilizer means measurement gives -1 (true)
+        self.data[scratch].phase
+    }
+
+    // The random branch of measure_z with the outcome picked by the caller.
+    // Returns false, leaving the tableau alone, when the outcome is
+    // determined instead.
+    pub fn collapse_z(&mut self, qubit: usize, outcome: bool) -> bool {
+        let n = self.num_qubits;
+        let Some(p) = (n..2 * n).find(|&row| self.data[row].x(qubit)) else {
+            return false;
+        };
+        // Row p - n anticommutes with p, but it is overwritten anyway
+        for row in 0..2 * n {
+            if row != p && row != p - n && self.data[row].x(qubit) {
+                self.rowsum(row, p);
             }
         }
-        
-        return false;  // Fixed: added return
+        self.data[p - n] = self.data[p].clone();
+        let mut z = Row::zero(self.data[p].xmask.len());
+        z.zmask[qubit / 64] |= 1 << (qubit % 64);
+        z.phase = outcome;
+        self.data[p] = z;
+        true
+    }
+
+    // Row h *= row i, keeping track of the sign. The product of the two
+    // Paulis on each qubit contributes a power i^g, and because the rows
+    // commute the total exponent 2 r_h + 2 r_i + sum g is 0 or 2 (mod 4).
+    fn rowsum(&mut self, h: usize, i: usize) {
+        let mut exponent = 2 * (self.data[h].phase as i64 + self.data[i].phase as i64);
+        for q in 0..self.num_qubits {
+            let (x1, z1) = self.pauli(i, q);
+            let (x2, z2) = self.pauli(h, q);
+            exponent += g(x1, z1, x2, z2);
+        }
+        debug_assert!(exponent.rem_euclid(2) == 0, "rowsum of anticommuting rows");
+        let (src, dst) = if i < h {
+            let (lo, hi) = self.data.split_at_mut(h);
+            (&lo[i], &mut hi[0])
+        } else {
+            let (lo, hi) = self.data.split_at_mut(i);
+            (&hi[0], &mut lo[h])
+        };
+        for (d, s) in dst.xmask.iter_mut().zip(&src.xmask) {
+            *d ^= s;
+        }
+        for (d, s) in dst.zmask.iter_mut().zip(&src.zmask) {
+            *d ^= s;
+        }
+        dst.phase = exponent.rem_euclid(4) == 2;
     }
 
     pub fn dump(&self) {
         println!("Tableau: {:?}", self.data);
     }
+
+    // Dense amplitudes of the stabilizer state (global phase is arbitrary).
+    // Projects computational basis states onto the +1 eigenspace of every
+    // stabilizer generator until one survives: O(n^2 2^n).
+    pub fn to_statevector(&self) -> Vec<Complex> {
+        let dim = 1usize << self.num_qubits;
+        for seed in 0..dim {
+            let mut psi = vec![Complex::zero(); dim];
+            psi[seed] = Complex::one();
+            for row in &self.data[self.num_qubits..2 * self.num_qubits] {
+                let applied = self.apply_row(row, &psi);
+                for (amp, p) in psi.iter_mut().zip(applied.iter()) {
+                    *amp = Complex::new((amp.re + p.re) / 2.0, (amp.im + p.im) / 2.0);
+                }
+            }
+            let norm2: f64 = psi.iter().map(|a| a.magnitude2()).sum();
+            if norm2 > 1e-9 {
+                let norm = norm2.sqrt();
+                return psi.iter().map(|a| Complex::new(a.re / norm, a.im / norm)).collect();
+            }
+        }
+        unreachable!("stabilizer group has an empty +1 eigenspace")
+    }
+
+    // Multiply a dense vector by the Pauli operator stored in `row`.
+    // A row with both x and z set on a qubit is Y = iXZ.
+    fn apply_row(&self, row: &Row, psi: &[Complex]) -> Vec<Complex> {
+        let (mut x, mut z, mut y_count) = (0usize, 0usize, 0u32);
+        for q in 0..self.num_qubits {
+            let (chunk, mask) = (q / 64, 1u64 << (q % 64));
+            let xq = row.xmask[chunk] & mask != 0;
+            let zq = row.zmask[chunk] & mask != 0;
+            if xq { x |= 1 << q; }
+            if zq { z |= 1 << q; }
+            if xq && zq { y_count += 1; }
+        }
+        // i^{#Y} * (-1)^phase
+        let mut factor = [Complex::one(), Complex::new(0.0, 1.0), Complex::new(-1.0, 0.0), Complex::new(0.0, -1.0)]
+            [(y_count % 4) as usize];
+        if row.phase {
+            factor = Complex::new(-factor.re, -factor.im);
+        }
+        let mut out = vec![Complex::zero(); psi.len()];
+        for (b, amp) in psi.iter().enumerate() {
+            let sign = if (b & z).count_ones() % 2 == 1 { -1.0 } else { 1.0 };
+            out[b ^ x] = amp.mul(&factor).mul(&Complex::new(sign, 0.0));
+        }
+        out
+    }
+}
+
+// Exponent of i picked up when the Pauli (x1, z1) multiplies (x2, z2) from
+// the left, for one qubit (Aaronson & Gottesman's g)
+pub(crate) fn g(x1: bool, z1: bool, x2: bool, z2: bool) -> i64 {
+    let (x2, z2) = (x2 as i64, z2 as i64);
+    match (x1, z1) {
+        (false, false) => 0,
+        (true, true) => z2 - x2,
+        (true, false) => z2 * (2 * x2 - 1),
+        (false, true) => x2 * (1 - 2 * z2),
+    }
 }
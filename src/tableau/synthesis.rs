@@ -0,0 +1,125 @@
+// Clifford synthesis from a full tableau (see `Tableau::identity`). The
+// tableau is reduced to the identity one qubit at a time, in the spirit of
+// Aaronson & Gottesman's canonical form: for qubit i, H/CNOT/S turn its
+// destabilizer into X_i and then gates that fix X_i turn its stabilizer into
+// Z_i. Qubits below i are never touched again, and rows of later qubits
+// already commute off qubit i. Every qubit costs at most 3n CNOTs, so the
+// whole circuit needs O(n^2). The reducing gates, undone in reverse after
+// the Paulis that fix the signs, give the Clifford itself.
+use crate::tableau::gates::CliffordGate;
+use crate::tableau::simulator::Tableau;
+
+struct Reducer {
+    t: Tableau,
+    gates: Vec<CliffordGate>,
+}
+
+impl Reducer {
+    fn h(&mut self, q: usize) {
+        self.t.apply_h(q);
+        self.gates.push(CliffordGate::H(q));
+    }
+
+    fn s(&mut self, q: usize) {
+        self.t.apply_s(q);
+        self.gates.push(CliffordGate::S(q));
+    }
+
+    fn cnot(&mut self, c: usize, t: usize) {
+        self.t.apply_cnot(c, t);
+        self.gates.push(CliffordGate::CNOT(c, t));
+    }
+
+    fn x(&self, row: usize, q: usize) -> bool {
+        self.t.pauli(row, q).0
+    }
+
+    fn z(&self, row: usize, q: usize) -> bool {
+        self.t.pauli(row, q).1
+    }
+
+    fn reduce_qubit(&mut self, i: usize) {
+        let n = self.t.num_qubits();
+        let (destab, stab) = (i, n + i);
+
+        // Destabilizer: give it an X somewhere, gather its X part on qubit
+        // i, then clear its Z part
+        if !(i..n).any(|q| self.x(destab, q)) {
+            let q = (i..n).find(|&q| self.z(destab, q)).expect("destabilizer acts on a reduced qubit");
+            self.h(q);
+        }
+        let j = (i..n).find(|&q| self.x(destab, q)).unwrap();
+        for k in i..n {
+            if k != j && self.x(destab, k) {
+                self.cnot(j, k);
+            }
+        }
+        if j != i {
+            self.cnot(j, i);
+            self.cnot(i, j);
+        }
+        if self.z(destab, i) {
+            self.s(i);
+        }
+        // X_i Z_k becomes X_i under H_k CNOT(i, k) H_k
+        let zs: Vec<usize> = (i + 1..n).filter(|&k| self.z(destab, k)).collect();
+        for &k in &zs {
+            self.h(k);
+        }
+        for &k in &zs {
+            self.cnot(i, k);
+        }
+        for &k in &zs {
+            self.h(k);
+        }
+
+        // Stabilizer: it anticommutes with X_i, so it has a Z on qubit i.
+        // H S H on i turns a Y there into Z, other qubits are turned into Z
+        // and then folded onto qubit i with CNOTs targeting it.
+        if self.x(stab, i) {
+            self.h(i);
+            self.s(i);
+            self.h(i);
+        }
+        for k in i + 1..n {
+            match self.t.pauli(stab, k) {
+                (true, false) => self.h(k),
+                (true, true) => {
+                    self.s(k);
+                    self.h(k);
+                }
+                _ => {}
+            }
+        }
+        for k in i + 1..n {
+            if self.z(stab, k) {
+                self.cnot(k, i);
+            }
+        }
+    }
+}
+
+// Gates (in circuit order) implementing the Clifford whose tableau is `t`,
+// up to global phase
+pub fn synthesize(t: Tableau) -> Vec<CliffordGate> {
+    let n = t.num_qubits();
+    let mut r = Reducer { t, gates: Vec::new() };
+    for i in 0..n {
+        r.reduce_qubit(i);
+    }
+    // Z_i flips the sign of X_i and X_i that of Z_i
+    let mut out = Vec::new();
+    for i in 0..n {
+        if r.t.phase(i) {
+            out.push(CliffordGate::Z(i));
+        }
+        if r.t.phase(n + i) {
+            out.push(CliffordGate::X(i));
+        }
+    }
+    out.extend(r.gates.into_iter().rev().map(|g| match g {
+        CliffordGate::S(q) => CliffordGate::Sdg(q),
+        g => g,
+    }));
+    out
+}
@@ -1,4 +1,10 @@
-use quantum_sim::compiler::runtime::compile_and_run;
+use quantum_sim::compiler::codegen::compile;
+use quantum_sim::compiler::diagnostics::DiagnosticKind;
+use quantum_sim::compiler::ir::IROp;
+use quantum_sim::compiler::runtime::{compile_and_run, try_compile_and_run, RunError};
+use quantum_sim::math::complex::Complex;
+use quantum_sim::runtime::controller::{RuntimeError, MAX_LOOP_ITERATIONS};
+use quantum_sim::runtime::statevector_backend::StatevectorSimulator;
 
 #[test]
 fn hybrid_execution_switches_modes() {
@@ -13,3 +19,255 @@ fn hybrid_execution_switches_modes() {
     let result = compile_and_run(src);
     assert_eq!(result.len(), 2);
 }
+
+#[test]
+fn measurements_after_promotion_stay_correlated() {
+    let src = "
+        qubit q0, q1;
+        h q0;
+        cnot q0, q1;
+        t q0;
+        measure q0, q1;
+    ";
+    for _ in 0..20 {
+        let result = compile_and_run(src);
+        assert_eq!(result[0], result[1]);
+    }
+}
+
+#[test]
+fn deterministic_circuit_on_tableau() {
+    let result = compile_and_run("qubit a, b; x a; measure a, b;");
+    assert_eq!(result, vec![true, false]);
+}
+
+#[test]
+fn codegen_resolves_names_and_allocates_clbits() {
+    let prog = compile("qubit q0, r[2]; h r[1]; rz(-pi) q0; measure r, q0;").unwrap();
+    assert_eq!(prog.num_qubits, 3);
+    assert_eq!(prog.num_clbits, 3);
+    assert_eq!(prog.ops, vec![
+        IROp::H(2),
+        IROp::RZ(0, -std::f64::consts::PI),
+        IROp::Measure(1, 0),
+        IROp::Measure(2, 1),
+        IROp::Measure(0, 2),
+    ]);
+}
+
+#[test]
+fn toffoli_expands_to_clifford_t() {
+    let prog = compile("qubit a, b, c; toffoli a, b, c;").unwrap();
+    assert!(prog.ops.iter().all(|op| op.is_clifford() || matches!(op, IROp::T(_) | IROp::Tdg(_))));
+
+    // Check the expansion on every basis state against the direct Toffoli
+    for basis in 0..8 {
+        let mut expanded = StatevectorSimulator::new(3);
+        expanded.state = vec![Complex::zero(); 8];
+        expanded.state[basis] = Complex::one();
+        let mut direct = StatevectorSimulator::from(expanded.state.clone());
+        for op in &prog.ops {
+            expanded.apply_ir(op);
+        }
+        direct.apply_toffoli(0, 1, 2);
+        for (x, y) in expanded.state.iter().zip(direct.state.iter()) {
+            assert!((x.re - y.re).abs() < 1e-9 && (x.im - y.im).abs() < 1e-9);
+        }
+    }
+}
+
+#[test]
+fn compile_errors_are_returned_not_panicked() {
+    match try_compile_and_run("qubit q0; cnot q0;") {
+        Err(RunError::Compile(errors)) => assert_eq!(errors.len(), 1),
+        other => panic!("expected a compile error, got {:?}", other),
+    }
+}
+
+#[test]
+fn user_gates_are_inlined_with_parameters() {
+    let prog = compile("
+        gate crz(theta) c, t {
+            rz(theta/2) t;
+            cnot c, t;
+            rz(-theta/2) t;
+            cnot c, t;
+        }
+        gate layer(a, b) x, y {
+            crz(a + b) y, x;
+            h y;
+        }
+        qubit q[2];
+        layer(pi, 2*pi) q[0], q[1];
+    ").unwrap();
+    let half = 3.0 * std::f64::consts::PI / 2.0;
+    assert_eq!(prog.ops, vec![
+        IROp::RZ(0, half),
+        IROp::CNOT(1, 0),
+        IROp::RZ(0, -half),
+        IROp::CNOT(1, 0),
+        IROp::H(1),
+    ]);
+}
+
+#[test]
+fn user_gates_run_end_to_end() {
+    let result = compile_and_run("
+        gate bell a, b { h a; cnot a, b; }
+        gate unbell a, b { cnot a, b; h a; }
+        gate flip_both a, b { x a; x b; }
+        qubit p, q;
+        bell p, q;
+        unbell p, q;
+        flip_both q, p;
+        measure p, q;
+    ");
+    assert_eq!(result, vec![true, true]);
+}
+
+#[test]
+fn declared_bits_and_conditions_lower_to_ir() {
+    let prog = compile("
+        qubit q[2];
+        bit c[2], flag;
+        measure q -> c;
+        if (c == 2) x q[0];
+        if (!flag) { h q[1]; } else { z q[1]; }
+        while (c[0] != 1) { reset q[0]; h q[0]; measure q[0] -> c[0]; }
+        measure q[1];
+    ").unwrap();
+    assert_eq!(prog.num_clbits, 4);
+    assert_eq!(prog.cregs.iter().map(|r| r.name.as_str()).collect::<Vec<_>>(), vec!["c", "flag", "meas"]);
+    assert_eq!(prog.ops, vec![
+        IROp::Measure(0, 0),
+        IROp::Measure(1, 1),
+        IROp::Conditional { clbits: vec![0, 1], value: 2, op: Box::new(IROp::X(0)) },
+        IROp::IfElse { clbits: vec![2], value: 0, then_ops: vec![IROp::H(1)], else_ops: vec![IROp::Z(1)] },
+        IROp::While { clbits: vec![0], value: 0, body: vec![IROp::Reset(0), IROp::H(0), IROp::Measure(0, 0)] },
+        IROp::Measure(1, 3),
+    ]);
+}
+
+#[test]
+fn teleportation_with_feed_forward() {
+    // Teleport ry(2pi/3)|0> from q[0] to q[2], then undo the rotation there
+    let src = "
+        qubit q[3];
+        bit m[2], out;
+        ry(2*pi/3) q[0];
+        h q[1];
+        cnot q[1], q[2];
+        cnot q[0], q[1];
+        h q[0];
+        measure q[0], q[1] -> m[0], m[1];
+        if (m[1]) x q[2];
+        if (m[0]) z q[2];
+        ry(-2*pi/3) q[2];
+        measure q[2] -> out;
+    ";
+    for _ in 0..20 {
+        assert!(!compile_and_run(src)[2]);
+    }
+}
+
+#[test]
+fn repeat_until_success_loop() {
+    let src = "
+        qubit q;
+        bit done;
+        while (!done) {
+            reset q;
+            h q;
+            measure q -> done;
+        }
+    ";
+    for _ in 0..10 {
+        assert_eq!(compile_and_run(src), vec![true]);
+    }
+}
+
+#[test]
+fn runaway_loops_are_errors_not_panics() {
+    // The body rewrites the condition bit, but always with 1
+    let src = "
+        qubit q;
+        bit c;
+        x q;
+        measure q -> c;
+        while (c) {
+            h q;
+            h q;
+            measure q -> c;
+        }
+    ";
+    assert_eq!(try_compile_and_run(src), Err(RunError::Runtime(RuntimeError::LoopLimit { iterations: MAX_LOOP_ITERATIONS })));
+
+    // A body that never writes the condition cannot end once entered
+    let errors = compile("qubit q; bit c; while (!c) { h q; }").unwrap_err();
+    assert_eq!(errors[0].kind, DiagnosticKind::Unsupported);
+}
+
+#[test]
+fn bit_flip_code_corrects_single_error() {
+    // Encode a logical superposition, flip the middle qubit, correct it
+    let src = "
+        qubit d[3], a[2];
+        bit s[2], out[3];
+        ry(2*pi/3) d[0];
+        cnot d[0], d[1];
+        cnot d[0], d[2];
+        x d[1];
+        cnot d[0], a[0];
+        cnot d[1], a[0];
+        cnot d[1], a[1];
+        cnot d[2], a[1];
+        measure a -> s;
+        if (s == 1) x d[0];
+        if (s == 3) x d[1];
+        if (s == 2) x d[2];
+        measure d -> out;
+    ";
+    for _ in 0..20 {
+        let bits = compile_and_run(src);
+        assert_eq!(&bits[..2], &[true, true]);
+        assert!(bits[2] == bits[3] && bits[3] == bits[4]);
+    }
+}
+
+#[test]
+fn for_loops_unroll_with_folded_constants() {
+    let prog = compile("
+        qubit q[4];
+        for i in 0..3 {
+            cnot q[i], q[i+1];
+            rz(2*pi/8) q[i+1];
+        }
+        for layer in 1..3 {
+            for j in 0..2 {
+                rx(layer*pi/(j+2)) q[2*j];
+            }
+        }
+        for i in 4..0 { h q[0]; }
+    ").unwrap();
+    let pi = std::f64::consts::PI;
+    assert_eq!(prog.ops, vec![
+        IROp::CNOT(0, 1), IROp::RZ(1, 2.0 * pi / 8.0),
+        IROp::CNOT(1, 2), IROp::RZ(2, 2.0 * pi / 8.0),
+        IROp::CNOT(2, 3), IROp::RZ(3, 2.0 * pi / 8.0),
+        IROp::RX(0, pi / 2.0), IROp::RX(2, pi / 3.0),
+        IROp::RX(0, 2.0 * pi / 2.0), IROp::RX(2, 2.0 * pi / 3.0),
+    ]);
+}
+
+#[test]
+fn loops_compose_with_measurement_and_user_gates() {
+    let result = compile_and_run("
+        gate flip(k) a { rx(k*pi) a; }
+        qubit q[3];
+        for i in 0..3 {
+            flip(i+1) q[i];
+            measure q[i];
+        }
+    ");
+    assert_eq!(result, vec![true, false, true]);
+}
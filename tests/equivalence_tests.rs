@@ -0,0 +1,207 @@
+use quantum_sim::compiler::equivalence::{check_equivalence, Equivalence, EquivalenceChecker, Method};
+use quantum_sim::compiler::ir::{IROp, IRProgram};
+use quantum_sim::compiler::optimizer::analysis::Analyses;
+use quantum_sim::compiler::optimizer::clifford_resynth::CliffordResynthesis;
+use quantum_sim::compiler::optimizer::decompose::{Basis, Decompose};
+use quantum_sim::compiler::optimizer::pass_manager::{OptLevel, Pass, PassManager};
+use quantum_sim::compiler::optimizer::peephole::Peephole;
+use quantum_sim::compiler::optimizer::phase_folding::PhaseFolding;
+use quantum_sim::compiler::optimizer::push_back::NonCliffordPushBack;
+use quantum_sim::compiler::optimizer::rz_synthesis::RotationSynthesis;
+use quantum_sim::compiler::optimizer::simplify::RemoveIdentities;
+use quantum_sim::compiler::optimizer::zx_simplify::ZXSimplify;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::f64::consts::PI;
+
+fn random_circuit(rng: &mut StdRng, n: usize, len: usize, clifford: bool) -> IRProgram {
+    let mut prog = IRProgram::new(n, 0);
+    for _ in 0..len {
+        let a = rng.gen_range(0..n);
+        let b = (a + rng.gen_range(1..n)) % n;
+        prog.push(match rng.gen_range(0..if clifford { 6 } else { 9 }) {
+            0 => IROp::H(a),
+            1 => IROp::S(a),
+            2 => IROp::CZ(a, b),
+            3 => IROp::SX(a),
+            4 => IROp::RZ(a, PI / 2.0),
+            5 => IROp::CNOT(a, b),
+            6 => IROp::T(a),
+            7 => IROp::RZ(a, rng.gen_range(-3.0..3.0)),
+            _ => IROp::Toffoli(a, b, (b + rng.gen_range(1..n - 1)) % n),
+        });
+    }
+    // Toffolis whose target wrapped onto a control
+    prog.ops.retain(|op| {
+        let mut qs = op.qubits();
+        qs.sort_unstable();
+        qs.windows(2).all(|w| w[0] != w[1])
+    });
+    prog
+}
+
+#[test]
+fn clifford_circuits_are_compared_on_tableaux() {
+    let mut rng = StdRng::seed_from_u64(21);
+    for _ in 0..20 {
+        let prog = random_circuit(&mut rng, 6, 60, true);
+        let resynthesized = PassManager::new().with(CliffordResynthesis).run(prog.clone());
+        assert_eq!(check_equivalence(&prog, &resynthesized), Equivalence::Equivalent(Method::Tableau));
+        let mut changed = prog.clone();
+        changed.ops.insert(rng.gen_range(0..prog.len()), IROp::S(rng.gen_range(0..6)));
+        assert_eq!(check_equivalence(&prog, &changed), Equivalence::NotEquivalent(Method::Tableau));
+    }
+    // Y = i X Z only differs by a global phase
+    let mut a = IRProgram::new(1, 0);
+    a.ops = vec![IROp::Z(0), IROp::X(0)];
+    let mut b = IRProgram::new(1, 0);
+    b.ops = vec![IROp::Y(0)];
+    assert!(check_equivalence(&a, &b).is_equivalent());
+}
+
+#[test]
+fn small_circuits_are_compared_on_random_states() {
+    let mut rng = StdRng::seed_from_u64(22);
+    for _ in 0..10 {
+        let prog = random_circuit(&mut rng, 5, 50, false);
+        let optimized = PassManager::for_level(OptLevel::O3).run(prog.clone());
+        assert_eq!(check_equivalence(&prog, &optimized), Equivalence::Equivalent(Method::Statevector));
+        let mut changed = prog.clone();
+        changed.ops.push(IROp::RZ(0, 1e-3));
+        assert_eq!(check_equivalence(&prog, &changed), Equivalence::NotEquivalent(Method::Statevector));
+        // A looser tolerance accepts approximations
+        let loose = EquivalenceChecker { tolerance: 1e-5, ..Default::default() };
+        assert!(loose.check(&prog, &changed).is_equivalent());
+    }
+}
+
+#[test]
+fn large_circuits_are_compared_through_the_miter() {
+    let mut rng = StdRng::seed_from_u64(23);
+    let base = random_circuit(&mut rng, 20, 200, false);
+    let with = |extra: Vec<IROp>| {
+        let mut prog = base.clone();
+        prog.ops.extend(extra);
+        prog
+    };
+    // The miter leaves T T Sdg on qubit 0
+    assert_eq!(
+        check_equivalence(&with(vec![IROp::T(0), IROp::T(0)]), &with(vec![IROp::S(0)])),
+        Equivalence::Equivalent(Method::Miter),
+    );
+    assert_eq!(
+        check_equivalence(&with(vec![IROp::T(0)]), &with(vec![IROp::S(0)])),
+        Equivalence::NotEquivalent(Method::Miter),
+    );
+    // A Clifford residue is checked on the tableau
+    assert_eq!(
+        check_equivalence(&with(vec![IROp::CZ(3, 7)]), &with(vec![IROp::H(7), IROp::CNOT(3, 7), IROp::H(7)])),
+        Equivalence::Equivalent(Method::Miter),
+    );
+    assert_eq!(
+        check_equivalence(&with(vec![IROp::CZ(3, 7)]), &with(vec![IROp::CNOT(3, 7)])),
+        Equivalence::NotEquivalent(Method::Miter),
+    );
+    // Inverse pairs cancel wherever they are
+    let mut padded = base.clone();
+    padded.ops.splice(0..0, [IROp::Tdg(0), IROp::T(0)]);
+    assert_eq!(check_equivalence(&padded, &base), Equivalence::Equivalent(Method::Miter));
+    // A difference at the start is peeled out of the whole circuit
+    let mut changed = base.clone();
+    changed.ops.insert(0, IROp::T(0));
+    assert_eq!(check_equivalence(&changed, &base), Equivalence::NotEquivalent(Method::Miter));
+    // Optimizer output too large for statevectors
+    for level in [OptLevel::O1, OptLevel::O2] {
+        let optimized = PassManager::for_level(level).run(base.clone());
+        assert_eq!(check_equivalence(&base, &optimized), Equivalence::Equivalent(Method::Miter));
+    }
+    // A residue on more qubits than can be simulated is left open
+    let (toffoli, ccz) = (with(vec![IROp::Toffoli(0, 1, 2)]), with(vec![IROp::CCZ(0, 1, 2)]));
+    assert_eq!(check_equivalence(&toffoli, &ccz), Equivalence::NotEquivalent(Method::Miter));
+    let narrow = EquivalenceChecker { max_dense_qubits: 2, ..Default::default() };
+    assert!(matches!(narrow.check(&toffoli, &ccz), Equivalence::Unknown(_)));
+}
+
+#[test]
+fn measurements_and_blocks_have_to_line_up() {
+    let mut a = IRProgram::new(2, 2);
+    a.ops = vec![
+        IROp::H(0), IROp::T(0), IROp::T(0), IROp::Measure(0, 0),
+        IROp::IfElse { clbits: vec![0], value: 1, then_ops: vec![IROp::X(1), IROp::X(1), IROp::T(1)], else_ops: vec![] },
+        IROp::Conditional { clbits: vec![0], value: 0, op: Box::new(IROp::CZ(0, 1)) },
+        IROp::Measure(1, 1),
+    ];
+    let mut b = a.clone();
+    b.ops = vec![
+        IROp::H(0), IROp::S(0), IROp::Measure(0, 0), IROp::Promote,
+        IROp::IfElse { clbits: vec![0], value: 1, then_ops: vec![IROp::T(1)], else_ops: vec![IROp::Barrier(vec![0, 1])] },
+        IROp::Conditional { clbits: vec![0], value: 0, op: Box::new(IROp::CZ(1, 0)) },
+        IROp::Demote, IROp::Measure(1, 1),
+    ];
+    assert!(check_equivalence(&a, &b).is_equivalent());
+
+    let mut c = b.clone();
+    c.ops.remove(2);
+    assert!(matches!(check_equivalence(&a, &c), Equivalence::Unknown(_)));
+    let mut d = b.clone();
+    d.ops[4] = IROp::IfElse { clbits: vec![0], value: 1, then_ops: vec![IROp::Tdg(1)], else_ops: vec![] };
+    assert!(!check_equivalence(&a, &d).is_equivalent());
+}
+
+fn verify(prog: &IRProgram, pass: impl Pass + 'static) {
+    let mut pm = PassManager::new().verified(EquivalenceChecker::default()).with(pass);
+    pm.run(prog.clone());
+    assert!(pm.verification_failures().is_empty(), "{}", pm.stats()[0]);
+}
+
+#[test]
+fn every_pass_is_validated_by_the_pass_manager() {
+    let mut rng = StdRng::seed_from_u64(24);
+    for _ in 0..5 {
+        let prog = random_circuit(&mut rng, 4, 40, false);
+        verify(&prog, RemoveIdentities);
+        verify(&prog, Peephole);
+        verify(&prog, PhaseFolding);
+        verify(&prog, ZXSimplify);
+        verify(&prog, CliffordResynthesis);
+        verify(&prog, NonCliffordPushBack);
+        verify(&prog, Decompose::new(Basis::CliffordT));
+        verify(&prog, Decompose::new(Basis::RzSx));
+        // Approximate synthesis is checked at its own precision
+        let lowered = PassManager::new().with(Decompose::new(Basis::CliffordT)).run(prog.clone());
+        let mut pm = PassManager::new()
+            .verified(EquivalenceChecker { tolerance: 1e-3, ..Default::default() })
+            .with(RotationSynthesis::new(1e-3));
+        pm.run(lowered);
+        assert!(pm.verification_failures().is_empty());
+    }
+}
+
+struct DropFirstT;
+
+impl Pass for DropFirstT {
+    fn name(&self) -> &'static str {
+        "drop_first_t"
+    }
+
+    fn run(&mut self, mut prog: IRProgram, _: &Analyses) -> IRProgram {
+        if let Some(i) = prog.ops.iter().position(|op| matches!(op, IROp::T(_))) {
+            prog.ops.remove(i);
+        }
+        prog
+    }
+}
+
+#[test]
+fn verification_catches_and_reverts_broken_passes() {
+    let mut prog = IRProgram::new(2, 0);
+    prog.ops = vec![IROp::H(0), IROp::T(0), IROp::CNOT(0, 1)];
+    let mut pm = PassManager::new().verified(EquivalenceChecker::default()).with(DropFirstT).with(Peephole);
+    let out = pm.run(prog.clone());
+    assert_eq!(out.ops, prog.ops);
+    let failures = pm.verification_failures();
+    assert_eq!(failures.len(), 1);
+    assert_eq!(failures[0].pass, "drop_first_t");
+    assert!(failures[0].to_string().ends_with("(failed verification, reverted)"));
+    assert!(pm.stats()[1].verification.as_ref().is_some_and(|v| v.is_equivalent()));
+}
@@ -0,0 +1,47 @@
+use quantum_sim::compiler::ir::{IRError, IROp, IRProgram};
+use std::f64::consts::PI;
+
+#[test]
+fn op_queries_report_qubits_and_params() {
+    let op = IROp::MCX(vec![0, 2], 1);
+    assert_eq!(op.qubits(), vec![0, 2, 1]);
+    assert_eq!(IROp::U3(0, 0.1, 0.2, 0.3).params(), vec![0.1, 0.2, 0.3]);
+    assert_eq!(IROp::Measure(3, 1).clbits(), vec![1]);
+    assert!(!IROp::Reset(0).is_unitary());
+}
+
+#[test]
+fn clifford_classification_depends_on_angle() {
+    assert!(IROp::CNOT(0, 1).is_clifford());
+    assert!(IROp::RZ(0, PI / 2.0).is_clifford());
+    assert!(!IROp::RZ(0, PI / 4.0).is_clifford());
+    assert!(!IROp::T(0).is_clifford());
+}
+
+#[test]
+fn inverses_round_trip() {
+    for op in [IROp::S(0), IROp::T(1), IROp::RZ(0, 0.3), IROp::U3(0, 0.1, 0.2, 0.3), IROp::H(2)] {
+        assert_eq!(op.inverse().unwrap().inverse().unwrap(), op);
+    }
+    assert_eq!(IROp::Measure(0, 0).inverse(), None);
+}
+
+#[test]
+fn validate_rejects_bad_indices() {
+    let mut prog = IRProgram::new(2, 1);
+    prog.push(IROp::H(0));
+    prog.push(IROp::CNOT(0, 1));
+    prog.push(IROp::Measure(1, 0));
+    assert_eq!(prog.validate(), Ok(()));
+
+    prog.push(IROp::CZ(1, 1));
+    assert_eq!(prog.validate(), Err(IRError::DuplicateQubit { op: 3, qubit: 1 }));
+
+    let mut prog = IRProgram::new(2, 1);
+    prog.push(IROp::Measure(0, 4));
+    assert_eq!(prog.validate(), Err(IRError::ClbitOutOfRange { op: 0, clbit: 4, num_clbits: 1 }));
+
+    let mut prog = IRProgram::new(2, 0);
+    prog.push(IROp::Toffoli(0, 1, 2));
+    assert!(matches!(prog.validate(), Err(IRError::QubitOutOfRange { qubit: 2, .. })));
+}
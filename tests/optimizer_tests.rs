@@ -0,0 +1,469 @@
+use quantum_sim::compiler::codegen::compile;
+use quantum_sim::compiler::ir::{IROp, IRProgram};
+use quantum_sim::compiler::optimizer::clifford_resynth::{resynthesize_clifford, CliffordResynthesis};
+use quantum_sim::compiler::optimizer::analysis::{depth, non_clifford_ops, t_count, AnalysisKind, Analyses, Metrics};
+use quantum_sim::compiler::optimizer::decompose::{Basis, Decompose};
+use quantum_sim::compiler::optimizer::gadgetize::{t_gadget, TGadgetization};
+use quantum_sim::compiler::optimizer::optimize;
+use quantum_sim::compiler::optimizer::pass_manager::{OptLevel, Pass, PassManager};
+use quantum_sim::compiler::optimizer::peephole::Peephole;
+use quantum_sim::compiler::optimizer::phase_folding::{fold_phases, TCountReport};
+use quantum_sim::compiler::optimizer::push_back::NonCliffordPushBack;
+use quantum_sim::compiler::optimizer::simplify::RemoveIdentities;
+use quantum_sim::compiler::resources::estimate_resources;
+use quantum_sim::math::complex::Complex;
+use quantum_sim::runtime::controller::{BackendType, RuntimeController};
+use quantum_sim::runtime::statevector_backend::StatevectorSimulator;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::f64::consts::PI;
+
+#[test]
+fn metrics_count_gates_depth_and_t() {
+    let prog = compile("
+        qubit q[3];
+        h q[0];
+        cnot q[0], q[1];
+        t q[2];
+        rz(3*pi/4) q[2];
+        rz(pi/2) q[1];
+        measure q;
+    ").unwrap();
+    let m = Metrics::of(&prog);
+    assert_eq!(m, Metrics { gates: 5, two_qubit: 1, depth: 4, t_count: 2 });
+    assert_eq!(non_clifford_ops(&prog.ops), vec![2, 3]);
+}
+
+#[test]
+fn depth_follows_barriers_and_classical_wires() {
+    let mut prog = IRProgram::new(2, 1);
+    prog.push(IROp::H(0));
+    prog.push(IROp::Barrier(vec![0, 1]));
+    prog.push(IROp::X(1));
+    prog.push(IROp::Measure(1, 0));
+    prog.push(IROp::Conditional { clbits: vec![0], value: 1, op: Box::new(IROp::X(0)) });
+    assert_eq!(depth(&prog), 4);
+    assert_eq!(t_count(&[IROp::IfElse { clbits: vec![0], value: 1, then_ops: vec![IROp::T(0)], else_ops: vec![IROp::Tdg(1)] }]), 2);
+}
+
+#[test]
+fn remove_identities_recurses_into_blocks() {
+    let mut prog = IRProgram::new(2, 1);
+    prog.push(IROp::I(0));
+    prog.push(IROp::RZ(1, 2.0 * PI));
+    prog.push(IROp::H(0));
+    prog.push(IROp::Conditional { clbits: vec![0], value: 1, op: Box::new(IROp::RX(0, 0.0)) });
+    prog.push(IROp::IfElse { clbits: vec![0], value: 0, then_ops: vec![IROp::I(1)], else_ops: Vec::new() });
+    prog.push(IROp::While { clbits: vec![0], value: 0, body: vec![IROp::I(0), IROp::Measure(0, 0)] });
+    let out = PassManager::new().with(RemoveIdentities).run(prog);
+    assert_eq!(out.ops, vec![
+        IROp::H(0),
+        IROp::While { clbits: vec![0], value: 0, body: vec![IROp::Measure(0, 0)] },
+    ]);
+    assert_eq!(out.metadata["passes"], "remove_identities");
+}
+
+// Records the depth it was handed and appends an X
+struct Probe {
+    seen: Vec<usize>,
+}
+
+impl Pass for Probe {
+    fn name(&self) -> &'static str {
+        "probe"
+    }
+
+    fn requires(&self) -> &[AnalysisKind] {
+        &[AnalysisKind::Depth]
+    }
+
+    fn run(&mut self, mut prog: IRProgram, analyses: &Analyses) -> IRProgram {
+        self.seen.push(analyses.depth());
+        prog.push(IROp::X(0));
+        prog
+    }
+}
+
+#[test]
+fn pass_manager_supplies_analyses_and_records_stats() {
+    let mut pm = PassManager::new().with(Probe { seen: Vec::new() }).with(RemoveIdentities);
+    pm.add(Probe { seen: Vec::new() });
+    assert_eq!(pm.pass_names(), vec!["probe", "remove_identities", "probe"]);
+
+    let mut prog = IRProgram::new(1, 0);
+    prog.push(IROp::I(0));
+    let out = pm.run(prog);
+    assert_eq!(out.ops, vec![IROp::X(0), IROp::X(0)]);
+
+    let stats = pm.stats();
+    assert_eq!(stats.len(), 3);
+    assert_eq!((stats[0].before.gates, stats[0].after.gates), (1, 2));
+    assert_eq!((stats[1].before.depth, stats[1].after.depth), (2, 1));
+    assert!(stats.iter().all(|s| s.changed()));
+    assert_eq!(stats[1].to_string(), "remove_identities: gates 2 -> 1, depth 2 -> 1, T 0 -> 0");
+}
+
+#[test]
+fn levels_build_pipelines() {
+    assert!(PassManager::for_level(OptLevel::O0).pass_names().is_empty());
+    for level in [OptLevel::O1, OptLevel::O2, OptLevel::O3] {
+        let names = PassManager::for_level(level).pass_names();
+        assert!(names.contains(&"remove_identities") && names.contains(&"peephole"));
+    }
+    let prog = compile("qubit q; id q; h q;").unwrap();
+    assert_eq!(optimize(prog.clone(), OptLevel::O0), prog);
+}
+
+// Both programs map every basis state to the same vector, up to one global phase
+fn assert_equivalent(a: &IRProgram, b: &IRProgram) {
+    let dim = 1 << a.num_qubits;
+    let mut phase: Option<Complex> = None;
+    for basis in 0..dim {
+        let run = |prog: &IRProgram| {
+            let mut state = vec![Complex::zero(); dim];
+            state[basis] = Complex::one();
+            let mut sim = StatevectorSimulator::from(state);
+            for op in &prog.ops {
+                sim.apply_ir(op);
+            }
+            sim.state
+        };
+        let (x, y) = (run(a), run(b));
+        for (u, v) in x.iter().zip(&y) {
+            if u.magnitude2() > 1e-12 && phase.is_none() {
+                // v = phase * u
+                let d = u.magnitude2();
+                phase = Some(Complex::new((v.re * u.re + v.im * u.im) / d, (v.im * u.re - v.re * u.im) / d));
+            }
+            let p = phase.unwrap_or(Complex::one());
+            let w = p.mul(u);
+            assert!((w.re - v.re).abs() < 1e-9 && (w.im - v.im).abs() < 1e-9, "programs differ on basis state {}", basis);
+        }
+    }
+}
+
+fn peephole(prog: &IRProgram) -> IRProgram {
+    PassManager::new().with(Peephole).run(prog.clone())
+}
+
+#[test]
+fn peephole_cancels_inverse_pairs() {
+    let prog = compile("
+        qubit a, b, c;
+        h a; h a;
+        cnot a, b; x c; cnot a, b;
+        s b; sdg b;
+        t c; tdg c;
+        cz a, b; cz b, a;
+        swap a, c; swap c, a;
+    ").unwrap();
+    let out = peephole(&prog);
+    assert_eq!(out.ops, vec![IROp::X(2)]);
+    assert_equivalent(&prog, &out);
+}
+
+#[test]
+fn peephole_merges_phase_runs_and_rotations() {
+    let prog = compile("
+        qubit a, b;
+        t a; t a;
+        s b; s b;
+        t a; t a; t a; t a; t a; t a;
+        rz(0.25) b; rz(0.5) b;
+        rx(0.125) a; rx(-0.125) a;
+    ").unwrap();
+    let out = peephole(&prog);
+    assert_eq!(out.ops, vec![IROp::RZ(1, PI + 0.75)]);
+    assert_equivalent(&prog, &out);
+
+    let out = peephole(&compile("qubit a; t a; t a; t a;").unwrap());
+    assert_eq!(out.ops, vec![IROp::S(0), IROp::T(0)]);
+}
+
+#[test]
+fn peephole_commutes_only_when_safe() {
+    // RZ on the control moves through the CNOT; H on the target does not
+    let prog = compile("
+        qubit a, b;
+        rz(0.5) a; cnot a, b; rz(0.25) a;
+        h b; cnot a, b; h b;
+        x b; cnot a, b; x b;
+    ").unwrap();
+    let out = peephole(&prog);
+    assert_eq!(out.ops, vec![
+        IROp::RZ(0, 0.75), IROp::CNOT(0, 1),
+        IROp::H(1), IROp::CNOT(0, 1), IROp::H(1),
+        IROp::CNOT(0, 1),
+    ]);
+    assert_equivalent(&prog, &out);
+
+    // Measurement, reset and barriers stop the search on their qubits
+    let prog = compile("qubit a; bit c; h a; measure a -> c; h a; x a; reset a; x a;").unwrap();
+    assert_eq!(peephole(&prog).ops.len(), 6);
+    let mut barrier = IRProgram::new(2, 0);
+    barrier.ops = vec![IROp::T(0), IROp::Barrier(vec![0]), IROp::Tdg(0), IROp::H(1), IROp::Barrier(vec![0]), IROp::H(1)];
+    assert_eq!(peephole(&barrier).ops, vec![IROp::T(0), IROp::Barrier(vec![0]), IROp::Tdg(0), IROp::Barrier(vec![0])]);
+}
+
+#[test]
+fn peephole_reaches_into_blocks() {
+    let prog = compile("
+        qubit q; bit c;
+        measure q -> c;
+        if (c) { x q; x q; } else { s q; s q; }
+    ").unwrap();
+    let out = optimize(prog, OptLevel::O1);
+    assert_eq!(out.ops[1], IROp::IfElse { clbits: vec![0], value: 1, then_ops: Vec::new(), else_ops: vec![IROp::Z(0)] });
+}
+
+#[test]
+fn phase_folding_merges_terms_on_the_same_parity() {
+    // After the three CNOTs `b` holds the parity `a` had at the first T
+    let prog = compile("
+        qubit a, b, c;
+        t a;
+        cnot a, b; cnot b, a; cnot a, b;
+        tdg b;
+        t c; x c; t c;
+        t a; h a; t a;
+    ").unwrap();
+    let (out, report) = fold_phases(&prog);
+    // T·X·T on `c` is X up to global phase
+    assert_eq!(report, TCountReport { before: 6, after: 2 });
+    assert_eq!(out.ops, vec![
+        IROp::CNOT(0, 1), IROp::CNOT(1, 0), IROp::CNOT(0, 1),
+        IROp::X(2),
+        IROp::T(0), IROp::H(0), IROp::T(0),
+    ]);
+    assert_equivalent(&prog, &out);
+}
+
+#[test]
+fn phase_folding_shrinks_repeated_toffolis() {
+    let prog = compile("qubit a, b, c, d; toffoli a, b, c; cnot c, d; toffoli a, b, c; rz(0.3) a; cnot a, b; rz(0.2) b;").unwrap();
+    let (out, report) = fold_phases(&prog);
+    assert_eq!(report.before, 14);
+    assert!(report.after < report.before, "{:?}", report);
+    assert_equivalent(&prog, &out);
+
+    let out = optimize(prog.clone(), OptLevel::O2);
+    // Peephole runs first at O2, so folding starts from a smaller count
+    assert!(out.metadata.contains_key("t_count"));
+    assert!(t_count(&out.ops) <= report.after);
+    assert_equivalent(&prog, &out);
+}
+
+fn push_back(prog: &IRProgram) -> IRProgram {
+    PassManager::new().with(NonCliffordPushBack).run(prog.clone())
+}
+
+#[test]
+fn push_back_groups_non_clifford_gates_into_one_window() {
+    let prog = compile("
+        qubit a, b, c;
+        t a;
+        h b;
+        cnot b, c;
+        s a;
+        rz(0.3) c;
+        h c;
+    ").unwrap();
+    let out = push_back(&prog);
+    assert_eq!(out.ops, vec![
+        IROp::H(1), IROp::CNOT(1, 2), IROp::S(0),
+        IROp::Promote, IROp::T(0), IROp::RZ(2, 0.3), IROp::H(2),
+    ]);
+    assert_equivalent(&prog, &out);
+
+    // Running again moves nothing and does not duplicate markers
+    assert_eq!(push_back(&out).ops, out.ops);
+    // Clifford-only programs are left alone
+    let clifford = compile("qubit a, b; h a; cnot a, b;").unwrap();
+    assert_eq!(push_back(&clifford).ops, clifford.ops);
+}
+
+#[test]
+fn push_back_demotes_once_tainted_qubits_are_measured() {
+    let prog = compile("
+        qubit a, b, c;
+        h a;
+        t a;
+        cnot a, b;
+        h c;
+        measure a;
+        measure b;
+        t c;
+        measure c;
+    ").unwrap();
+    let out = push_back(&prog);
+    // T commutes with the CNOT on its control, so the first window only
+    // needs `a`
+    assert_eq!(out.ops, vec![
+        IROp::H(0), IROp::CNOT(0, 1), IROp::H(2),
+        IROp::Promote, IROp::T(0), IROp::Measure(0, 0), IROp::Demote,
+        IROp::Measure(1, 1),
+        IROp::Promote, IROp::T(2), IROp::Measure(2, 2), IROp::Demote,
+    ]);
+
+    // A gate that entangles a tainted qubit widens the window
+    let prog = compile("qubit a, b; h a; t a; h a; cnot a, b; measure a; measure b;").unwrap();
+    assert_eq!(push_back(&prog).ops, vec![
+        IROp::H(0), IROp::Promote, IROp::T(0), IROp::H(0), IROp::CNOT(0, 1),
+        IROp::Measure(0, 0), IROp::Measure(1, 1), IROp::Demote,
+    ]);
+}
+
+#[test]
+fn controller_returns_to_the_tableau_after_a_window() {
+    let prog = compile("qubit a, b; h a; t a; h a; measure a; h b; cnot b, a;").unwrap();
+
+    let mut plain = RuntimeController::new(prog.num_qubits, prog.num_clbits);
+    plain.execute(&prog.ops).unwrap();
+    assert_eq!(*plain.backend_type(), BackendType::RankDecomposition);
+
+    let marked = optimize(prog, OptLevel::O2);
+    assert!(marked.ops.contains(&IROp::Demote));
+    let mut controller = RuntimeController::new(marked.num_qubits, marked.num_clbits);
+    controller.execute(&marked.ops).unwrap();
+    assert_eq!(*controller.backend_type(), BackendType::Tableau);
+    let stats = controller.stats();
+    assert_eq!((stats.promotions, stats.demotions), (1, 1));
+    // h b and cnot b, a ran on the tableau
+    assert!(stats.tableau_ops >= 2, "{:?}", stats);
+}
+
+fn random_clifford(rng: &mut StdRng, n: usize, len: usize) -> Vec<IROp> {
+    (0..len).map(|_| {
+        let (a, b) = (rng.gen_range(0..n), rng.gen_range(0..n));
+        match rng.gen_range(0..9) {
+            0 => IROp::S(a),
+            1 => IROp::Sdg(a),
+            2 => IROp::X(a),
+            3 => IROp::Y(a),
+            4 => IROp::SX(a),
+            5 if a != b => IROp::CNOT(a, b),
+            6 if a != b => IROp::CZ(a, b),
+            7 if a != b => IROp::SWAP(a, b),
+            8 => IROp::RY(a, PI / 2.0),
+            _ => IROp::H(a),
+        }
+    }).collect()
+}
+
+#[test]
+fn clifford_runs_are_resynthesized_from_the_tableau() {
+    let mut rng = StdRng::seed_from_u64(7);
+    for _ in 0..200 {
+        let n = rng.gen_range(1..=5);
+        let len = rng.gen_range(0..80);
+        let mut prog = IRProgram::new(n, 0);
+        prog.ops = random_clifford(&mut rng, n, len);
+        let out = PassManager::new().with(CliffordResynthesis).run(prog.clone());
+        assert!(out.ops.len() <= prog.ops.len());
+        assert_equivalent(&prog, &out);
+        // At most 3n CNOTs per qubit
+        let cnots = out.ops.iter().filter(|op| op.qubits().len() == 2).count();
+        assert!(out.ops == prog.ops || cnots <= 3 * n * n, "{} CNOTs on {} qubits", cnots, n);
+    }
+}
+
+#[test]
+fn clifford_resynthesis_only_keeps_shorter_blocks() {
+    // Twelve gates that amount to a single SWAP's worth of CNOTs
+    let prog = compile("
+        qubit a, b;
+        h a; h b; cnot a, b; h a; h b; cnot b, a; s a; s a; z a; cnot b, a; h b; h b;
+        t a;
+        cnot a, b;
+    ").unwrap();
+    let out = PassManager::new().with(CliffordResynthesis).run(prog.clone());
+    let t = out.ops.iter().position(|op| *op == IROp::T(0)).unwrap();
+    assert!(t < 12, "{:?}", out.ops);
+    assert_equivalent(&prog, &out);
+    // A single CNOT cannot get any shorter
+    assert_eq!(out.ops[t..], prog.ops[12..]);
+    assert_eq!(resynthesize_clifford(vec![IROp::H(3), IROp::CNOT(3, 5)]), vec![IROp::H(3), IROp::CNOT(3, 5)]);
+    assert!(PassManager::for_level(OptLevel::O3).pass_names().contains(&"clifford_resynthesis"));
+}
+
+#[test]
+fn decomposition_lowers_every_gate_into_the_basis() {
+    let mut prog = IRProgram::new(5, 1);
+    prog.ops = vec![
+        IROp::H(0), IROp::RX(1, 0.3), IROp::RY(2, -1.1), IROp::U3(3, 0.4, 1.2, -0.7), IROp::SXdg(4), IROp::Y(0),
+        IROp::Toffoli(0, 1, 2), IROp::CCZ(3, 1, 0), IROp::SWAP(2, 4), IROp::CZ(1, 3),
+        IROp::CPhase(0, 4, 0.9), IROp::CU3(2, 1, 0.8, -0.3, 1.7),
+        IROp::MCX(vec![0, 1, 2], 3), IROp::MCX(vec![4, 2, 3, 0], 1), IROp::MCX(vec![1], 0),
+    ];
+    for basis in [Basis::CliffordT, Basis::RzSx] {
+        let out = PassManager::new().with(Decompose::new(basis)).run(prog.clone());
+        assert!(out.ops.iter().all(|op| basis.contains(op)), "{:?}: {:?}", basis, out.ops);
+        assert_equivalent(&prog, &out);
+    }
+}
+
+#[test]
+fn clifford_t_decomposition_turns_eighth_turns_into_phase_gates() {
+    let lower = |op: IROp| {
+        let mut prog = IRProgram::new(1, 0);
+        prog.push(op);
+        PassManager::new().with(Decompose::new(Basis::CliffordT)).run(prog).ops
+    };
+    assert_eq!(lower(IROp::RZ(0, PI / 4.0)), vec![IROp::T(0)]);
+    assert_eq!(lower(IROp::RZ(0, PI / 2.0)), vec![IROp::S(0)]);
+    assert_eq!(lower(IROp::RZ(0, 0.3)), vec![IROp::RZ(0, 0.3)]);
+}
+
+#[test]
+fn decomposed_toffolis_run_on_the_stabilizer_backends() {
+    // The frontend already expands `toffoli`, so build the IR by hand
+    let mut raw = IRProgram::new(3, 1);
+    raw.ops = vec![IROp::X(0), IROp::X(1), IROp::Toffoli(0, 1, 2), IROp::Measure(2, 0)];
+    let out = PassManager::new().with(Decompose::new(Basis::CliffordT)).run(raw.clone());
+    assert_eq!(t_count(&out.ops), 7);
+    assert!(out.ops.iter().all(|op| !matches!(op, IROp::Toffoli(..))));
+    assert_eq!(RuntimeController::run(&out).unwrap(), vec![true]);
+
+    // Measurements and conditions pass through, conditioned ops are lowered one by one
+    let mut cond = IRProgram::new(2, 1);
+    cond.ops = vec![IROp::Measure(0, 0), IROp::Conditional { clbits: vec![0], value: 1, op: Box::new(IROp::SWAP(0, 1)) }];
+    let out = PassManager::new().with(Decompose::new(Basis::RzSx)).run(cond);
+    assert_eq!(out.ops.len(), 4);
+    assert!(out.ops[1..].iter().all(|op| matches!(op, IROp::Conditional { op, .. } if matches!(**op, IROp::CNOT(..)))));
+}
+
+#[test]
+fn t_gates_become_magic_state_gadgets() {
+    let mut prog = IRProgram::new(2, 1);
+    prog.ops = vec![
+        IROp::H(0), IROp::T(0), IROp::CNOT(0, 1), IROp::Tdg(1), IROp::T(1), IROp::Tdg(0), IROp::T(0), IROp::H(0), IROp::Measure(0, 0),
+        IROp::Conditional { clbits: vec![0], value: 1, op: Box::new(IROp::T(1)) },
+    ];
+    let out = PassManager::new().with(TGadgetization).run(prog.clone());
+    assert_eq!((out.num_qubits, out.num_clbits), (3, 2));
+    assert_eq!(out.ops[1..5], t_gadget(0, 2, 1)[..]);
+    assert_eq!(t_count(&out.ops), 0);
+    assert!(matches!(&out.ops[out.len() - 1], IROp::IfElse { then_ops, .. } if *then_ops == t_gadget(1, 2, 1)));
+    assert_eq!(estimate_resources(&out).magic_states, 6);
+    // Programs without T keep their registers
+    assert_eq!(PassManager::new().with(TGadgetization).run(IRProgram::new(1, 0)).num_qubits, 1);
+
+    // H T^4 H = X and H (T†)^2 S H = 1, whatever the gadgets measure
+    let x = vec![IROp::T(0); 4];
+    let identity = vec![IROp::Tdg(0), IROp::Tdg(0), IROp::S(0)];
+    for (body, expected) in [(x, true), (identity, false)] {
+        let mut prog = IRProgram::new(1, 1);
+        prog.ops = [vec![IROp::H(0)], body, vec![IROp::H(0), IROp::Measure(0, 0)]].concat();
+        let out = PassManager::new().with(TGadgetization).run(prog);
+        for _ in 0..20 {
+            let mut controller = RuntimeController::new(out.num_qubits, out.num_clbits);
+            controller.execute(&out.ops).unwrap();
+            assert_eq!(controller.clbits()[0], expected);
+            // The magic states were injected without leaving the tableau for good
+            assert_eq!(*controller.backend_type(), BackendType::Tableau);
+            let stats = controller.stats();
+            assert_eq!((stats.promotions, stats.demotions, stats.statevector_ops), (0, 0, 0), "{:?}", stats);
+            assert_eq!(stats.magic_states, out.ops.iter().filter(|op| matches!(op, IROp::MagicState(_))).count());
+        }
+    }
+}
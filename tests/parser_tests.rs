@@ -0,0 +1,169 @@
+use quantum_sim::compiler::ast::{BinOp, Expr, Stmt};
+use quantum_sim::compiler::diagnostics::{Diagnostic, DiagnosticKind};
+use quantum_sim::compiler::lexer::{tokenize, Span, TokenKind};
+use quantum_sim::compiler::parser::parse;
+use quantum_sim::compiler::sema::check;
+
+#[test]
+fn lexer_tracks_lines_and_columns() {
+    let tokens = tokenize("qubit q0;\n  h q0; // comment\n").unwrap();
+    let kinds: Vec<TokenKind> = tokens.iter().map(|t| t.kind.clone()).collect();
+    assert_eq!(kinds, vec![
+        TokenKind::Ident("qubit".into()), TokenKind::Ident("q0".into()), TokenKind::Semicolon,
+        TokenKind::Ident("h".into()), TokenKind::Ident("q0".into()), TokenKind::Semicolon,
+        TokenKind::Eof,
+    ]);
+    assert_eq!((tokens[3].span.line, tokens[3].span.col), (2, 3));
+}
+
+#[test]
+fn parses_declarations_gates_and_measurements() {
+    let prog = parse("
+        qubit q0, q1, r[3];
+        h q0;
+        cnot q0, r[2];
+        rz(-0.5) q1;
+        measure q0, q1;
+    ").unwrap();
+    assert_eq!(prog.statements.len(), 5);
+
+    match &prog.statements[0] {
+        Stmt::QubitDecl(decls) => {
+            let names: Vec<_> = decls.iter().map(|d| (d.name.as_str(), d.size)).collect();
+            assert_eq!(names, vec![("q0", None), ("q1", None), ("r", Some(3))]);
+        }
+        other => panic!("expected declaration, got {:?}", other),
+    }
+    match &prog.statements[2] {
+        Stmt::Gate(call) => {
+            assert_eq!(call.name, "cnot");
+            assert_eq!(call.args[1].name, "r");
+            assert!(matches!(call.args[1].index, Some(Expr::Number(v, _)) if v == 2.0));
+        }
+        other => panic!("expected gate, got {:?}", other),
+    }
+    match &prog.statements[3] {
+        Stmt::Gate(call) => assert!(matches!(&call.params[0], Expr::Neg(inner, _) if matches!(**inner, Expr::Number(v, _) if v == 0.5))),
+        other => panic!("expected gate, got {:?}", other),
+    }
+    assert!(matches!(&prog.statements[4], Stmt::Measure(args, _) if args.len() == 2));
+}
+
+#[test]
+fn missing_semicolon_is_reported_at_next_token() {
+    let err = parse("qubit q0\nh q0;").unwrap_err();
+    assert_eq!((err.span.line, err.span.col), (2, 1));
+    assert!(err.message.contains("`;`"));
+}
+
+fn check_errors(src: &str) -> Vec<Diagnostic> {
+    check(&parse(src).unwrap()).unwrap_err()
+}
+
+#[test]
+fn semantic_errors_are_collected_with_kinds() {
+    let errors = check_errors("
+        qubit q0, q1;
+        qubit q1;
+        foo q0;
+        cnot q0;
+        h q7;
+        rz q0;
+    ");
+    let kinds: Vec<DiagnosticKind> = errors.iter().map(|e| e.kind).collect();
+    assert_eq!(kinds, vec![
+        DiagnosticKind::DuplicateDeclaration,
+        DiagnosticKind::UnknownGate,
+        DiagnosticKind::WrongArity,
+        DiagnosticKind::UndeclaredQubit,
+        DiagnosticKind::WrongArity,
+    ]);
+    assert_eq!(errors[0].note.as_ref().unwrap().1.line, 2);
+}
+
+#[test]
+fn register_indices_are_bounds_checked() {
+    let errors = check_errors("qubit r[2]; x r[2]; cnot r[0], r[0];");
+    assert_eq!(errors[0].kind, DiagnosticKind::IndexOutOfRange);
+    assert_eq!(errors[1].kind, DiagnosticKind::DuplicateOperand);
+}
+
+#[test]
+fn diagnostic_renders_caret_snippet() {
+    let src = "qubit q0;\ncnot q0;\n";
+    let errors = check_errors(src);
+    let rendered = errors[0].render("bell.qc", src);
+    assert_eq!(rendered, "\
+error: gate `cnot` acts on 2 qubits, got 1
+ --> bell.qc:2:1
+  |
+2 | cnot q0;
+  | ^^^^^^^
+");
+}
+
+#[test]
+fn diagnostic_without_a_span_still_renders() {
+    let d = Diagnostic::new(DiagnosticKind::Unsupported, "no location", Span::default());
+    assert!(d.render("x.qc", "h q;\n").starts_with("error: no location"));
+}
+
+#[test]
+fn parses_gate_definitions_and_arithmetic() {
+    let prog = parse("gate g(theta) a, b { rz(-theta/2 + pi) a; cnot a, b; }").unwrap();
+    let Stmt::GateDef(def) = &prog.statements[0] else { panic!("expected a gate definition") };
+    assert_eq!(def.name, "g");
+    assert_eq!(def.params.len(), 1);
+    assert_eq!(def.qubits.len(), 2);
+    assert_eq!(def.body.len(), 2);
+    assert!(matches!(&def.body[0].params[0], Expr::Binary(BinOp::Add, lhs, _, _) if matches!(**lhs, Expr::Binary(BinOp::Div, ..))));
+
+    let err = parse("gate g a { measure a; }").unwrap_err();
+    assert_eq!(err.kind, DiagnosticKind::Syntax);
+}
+
+#[test]
+fn gate_definitions_are_checked() {
+    let kinds = |src: &str| check_errors(src).iter().map(|d| d.kind).collect::<Vec<_>>();
+    assert_eq!(kinds("gate h a { x a; }"), vec![DiagnosticKind::DuplicateDeclaration]);
+    assert_eq!(kinds("gate g a { x a; } gate g b { z b; }"), vec![DiagnosticKind::DuplicateDeclaration]);
+    assert_eq!(kinds("qubit q; gate g a { x q; }"), vec![DiagnosticKind::UndeclaredQubit]);
+    assert_eq!(kinds("gate g(t) a { rz(u) a; }"), vec![DiagnosticKind::UnknownIdentifier]);
+    assert_eq!(kinds("gate g a, b { cnot a, a; }"), vec![DiagnosticKind::DuplicateOperand]);
+    // A gate cannot call itself or anything defined after it
+    assert_eq!(kinds("gate g a { g a; }"), vec![DiagnosticKind::UnknownGate]);
+    assert_eq!(kinds("gate g(t) a { rz(t) a; } qubit q; g q;"), vec![DiagnosticKind::WrongArity]);
+}
+
+#[test]
+fn classical_bits_are_checked() {
+    let kinds = |src: &str| check_errors(src).iter().map(|d| d.kind).collect::<Vec<_>>();
+    assert_eq!(kinds("qubit q; bit q;"), vec![DiagnosticKind::DuplicateDeclaration]);
+    assert_eq!(kinds("qubit q; measure q -> c;"), vec![DiagnosticKind::UndeclaredBit]);
+    assert_eq!(kinds("qubit q[2]; bit c; measure q -> c;"), vec![DiagnosticKind::WrongArity]);
+    assert_eq!(kinds("qubit q; bit c[2]; if (c) x q;"), vec![DiagnosticKind::WrongArity]);
+    assert_eq!(kinds("qubit q; bit c[2]; if (c == 4) x q;"), vec![DiagnosticKind::IndexOutOfRange]);
+    assert_eq!(kinds("qubit q; bit c[2]; if (c != 1) x q;"), vec![DiagnosticKind::Unsupported]);
+    assert_eq!(kinds("qubit q; bit c; if (c) { qubit r; }"), vec![DiagnosticKind::Unsupported]);
+}
+
+#[test]
+fn loop_ranges_and_index_expressions() {
+    let kinds: Vec<TokenKind> = tokenize("0..n 1.5").unwrap().into_iter().map(|t| t.kind).collect();
+    assert_eq!(kinds, vec![
+        TokenKind::Int(0), TokenKind::DotDot, TokenKind::Ident("n".into()), TokenKind::Float(1.5), TokenKind::Eof,
+    ]);
+
+    let errors = check_errors("qubit q[3]; for i in 0..3 { x q[i]; cnot q[i], q[i+1]; }");
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].kind, DiagnosticKind::IndexOutOfRange);
+    assert!(errors[0].message.contains("index 3"));
+
+    let kinds = |src: &str| check_errors(src).iter().map(|d| d.kind).collect::<Vec<_>>();
+    assert_eq!(kinds("qubit q[2]; x q[1/2];"), vec![DiagnosticKind::IndexOutOfRange]);
+    assert_eq!(kinds("qubit q[2]; for i in 0..2 { x q[i]; } x q[i];"), vec![DiagnosticKind::UnknownIdentifier]);
+    assert_eq!(kinds("qubit q; for i in 0..1e9 { x q; }"), vec![DiagnosticKind::Unsupported]);
+    assert_eq!(kinds("qubit q; for i in -1e300..1e300 { h q; }"), vec![DiagnosticKind::Unsupported]);
+    assert_eq!(kinds("qubit q; for i in 1e19..1e19+4096 { h q; }"), vec![DiagnosticKind::Unsupported]);
+    assert_eq!(kinds("qubit q; for i in 0..2.5 { h q; }"), vec![DiagnosticKind::InvalidValue]);
+}
@@ -0,0 +1,308 @@
+use quantum_sim::compiler::codegen::compile;
+use quantum_sim::compiler::diagnostics::DiagnosticKind;
+use quantum_sim::compiler::ir::{IROp, IRProgram, Register};
+use quantum_sim::compiler::qasm::export::{export_qasm2, export_qasm3, format_angle};
+use quantum_sim::compiler::qasm::import::{import_qasm, import_qasm2, import_qasm3};
+use quantum_sim::runtime::controller::RuntimeController;
+use std::f64::consts::PI;
+
+const BELL: &str = r#"
+OPENQASM 2.0;
+include "qelib1.inc";
+qreg q[2];
+creg c[2];
+h q[0];
+cx q[0], q[1];
+measure q -> c;
+"#;
+
+#[test]
+fn imports_registers_and_measurements() {
+    let prog = import_qasm2(BELL).unwrap();
+    assert_eq!((prog.num_qubits, prog.num_clbits), (2, 2));
+    assert_eq!(prog.cregs, vec![Register { name: "c".into(), offset: 0, size: 2 }]);
+    assert_eq!(prog.ops, vec![IROp::H(0), IROp::CNOT(0, 1), IROp::Measure(0, 0), IROp::Measure(1, 1)]);
+}
+
+#[test]
+fn matches_native_language_lowering() {
+    let native = compile("qubit q[2]; h q[0]; cnot q[0], q[1]; measure q;").unwrap();
+    let qasm = import_qasm2(BELL).unwrap();
+    assert_eq!(native.ops, qasm.ops);
+    assert_eq!(native.qregs, qasm.qregs);
+}
+
+#[test]
+fn expands_parameterized_gate_definitions() {
+    let prog = import_qasm2(r#"
+        OPENQASM 2.0;
+        include "qelib1.inc";
+        gate rot(theta) a { rz(theta/2) a; }
+        gate pair(theta, phi) a, b { rot(theta) a; rot(-phi*2) b; cx a, b; }
+        qreg q[2];
+        pair(pi/4, 2^-1) q[1], q[0];
+        u2(0, pi) q[0];
+    "#).unwrap();
+    assert_eq!(prog.ops, vec![
+        IROp::RZ(1, PI / 8.0),
+        IROp::RZ(0, -0.5),
+        IROp::CNOT(1, 0),
+        IROp::U3(0, PI / 2.0, 0.0, PI),
+    ]);
+}
+
+#[test]
+fn broadcasts_register_operands() {
+    let prog = import_qasm2(r#"
+        include "qelib1.inc";
+        qreg a[3];
+        qreg b[3];
+        h a;
+        cx a, b;
+    "#).unwrap();
+    assert_eq!(prog.ops.len(), 6);
+    assert_eq!(prog.ops[3], IROp::CNOT(0, 3));
+    assert_eq!(prog.ops[5], IROp::CNOT(2, 5));
+}
+
+#[test]
+fn conditional_ops_use_register_value() {
+    let prog = import_qasm2(r#"
+        OPENQASM 2.0;
+        include "qelib1.inc";
+        qreg q[2];
+        creg c[1];
+        creg d[1];
+        x q[0];
+        measure q[0] -> c[0];
+        if(c==1) x q[1];
+        measure q[1] -> d[0];
+    "#).unwrap();
+    assert_eq!(prog.ops[2], IROp::Conditional { clbits: vec![0], value: 1, op: Box::new(IROp::X(1)) });
+    assert_eq!(RuntimeController::run(&prog).unwrap(), vec![true, true]);
+}
+
+#[test]
+fn qelib1_gates_require_the_include() {
+    let err = import_qasm2("OPENQASM 2.0; qreg q[1]; h q[0];").unwrap_err();
+    assert_eq!(err.kind, DiagnosticKind::UnknownGate);
+    // U and CX are always available
+    let prog = import_qasm2("OPENQASM 2.0; qreg q[2]; U(pi,0,pi) q[0]; CX q[0],q[1];").unwrap();
+    assert_eq!(prog.ops[1], IROp::CNOT(0, 1));
+}
+
+#[test]
+fn reports_register_errors_with_spans() {
+    let err = import_qasm2("include \"qelib1.inc\";\nqreg q[2];\nh q[2];").unwrap_err();
+    assert_eq!(err.kind, DiagnosticKind::IndexOutOfRange);
+    assert_eq!((err.span.line, err.span.col), (3, 3));
+}
+
+const DYNAMIC: &str = r#"
+OPENQASM 2.0;
+include "qelib1.inc";
+qreg data[2];
+qreg anc[1];
+creg syn[1];
+creg out[2];
+h data[0];
+rz(3*pi/4) data[1];
+rx(0.123456789) anc[0];
+u3(pi/2, -pi/8, 1e-3) data[0];
+cu1(-pi/3) data[0], anc[0];
+cx data[0], anc[0];
+measure anc[0] -> syn[0];
+if(syn==1) x data[1];
+reset anc[0];
+barrier data, anc;
+measure data -> out;
+"#;
+
+#[test]
+fn qasm2_export_round_trips() {
+    let prog = import_qasm2(DYNAMIC).unwrap();
+    let text = export_qasm2(&prog).unwrap();
+    assert!(text.contains("qreg anc[1];"));
+    assert!(text.contains("creg syn[1];"));
+    assert!(text.contains("rz(3*pi/4) data[1];"));
+    assert!(text.contains("if(syn==1) x data[1];"));
+    assert_eq!(import_qasm2(&text).unwrap(), prog);
+}
+
+#[test]
+fn qasm3_export_uses_new_syntax() {
+    let prog = import_qasm2(DYNAMIC).unwrap();
+    let text = export_qasm3(&prog).unwrap();
+    assert!(text.starts_with("OPENQASM 3.0;"));
+    assert!(text.contains("qubit[2] data;"));
+    assert!(text.contains("syn[0] = measure anc[0];"));
+    assert!(text.contains("if (syn==1) { x data[1]; }"));
+    assert!(text.contains("cp(-pi/3) data[0], anc[0];"));
+}
+
+#[test]
+fn angles_print_exactly() {
+    for angle in [PI, -PI / 2.0, 5.0 * PI / 8.0, 0.1, -1.0e-7, 2.5] {
+        let text = format_angle(angle);
+        let prog = import_qasm2(&format!("include \"qelib1.inc\"; qreg q[1]; rz({}) q[0];", text)).unwrap();
+        assert_eq!(prog.ops[0], IROp::RZ(0, angle), "{}", text);
+    }
+    assert_eq!(format_angle(PI / 4.0), "pi/4");
+}
+
+#[test]
+fn qasm2_rejects_ops_it_cannot_express() {
+    let mut prog = IRProgram::new(4, 0);
+    prog.push(IROp::MCX(vec![0, 1, 2], 3));
+    let err = export_qasm2(&prog).unwrap_err();
+    assert_eq!(err.op, 0);
+    assert!(export_qasm3(&prog).unwrap().contains("ctrl(3) @ x q[0], q[1], q[2], q[3];"));
+}
+
+#[test]
+fn qasm3_for_loops_unroll() {
+    let prog = import_qasm3(r#"
+        OPENQASM 3.0;
+        include "stdgates.inc";
+        qubit[4] q;
+        h q[0];
+        for uint i in [0:2] {
+            cx q[i], q[i+1];
+        }
+        for int[32] k in {1, 3} rz(k*pi/4) q[k];
+        for i in [3:0] x q[0];
+    "#).unwrap();
+    assert_eq!(prog.ops, vec![
+        IROp::H(0),
+        IROp::CNOT(0, 1), IROp::CNOT(1, 2), IROp::CNOT(2, 3),
+        IROp::RZ(1, PI / 4.0), IROp::RZ(3, 3.0 * PI / 4.0),
+    ]);
+
+    // Values are start + i*step, so a fractional step doesn't drift
+    let prog = import_qasm3("include \"stdgates.inc\"; qubit q; for a in [0:0.1:1] rz(a) q;").unwrap();
+    let angles: Vec<f64> = prog.ops.iter().map(|op| match op { IROp::RZ(_, a) => *a, _ => panic!() }).collect();
+    assert_eq!(angles.len(), 11);
+    assert_eq!(angles[3], 3.0 * 0.1);
+    let err = import_qasm3("include \"stdgates.inc\"; qubit q; for i in [0:10000000] x q;").unwrap_err();
+    assert_eq!(err.kind, DiagnosticKind::Unsupported);
+}
+
+#[test]
+fn qasm3_if_else_and_while_feed_forward() {
+    let prog = import_qasm(r#"
+        OPENQASM 3.0;
+        include "stdgates.inc";
+        qubit[2] q;
+        bit c;
+        bit[1] d;
+        x q[0];
+        c = measure q[0];
+        if (c) {
+            x q[1];
+        } else {
+            h q[1];
+        }
+        d[0] = measure q[1];
+    "#).unwrap();
+    assert!(matches!(prog.ops[2], IROp::IfElse { value: 1, .. }));
+    assert_eq!(RuntimeController::run(&prog).unwrap(), vec![true, true]);
+
+    // Repeat until success: flip a coin until it lands on 1
+    let rus = import_qasm3(r#"
+        OPENQASM 3.0;
+        include "stdgates.inc";
+        qubit q;
+        bit c;
+        while (!c) {
+            reset q;
+            h q;
+            c = measure q;
+        }
+    "#).unwrap();
+    assert!(matches!(rus.ops[0], IROp::While { value: 0, .. }));
+    for _ in 0..10 {
+        assert_eq!(RuntimeController::run(&rus).unwrap(), vec![true]);
+    }
+
+    let err = import_qasm3("OPENQASM 3.0; include \"stdgates.inc\"; qubit q; bit c; while (!c) { h q; }").unwrap_err();
+    assert_eq!(err.kind, DiagnosticKind::Unsupported);
+}
+
+#[test]
+fn qasm3_gate_modifiers() {
+    let prog = import_qasm3(r#"
+        OPENQASM 3.0;
+        include "stdgates.inc";
+        qubit[4] q;
+        inv @ s q[0];
+        ctrl @ x q[0], q[1];
+        ctrl(2) @ z q[0], q[1], q[2];
+        ctrl @ cx q[0], q[1], q[2];
+        inv @ ctrl @ x q[3], q[0];
+    "#).unwrap();
+    assert_eq!(prog.ops, vec![
+        IROp::Sdg(0),
+        IROp::CNOT(0, 1),
+        IROp::CCZ(0, 1, 2),
+        IROp::MCX(vec![0, 1], 2),
+        IROp::CNOT(3, 0),
+    ]);
+    let err = import_qasm3("qubit[2] q; pow(2) @ x q[0];").unwrap_err();
+    assert_eq!(err.kind, DiagnosticKind::Unsupported);
+
+    // Register operands broadcast like unmodified gates
+    let prog = import_qasm3(r#"
+        OPENQASM 3.0;
+        include "stdgates.inc";
+        qubit[3] q;
+        qubit[2] a;
+        qubit[2] b;
+        inv @ t q;
+        ctrl @ x a, b;
+        ctrl @ z q[0], a;
+    "#).unwrap();
+    assert_eq!(prog.ops, vec![
+        IROp::Tdg(0), IROp::Tdg(1), IROp::Tdg(2),
+        IROp::CNOT(3, 5), IROp::CNOT(4, 6),
+        IROp::CZ(0, 3), IROp::CZ(0, 4),
+    ]);
+    let err = import_qasm3("include \"stdgates.inc\"; qubit[2] a; qubit[3] b; ctrl @ x a, b;").unwrap_err();
+    assert_eq!(err.kind, DiagnosticKind::WrongArity);
+    let err = import_qasm3("include \"stdgates.inc\"; qubit[0] z; inv @ h z;").unwrap_err();
+    assert_eq!(err.kind, DiagnosticKind::WrongArity);
+}
+
+#[test]
+fn qasm3_control_flow_round_trips() {
+    let mut prog = IRProgram::new(2, 2);
+    prog.qregs = vec![Register { name: "q".to_string(), offset: 0, size: 2 }];
+    prog.cregs = vec![Register { name: "c".to_string(), offset: 0, size: 2 }];
+    prog.push(IROp::H(0));
+    prog.push(IROp::Measure(0, 0));
+    prog.push(IROp::IfElse {
+        clbits: vec![0],
+        value: 0,
+        then_ops: vec![IROp::X(1), IROp::Measure(1, 1)],
+        else_ops: vec![IROp::SXdg(1)],
+    });
+    prog.push(IROp::While { clbits: vec![0, 1], value: 3, body: vec![IROp::Reset(0), IROp::Measure(0, 0)] });
+    prog.push(IROp::MCX(vec![0], 1));
+    let text = export_qasm3(&prog).unwrap();
+    assert!(text.contains("if (!c[0]) {"));
+    assert!(text.contains("while (c==3) {"));
+    assert!(export_qasm2(&prog).is_err());
+    let mut back = import_qasm3(&text).unwrap();
+    assert_eq!(back.ops.pop(), Some(IROp::CNOT(0, 1)));
+    prog.ops.pop();
+    assert_eq!(back, prog);
+}
+
+#[test]
+fn qasm2_rejects_conditional_magic_state() {
+    let mut prog = IRProgram::new(1, 1);
+    prog.push(IROp::Measure(0, 0));
+    prog.push(IROp::Conditional { clbits: vec![0], value: 1, op: Box::new(IROp::MagicState(0)) });
+    let err = export_qasm2(&prog).unwrap_err();
+    assert_eq!(err.op, 1);
+    assert!(export_qasm3(&prog).unwrap().contains("if (c==1) { reset q[0]; h q[0]; t q[0]; }"));
+}
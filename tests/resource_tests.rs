@@ -0,0 +1,67 @@
+use quantum_sim::compiler::ir::{IROp, IRProgram};
+use quantum_sim::compiler::optimizer::push_back::Window;
+use quantum_sim::compiler::resources::ResourceReport;
+use quantum_sim::runtime::controller::BackendType;
+
+#[test]
+fn gate_figures_and_windows() {
+    let mut prog = IRProgram::new(3, 3);
+    prog.ops = vec![
+        IROp::H(0),
+        IROp::T(0),
+        IROp::CNOT(0, 1),
+        IROp::T(1),
+        IROp::Tdg(0),
+        IROp::CNOT(1, 2),
+        IROp::Measure(0, 0),
+        IROp::Measure(1, 1),
+        IROp::Measure(2, 2),
+        IROp::H(2),
+        IROp::IfElse { clbits: vec![0], value: 1, then_ops: vec![IROp::RZ(2, 0.3)], else_ops: vec![IROp::S(2)] },
+    ];
+    let report = ResourceReport::of(&prog);
+    assert_eq!(report.gates, 9);
+    assert_eq!(report.gate_counts["cx"], 2);
+    assert_eq!(report.gate_counts["t"], 2);
+    assert_eq!(report.two_qubit, 2);
+    assert_eq!(report.measurements, 3);
+    assert_eq!(report.depth, 8);
+    assert_eq!(report.t_count, 3);
+    // T on 0, then T on 1 next to T† on 0
+    assert_eq!(report.t_depth, 2);
+    // The RZ needs a magic state too
+    assert_eq!(report.magic_states, 4);
+    assert_eq!(report.windows, vec![
+        Window { start: 1, end: Some(9), non_clifford: 3, qubits: vec![0, 1, 2] },
+        Window { start: 10, end: None, non_clifford: 1, qubits: vec![2] },
+    ]);
+    let text = report.to_string();
+    assert!(text.contains("T-count: 3, T-depth: 2"));
+    assert!(text.contains("ops 10..: 1 non-Clifford on qubits [2]"));
+}
+
+#[test]
+fn backend_costs_pick_the_cheapest_simulator() {
+    let layer = |n: usize, t: usize| {
+        let mut prog = IRProgram::new(n, 0);
+        prog.ops = (0..n).map(IROp::H).collect();
+        prog.ops.extend((1..n).map(|q| IROp::CNOT(q - 1, q)));
+        prog.ops.extend((0..t).map(|i| IROp::T(i % n)));
+        prog
+    };
+
+    let clifford = ResourceReport::of(&layer(40, 0));
+    assert_eq!(clifford.tableau_bits(), 80 * 81);
+    assert_eq!(clifford.statevector_bytes(), 16.0 * 2f64.powi(40));
+    assert_eq!(clifford.rank_terms(), 1.0);
+    assert_eq!(clifford.recommended_backend(), BackendType::Tableau);
+    assert!(clifford.to_string().contains("statevector: 2^40 amplitudes, 16.0 TiB"));
+
+    // 2^(0.23 * 20) terms of 40 qubits beat 2^40 amplitudes
+    let few_t = ResourceReport::of(&layer(40, 20));
+    assert!((few_t.rank_terms() - 2f64.powf(4.6)).abs() < 1e-9);
+    assert_eq!(few_t.recommended_backend(), BackendType::RankDecomposition);
+
+    let many_t = ResourceReport::of(&layer(10, 200));
+    assert_eq!(many_t.recommended_backend(), BackendType::Statevector);
+}
@@ -0,0 +1,183 @@
+use quantum_sim::compiler::ir::{IROp, IRProgram};
+use quantum_sim::compiler::routing::{route, CouplingMap, RoutingError};
+use quantum_sim::math::complex::Complex;
+use quantum_sim::runtime::statevector_backend::StatevectorSimulator;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+fn random_circuit(rng: &mut StdRng, n: usize, len: usize) -> IRProgram {
+    let mut prog = IRProgram::new(n, 0);
+    for _ in 0..len {
+        let a = rng.gen_range(0..n);
+        let mut b = rng.gen_range(0..n - 1);
+        if b >= a {
+            b += 1;
+        }
+        prog.push(match rng.gen_range(0..5) {
+            0 => IROp::H(a),
+            1 => IROp::T(a),
+            2 => IROp::CZ(a, b),
+            3 => IROp::CPhase(a, b, rng.gen_range(-3.0..3.0)),
+            _ => IROp::CNOT(a, b),
+        });
+    }
+    prog
+}
+
+// Every gate in `ops` (and the blocks nested in it) acts on neighbours
+fn all_adjacent(ops: &[IROp], map: &CouplingMap) -> bool {
+    ops.iter().all(|op| match op {
+        IROp::IfElse { .. } | IROp::While { .. } => {
+            let nested: Vec<IROp> = op.nested_ops().into_iter().cloned().collect();
+            all_adjacent(&nested, map)
+        }
+        IROp::Barrier(_) => true,
+        op => match op.qubits()[..] {
+            [a, b] => map.are_adjacent(a, b),
+            _ => true,
+        },
+    })
+}
+
+fn statevector(prog: &IRProgram, n: usize) -> Vec<Complex> {
+    let mut sim = StatevectorSimulator::new(n);
+    for op in &prog.ops {
+        sim.apply_ir(op);
+    }
+    sim.state
+}
+
+// Runs programs made of X, CNOT, SWAP, measurements and control flow on
+// basis states
+fn run_classical(ops: &[IROp], qubits: &mut [bool], clbits: &mut [bool]) {
+    let value = |clbits: &[bool], bits: &[usize]| bits.iter().enumerate().map(|(i, &c)| (clbits[c] as u64) << i).sum::<u64>();
+    for op in ops {
+        match op {
+            IROp::X(q) => qubits[*q] ^= true,
+            IROp::CNOT(c, t) => qubits[*t] ^= qubits[*c],
+            IROp::SWAP(a, b) => qubits.swap(*a, *b),
+            IROp::Measure(q, c) => clbits[*c] = qubits[*q],
+            IROp::Conditional { clbits: bits, value: v, op } => {
+                if value(clbits, bits) == *v {
+                    run_classical(std::slice::from_ref(op), qubits, clbits);
+                }
+            }
+            IROp::IfElse { clbits: bits, value: v, then_ops, else_ops } => {
+                let block = if value(clbits, bits) == *v { then_ops } else { else_ops };
+                run_classical(block, qubits, clbits);
+            }
+            IROp::While { clbits: bits, value: v, body } => {
+                while value(clbits, bits) == *v {
+                    run_classical(body, qubits, clbits);
+                }
+            }
+            _ => panic!("not classical: {}", op),
+        }
+    }
+}
+
+#[test]
+fn coupling_maps_have_the_expected_shape() {
+    let line = CouplingMap::line(5);
+    assert_eq!(line.edges().len(), 4);
+    assert_eq!(line.distance(0, 4), 4);
+    assert_eq!(line.shortest_path(3, 0), Some(vec![3, 2, 1, 0]));
+
+    let grid = CouplingMap::grid(3, 4);
+    assert_eq!(grid.num_qubits(), 12);
+    assert_eq!(grid.edges().len(), 3 * 3 + 2 * 4);
+    assert_eq!(grid.distance(0, 11), 5);
+
+    // 2 x 2 hexagons: 3 chains of 11 and 2 x 3 bridges
+    let hex = CouplingMap::heavy_hex(2, 2);
+    assert_eq!(hex.num_qubits(), 3 * 11 + 6);
+    assert!(hex.is_connected());
+    assert!((0..hex.num_qubits()).all(|q| hex.neighbours(q).len() <= 3));
+    // Each hexagon is a 12-cycle
+    assert_eq!(hex.edges().len(), hex.num_qubits() - 1 + 4);
+
+    let custom = CouplingMap::from_edges(4, &[(1, 0), (0, 1), (2, 3)]).unwrap();
+    assert_eq!(custom.edges(), &[(0, 1), (2, 3)]);
+    assert!(!custom.is_connected());
+    assert_eq!(custom.shortest_path(0, 3), None);
+    assert_eq!(CouplingMap::from_edges(2, &[(0, 2)]), Err(RoutingError::InvalidEdge { edge: (0, 2), num_qubits: 2 }));
+    assert_eq!(CouplingMap::from_edges(2, &[(1, 1)]), Err(RoutingError::InvalidEdge { edge: (1, 1), num_qubits: 2 }));
+}
+
+#[test]
+fn routed_circuits_only_use_coupled_pairs_and_keep_the_state() {
+    let mut rng = StdRng::seed_from_u64(20);
+    let maps = [CouplingMap::line(6), CouplingMap::grid(2, 3), CouplingMap::heavy_hex(1, 1)];
+    for map in &maps {
+        for _ in 0..5 {
+            let n = 5;
+            let prog = random_circuit(&mut rng, n, 40);
+            let routed = route(&prog, map).unwrap();
+            assert!(all_adjacent(&routed.program.ops, map));
+            let inserted = routed.program.ops.iter().filter(|op| matches!(op, IROp::SWAP(..))).count();
+            assert_eq!(routed.swaps, inserted);
+            assert_eq!(routed.program.metadata["swaps"], inserted.to_string());
+
+            // The routed state is the original one with logical qubit i on
+            // physical qubit final_layout[i]
+            let physical = map.num_qubits();
+            let before = statevector(&prog, n);
+            let after = statevector(&routed.program, physical);
+            for (b, amp) in before.iter().enumerate() {
+                let index: usize = (0..n).filter(|i| b >> i & 1 == 1).map(|i| 1 << routed.final_layout[i]).sum();
+                let other = after[index];
+                assert!((amp.re - other.re).abs() < 1e-9 && (amp.im - other.im).abs() < 1e-9);
+            }
+        }
+    }
+}
+
+#[test]
+fn circuits_that_fit_need_no_swaps() {
+    let mut prog = IRProgram::new(4, 0);
+    prog.ops = vec![IROp::CNOT(0, 1), IROp::CNOT(1, 2), IROp::CNOT(2, 3), IROp::CZ(3, 2)];
+    let routed = route(&prog, &CouplingMap::line(4)).unwrap();
+    assert_eq!(routed.swaps, 0);
+    assert_eq!(routed.initial_layout, routed.final_layout);
+
+    // A star needs at least one swap on a line
+    prog.ops = vec![IROp::CNOT(0, 1), IROp::CNOT(0, 2), IROp::CNOT(0, 3)];
+    let routed = route(&prog, &CouplingMap::line(4)).unwrap();
+    assert!(routed.swaps >= 1);
+    assert!(all_adjacent(&routed.program.ops, &CouplingMap::line(4)));
+}
+
+#[test]
+fn control_flow_blocks_restore_the_layout() {
+    // 0 and 3 are far apart on the line in both branches and the loop
+    let mut prog = IRProgram::new(4, 3);
+    prog.ops = vec![
+        IROp::X(0),
+        IROp::CNOT(0, 3),
+        IROp::Measure(3, 0),
+        IROp::IfElse { clbits: vec![0], value: 1, then_ops: vec![IROp::CNOT(3, 1), IROp::CNOT(0, 2)], else_ops: vec![IROp::X(1)] },
+        IROp::While { clbits: vec![2], value: 0, body: vec![IROp::CNOT(1, 3), IROp::Measure(3, 2)] },
+        IROp::Conditional { clbits: vec![0], value: 1, op: Box::new(IROp::CNOT(2, 0)) },
+        IROp::Measure(0, 1),
+    ];
+    let map = CouplingMap::line(5);
+    let routed = route(&prog, &map).unwrap();
+    assert!(all_adjacent(&routed.program.ops, &map));
+    let run = |prog: &IRProgram| {
+        let mut clbits = vec![false; prog.num_clbits];
+        run_classical(&prog.ops, &mut vec![false; prog.num_qubits], &mut clbits);
+        clbits
+    };
+    assert_eq!(run(&prog), vec![true, false, true]);
+    assert_eq!(run(&routed.program), run(&prog));
+}
+
+#[test]
+fn unroutable_programs_are_rejected() {
+    let mut prog = IRProgram::new(3, 0);
+    prog.ops = vec![IROp::H(0), IROp::Toffoli(0, 1, 2)];
+    assert_eq!(route(&prog, &CouplingMap::line(3)), Err(RoutingError::MultiQubitGate { op: 1 }));
+    assert_eq!(route(&prog, &CouplingMap::line(2)), Err(RoutingError::TooManyQubits { needed: 3, available: 2 }));
+    prog.ops = vec![IROp::CNOT(0, 2)];
+    assert_eq!(route(&prog, &CouplingMap::from_edges(3, &[(0, 1)]).unwrap()), Err(RoutingError::Disconnected));
+}
@@ -0,0 +1,134 @@
+use quantum_sim::compiler::equivalence::{check_equivalence, EquivalenceChecker};
+use quantum_sim::compiler::ir::{IROp, IRProgram};
+use quantum_sim::compiler::optimizer::analysis::depth;
+use quantum_sim::compiler::optimizer::pass_manager::PassManager;
+use quantum_sim::compiler::scheduling::{schedule, Durations, Idle, Reschedule, Strategy};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+fn random_circuit(rng: &mut StdRng, n: usize, len: usize) -> IRProgram {
+    let mut prog = IRProgram::new(n, 2);
+    for _ in 0..len {
+        let a = rng.gen_range(0..n);
+        let b = (a + rng.gen_range(1..n)) % n;
+        prog.push(match rng.gen_range(0..8) {
+            0 => IROp::H(a),
+            1 => IROp::T(a),
+            2 => IROp::RZ(a, rng.gen_range(-3.0..3.0)),
+            3 => IROp::CZ(a, b),
+            4 => IROp::Measure(a, rng.gen_range(0..2)),
+            5 => IROp::Conditional { clbits: vec![rng.gen_range(0..2)], value: 1, op: Box::new(IROp::X(a)) },
+            6 => IROp::Barrier(vec![a, b]),
+            _ => IROp::CNOT(a, b),
+        });
+    }
+    prog
+}
+
+// The ops on each qubit and clbit, in order
+fn wires(prog: &IRProgram) -> Vec<Vec<IROp>> {
+    let mut wires = vec![Vec::new(); prog.num_qubits + prog.num_clbits];
+    for op in &prog.ops {
+        let mut touched: Vec<usize> = op.qubits().into_iter().chain(op.clbits().into_iter().map(|c| prog.num_qubits + c)).collect();
+        touched.sort_unstable();
+        touched.dedup();
+        for w in touched {
+            wires[w].push(op.clone());
+        }
+    }
+    wires
+}
+
+#[test]
+fn moments_hold_ops_on_disjoint_qubits() {
+    let mut prog = IRProgram::new(3, 0);
+    prog.ops = vec![IROp::H(0), IROp::X(2), IROp::CNOT(0, 1), IROp::H(0), IROp::H(1), IROp::CNOT(0, 1)];
+    let asap = schedule(&prog, Strategy::Asap, &Durations::default());
+    assert_eq!(asap.makespan, 4);
+    assert_eq!(asap.moments.len(), 4);
+    assert_eq!(asap.moments[0].ops, vec![IROp::H(0), IROp::X(2)]);
+    let alap = schedule(&prog, Strategy::Alap, &Durations::default());
+    assert_eq!(alap.makespan, 4);
+    assert_eq!(alap.moments[0].ops, vec![IROp::H(0)]);
+    assert_eq!(alap.moments[3].ops, vec![IROp::X(2), IROp::CNOT(0, 1)]);
+
+    let mut rng = StdRng::seed_from_u64(25);
+    for _ in 0..20 {
+        let prog = random_circuit(&mut rng, 4, 40);
+        for strategy in [Strategy::Asap, Strategy::Alap] {
+            let s = schedule(&prog, strategy, &Durations::default());
+            assert_eq!(s.makespan as usize, depth(&prog));
+            for m in &s.moments {
+                let mut qs: Vec<usize> = m.ops.iter().flat_map(|op| op.qubits()).collect();
+                qs.sort_unstable();
+                assert!(qs.windows(2).all(|w| w[0] != w[1]));
+            }
+            let mut reordered = prog.clone();
+            reordered.ops = s.ops();
+            assert_eq!(wires(&reordered), wires(&prog));
+
+            let mut unitary = prog.clone();
+            unitary.ops.retain(|op| op.is_unitary() || matches!(op, IROp::Barrier(_)));
+            let mut reordered = unitary.clone();
+            reordered.ops = schedule(&unitary, strategy, &Durations::default()).ops();
+            assert!(check_equivalence(&unitary, &reordered).is_equivalent());
+        }
+    }
+}
+
+#[test]
+fn durations_set_start_times_and_idle_gaps() {
+    let durations = Durations::uniform(1).with("cx", 4).with("measure", 10).with("rz", 0);
+    let mut prog = IRProgram::new(2, 2);
+    prog.ops = vec![IROp::H(0), IROp::RZ(1, 0.5), IROp::CNOT(0, 1), IROp::H(1), IROp::Measure(0, 0), IROp::Measure(1, 1)];
+
+    // q1 waits for the Hadamard on q0 after its virtual RZ
+    let asap = schedule(&prog, Strategy::Asap, &durations);
+    assert_eq!(asap.makespan, 16);
+    assert_eq!(asap.idle_periods(), vec![Idle { qubit: 1, start: 0, duration: 1 }]);
+
+    // Late measurements leave q0 waiting on q1 instead
+    let alap = schedule(&prog, Strategy::Alap, &durations);
+    assert_eq!(alap.makespan, 16);
+    let starts: Vec<(u64, u64)> = alap.moments.iter().map(|m| (m.start, m.duration)).collect();
+    assert_eq!(starts, vec![(0, 1), (1, 0), (1, 4), (5, 1), (6, 10)]);
+    assert_eq!(alap.moments[1].ops, vec![IROp::RZ(1, 0.5)]);
+    assert_eq!(alap.idle_periods(), vec![Idle { qubit: 0, start: 5, duration: 1 }]);
+}
+
+#[test]
+fn markers_barriers_and_blocks_keep_their_place() {
+    let durations = Durations::uniform(1).with("cx", 4);
+    let mut prog = IRProgram::new(3, 1);
+    prog.ops = vec![
+        IROp::H(0),
+        IROp::Measure(0, 0),
+        IROp::IfElse { clbits: vec![0], value: 1, then_ops: vec![IROp::CNOT(1, 2)], else_ops: vec![IROp::H(1)] },
+        IROp::H(0),
+        IROp::Promote,
+        IROp::T(2),
+        IROp::Barrier(vec![0, 2]),
+        IROp::H(0),
+        IROp::Demote,
+        IROp::X(1),
+    ];
+    let s = schedule(&prog, Strategy::Asap, &durations);
+    // The block takes as long as its CNOT
+    let block = s.moments.iter().find(|m| matches!(m.ops[0], IROp::IfElse { .. })).unwrap();
+    assert_eq!((block.start, block.duration), (2, 4));
+    // X(1) could run right after the block but has to wait for the markers
+    let ops = s.ops();
+    let position = |op: &IROp| ops.iter().position(|o| o == op).unwrap();
+    assert!(position(&IROp::Promote) < position(&IROp::T(2)));
+    assert!(position(&IROp::Barrier(vec![0, 2])) < ops.iter().rposition(|o| o == &IROp::H(0)).unwrap());
+    assert!(position(&IROp::Demote) < position(&IROp::X(1)));
+    assert_eq!(s.makespan, 9);
+
+    for strategy in [Strategy::Asap, Strategy::Alap] {
+        let rescheduled = PassManager::new()
+            .verified(EquivalenceChecker::default())
+            .with(Reschedule::new(strategy, durations.clone()))
+            .run(prog.clone());
+        assert_eq!(rescheduled.len(), prog.len());
+    }
+}
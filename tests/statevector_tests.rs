@@ -28,6 +28,7 @@ fn complex_sub_and_div() {
 }
 
 #[test]
+#[allow(clippy::bool_assert_comparison)]
 fn utils_bit_helpers() {
     assert_eq!(get_bit(0b101, 0), true);  // least significant bit
     assert_eq!(get_bit(0b101, 1), false);
@@ -0,0 +1,158 @@
+use quantum_sim::compiler::ir::{IROp, IRProgram};
+use quantum_sim::compiler::optimizer::pass_manager::PassManager;
+use quantum_sim::compiler::optimizer::rz_synthesis::RotationSynthesis;
+use quantum_sim::compiler::optimizer::sk_synthesis::SKSynthesis;
+use quantum_sim::compiler::synthesis::diophantine::solve_norm_equation;
+use quantum_sim::compiler::synthesis::gridsynth::rz_distance;
+use quantum_sim::compiler::synthesis::ring::ZOmega;
+use quantum_sim::compiler::synthesis::solovay_kitaev::{distance, to_quaternion};
+use quantum_sim::compiler::synthesis::{approximate_rz, exact_synthesis, ExactUnitary, SolovayKitaev, Syllable};
+use quantum_sim::math::complex::Complex;
+use quantum_sim::statevector::gates::Gates;
+use quantum_sim::statevector::simulator::StatevectorSimulator;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::f64::consts::PI;
+
+fn random_word(rng: &mut StdRng, len: usize) -> Vec<IROp> {
+    (0..len).map(|_| match rng.gen_range(0..6) {
+        0 => IROp::H(0),
+        1 => IROp::S(0),
+        2 => IROp::Tdg(0),
+        3 => IROp::X(0),
+        _ => IROp::T(0),
+    }).collect()
+}
+
+#[test]
+fn exact_synthesis_gives_matsumoto_amano_normal_form() {
+    let mut rng = StdRng::seed_from_u64(5);
+    for _ in 0..300 {
+        let len = rng.gen_range(0..60);
+        let word = random_word(&mut rng, len);
+        let u = ExactUnitary::from_ops(&word).unwrap();
+        let nf = exact_synthesis(&u);
+        assert!(ExactUnitary::from_ops(&nf.ops(0)).unwrap().equal_up_to_phase(&u), "{:?}", word);
+        assert!(nf.syllables.iter().skip(1).all(|s| *s != Syllable::T));
+        let t_gates = word.iter().filter(|op| matches!(op, IROp::T(_) | IROp::Tdg(_))).count();
+        assert!(nf.t_count() <= t_gates);
+        // The normal form is unique
+        let mut padded = word.clone();
+        padded.splice(len / 2..len / 2, [IROp::T(0), IROp::H(0), IROp::H(0), IROp::Tdg(0)]);
+        assert_eq!(exact_synthesis(&ExactUnitary::from_ops(&padded).unwrap()), nf);
+    }
+}
+
+#[test]
+fn norm_equations_are_solved() {
+    let mut rng = StdRng::seed_from_u64(9);
+    let mut solved = 0;
+    for _ in 0..200 {
+        let t = ZOmega::new([0; 4].map(|_: i128| rng.gen_range(-300..300)));
+        let xi = t.norm_sq();
+        if let Some(s) = solve_norm_equation(xi) {
+            assert_eq!(s.norm_sq(), xi);
+            solved += 1;
+        }
+    }
+    // Every xi = t†t has a solution, but factoring may give up on a few
+    assert!(solved >= 190, "{}", solved);
+}
+
+#[test]
+fn rz_rotations_are_approximated_to_epsilon() {
+    for theta in [0.1, 1.0, -2.3, PI / 3.0, 3.0] {
+        for (epsilon, max_t) in [(1e-1, 14), (1e-2, 28), (1e-3, 38), (1e-4, 50)] {
+            let nf = approximate_rz(theta, epsilon).expect("synthesizes");
+            let u = ExactUnitary::from_ops(&nf.ops(0)).unwrap();
+            assert!(rz_distance(&u, theta) <= epsilon);
+            assert!(nf.t_count() <= max_t, "theta {} eps {}: {} T gates", theta, epsilon, nf.t_count());
+        }
+    }
+    // pi/4 multiples come out exact
+    let nf = approximate_rz(PI / 4.0, 1e-3).unwrap();
+    assert_eq!(nf.t_count(), 1);
+}
+
+#[test]
+fn rotation_synthesis_pass_leaves_clifford_t_only() {
+    let mut prog = IRProgram::new(2, 1);
+    prog.ops = vec![
+        IROp::H(0), IROp::RZ(0, 0.3), IROp::CNOT(0, 1), IROp::RZ(1, PI / 2.0),
+        IROp::Measure(0, 0), IROp::Conditional { clbits: vec![0], value: 1, op: Box::new(IROp::RZ(1, 0.3)) },
+    ];
+    let out = PassManager::new().with(RotationSynthesis::new(1e-3)).run(prog.clone());
+    let m = out.ops.iter().position(|op| matches!(op, IROp::Measure(..))).unwrap();
+    assert!(out.ops.iter().all(|op| !matches!(op, IROp::RZ(..))));
+    // Both RZ(0.3) come out the same, the second one conditioned
+    let first: Vec<IROp> = out.ops[1..m - 2].to_vec();
+    assert_eq!(out.ops[m - 1], IROp::S(1));
+    let conditioned: Vec<IROp> = out.ops[m + 1..].iter().map(|op| match op {
+        IROp::Conditional { op, .. } => op.map_qubits(|_| 0),
+        _ => panic!("unconditioned {}", op),
+    }).collect();
+    assert_eq!(conditioned, first);
+    assert!(rz_distance(&ExactUnitary::from_ops(&first).unwrap(), 0.3) <= 1e-3);
+}
+
+// The product of a Clifford+T word in floats; ExactUnitary overflows on the
+// long words Solovay-Kitaev produces
+fn word_matrix(ops: &[IROp]) -> [[Complex; 2]; 2] {
+    let mut m = [[Complex::one(), Complex::zero()], [Complex::zero(), Complex::one()]];
+    for op in ops {
+        let g = match op {
+            IROp::H(_) => Gates::h(),
+            IROp::S(_) => Gates::s(),
+            IROp::Sdg(_) => Gates::sdg(),
+            IROp::T(_) => Gates::t(),
+            IROp::Tdg(_) => Gates::tdg(),
+            IROp::X(_) => Gates::x(),
+            IROp::Y(_) => Gates::y(),
+            IROp::Z(_) => Gates::z(),
+            _ => panic!("not Clifford+T: {}", op),
+        };
+        let e = |i: usize, j: usize| g[i][0].mul(&m[0][j]).add(&g[i][1].mul(&m[1][j]));
+        m = [[e(0, 0), e(0, 1)], [e(1, 0), e(1, 1)]];
+    }
+    m
+}
+
+#[test]
+fn solovay_kitaev_refines_arbitrary_unitaries() {
+    let sk = SolovayKitaev::new(6);
+    assert_eq!(sk.net_size(), 24 * (3 * 64 - 2));
+    let mut rng = StdRng::seed_from_u64(19);
+    for _ in 0..5 {
+        let u = Gates::u3(rng.gen_range(0.0..PI), rng.gen_range(-PI..PI), rng.gen_range(-PI..PI));
+        let error = |ops: &[IROp]| distance(&to_quaternion(&word_matrix(ops)), &to_quaternion(&u));
+        let errors: Vec<f64> = (0..4).map(|depth| error(&sk.approximate(&u, depth))).collect();
+        assert!(errors.windows(2).all(|w| w[1] <= w[0] + 1e-12), "{:?}", errors);
+        assert!(errors[3] < errors[0] / 5.0, "{:?}", errors);
+        assert!(error(&sk.compile(&u, 1e-2)) <= 1e-2);
+    }
+    // Net elements come out exactly
+    let t_word = [IROp::H(0), IROp::T(0), IROp::H(0), IROp::S(0), IROp::T(0)];
+    assert!(distance(&to_quaternion(&word_matrix(&sk.compile(&word_matrix(&t_word), 1e-9))), &to_quaternion(&word_matrix(&t_word))) < 1e-9);
+}
+
+#[test]
+fn sk_synthesis_pass_runs_arbitrary_rotations_as_clifford_t() {
+    let mut prog = IRProgram::new(2, 0);
+    prog.ops = vec![
+        IROp::U3(0, 1.1, 0.4, -0.9), IROp::RX(1, 0.7), IROp::CNOT(0, 1), IROp::RY(0, -2.3), IROp::RZ(1, PI / 4.0),
+    ];
+    let out = PassManager::new().with(SKSynthesis::new(6, 1e-2)).run(prog.clone());
+    assert!(out.ops.iter().all(|op| matches!(op,
+        IROp::H(_) | IROp::S(_) | IROp::Sdg(_) | IROp::T(_) | IROp::Tdg(_) | IROp::X(_) | IROp::Y(_) | IROp::Z(_) | IROp::CNOT(..))));
+    assert_eq!(out.ops.last(), Some(&IROp::T(1)));
+    let run = |prog: &IRProgram| {
+        let mut sim = StatevectorSimulator::new(2);
+        for op in &prog.ops {
+            sim.apply_ir(op);
+        }
+        sim.state
+    };
+    let (a, b) = (run(&prog), run(&out));
+    let overlap = a.iter().zip(&b).fold(Complex::zero(), |acc, (x, y)| acc.add(&x.conj().mul(y)));
+    assert!(overlap.magnitude2().sqrt() > 0.99);
+}
@@ -1,10 +1,23 @@
+use quantum_sim::compiler::ir::IROp;
+use quantum_sim::math::complex::Complex;
+use quantum_sim::runtime::stabilizer_sum_backend::StabilizerSumSimulator;
+use quantum_sim::runtime::statevector_backend::StatevectorSimulator;
+use quantum_sim::runtime::tableau_backend::TableauSimulator;
 use quantum_sim::tableau::simulator::Tableau;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 
 #[test]
 fn tableau_initializes_to_zero_state() {
     let t = Tableau::new(2);
-    // Verify first n rows are Z stabilizers
-    // (optional: check data explicitly)
+    // Destabilizers X_i in rows 0..n, stabilizers Z_i in rows n..2n
+    for q in 0..2 {
+        for k in 0..2 {
+            assert_eq!(t.pauli(q, k), (q == k, false));
+            assert_eq!(t.pauli(2 + q, k), (false, q == k));
+        }
+        assert!(!t.phase(q) && !t.phase(2 + q));
+    }
     t.dump();
 }
 
@@ -27,8 +40,191 @@ fn apply_cnot_preserves_stabilizer_relations() {
 }
 
 #[test]
+#[allow(clippy::bool_comparison)]
 fn measurement_returns_valid_bit() {
     let mut t = Tableau::new(1);
     let outcome = t.measure_z(0);
     assert!(outcome == false || outcome == true);
 }
+
+// |<a|b>| for normalized states
+fn overlap(a: &[Complex], b: &[Complex]) -> f64 {
+    let (re, im) = a.iter().zip(b).fold((0.0, 0.0), |(re, im), (x, y)| {
+        (re + x.re * y.re + x.im * y.im, im + x.re * y.im - x.im * y.re)
+    });
+    (re * re + im * im).sqrt()
+}
+
+#[test]
+fn stabilizer_states_round_trip_through_the_tableau() {
+    let mut rng = StdRng::seed_from_u64(7);
+    for _ in 0..50 {
+        let n = rng.gen_range(1..=4);
+        let mut sim = StatevectorSimulator::new(n);
+        for _ in 0..12 {
+            let (a, b) = (rng.gen_range(0..n), rng.gen_range(0..n));
+            let op = match rng.gen_range(0..5) {
+                0 => IROp::H(a),
+                1 => IROp::S(a),
+                2 => IROp::X(a),
+                3 if a != b => IROp::CNOT(a, b),
+                4 if a != b => IROp::CZ(a, b),
+                _ => IROp::Sdg(a),
+            };
+            sim.apply_ir(&op);
+        }
+        let tableau = TableauSimulator::from_statevector(&sim.state).expect("Clifford circuits give stabilizer states");
+        assert!((overlap(&sim.state, &tableau.to_statevector()) - 1.0).abs() < 1e-9);
+    }
+}
+
+#[test]
+fn non_stabilizer_states_are_rejected() {
+    let mut sim = StatevectorSimulator::new(2);
+    sim.apply_ir(&IROp::H(0));
+    sim.apply_ir(&IROp::T(0));
+    assert!(TableauSimulator::from_statevector(&sim.state).is_none());
+
+    // Uneven amplitudes on an affine support
+    let mut sim = StatevectorSimulator::new(1);
+    sim.apply_ir(&IROp::RY(0, 0.4));
+    assert!(TableauSimulator::from_statevector(&sim.state).is_none());
+}
+
+#[test]
+fn deterministic_outcomes_carry_the_stabilizer_sign() {
+    // H S S H = X, so the first qubit is stabilized by -Z
+    let mut t = TableauSimulator::new(3);
+    for op in [IROp::H(0), IROp::S(0), IROp::S(0), IROp::H(0), IROp::H(1), IROp::CNOT(1, 2), IROp::Z(1)] {
+        t.apply_ir(&op);
+    }
+    assert!(t.measure_z(0));
+    // Bell pair with a phase: the second outcome follows the first
+    let first = t.measure_z(1);
+    assert_eq!(t.measure_z(2), first);
+    assert_eq!(t.measure_z(1), first);
+
+    // Random outcomes are uniform and repeatable
+    let ones = (0..200).filter(|_| {
+        let mut t = TableauSimulator::new(1);
+        t.apply_ir(&IROp::H(0));
+        let outcome = t.measure_z(0);
+        assert_eq!(t.measure_z(0), outcome);
+        outcome
+    }).count();
+    assert!((50..150).contains(&ones), "{} ones out of 200", ones);
+}
+
+#[test]
+fn measurements_match_the_statevector() {
+    let mut rng = StdRng::seed_from_u64(11);
+    for _ in 0..200 {
+        let n = rng.gen_range(1..=4);
+        let mut t = TableauSimulator::new(n);
+        let mut sim = StatevectorSimulator::new(n);
+        for _ in 0..30 {
+            let (a, b) = (rng.gen_range(0..n), rng.gen_range(0..n));
+            let op = match rng.gen_range(0..7) {
+                0 => IROp::H(a),
+                1 => IROp::S(a),
+                2 => IROp::Y(a),
+                3 if a != b => IROp::CNOT(a, b),
+                4 if a != b => IROp::CZ(a, b),
+                5 => IROp::Measure(a, 0),
+                _ => IROp::Sdg(a),
+            };
+            if let IROp::Measure(q, _) = op {
+                // Collapse the statevector onto whatever the tableau saw
+                let outcome = t.measure_z(q);
+                let p = sim.probability_one(q);
+                let p = if outcome { p } else { 1.0 - p };
+                assert!(p > 1e-9, "tableau gave an impossible outcome");
+                assert!((p - 1.0).abs() < 1e-9 || (p - 0.5).abs() < 1e-9, "stabilizer outcome with probability {}", p);
+                for (b, amp) in sim.state.iter_mut().enumerate() {
+                    *amp = if (b >> q & 1 == 1) == outcome {
+                        Complex::new(amp.re / p.sqrt(), amp.im / p.sqrt())
+                    } else {
+                        Complex::zero()
+                    };
+                }
+            } else {
+                t.apply_ir(&op);
+                sim.apply_ir(&op);
+            }
+        }
+        assert!((overlap(&sim.state, &t.to_statevector()) - 1.0).abs() < 1e-9);
+    }
+}
+
+#[test]
+fn stabilizer_sums_match_the_statevector() {
+    let mut rng = StdRng::seed_from_u64(13);
+    for _ in 0..200 {
+        let n = rng.gen_range(1..=4);
+        let mut sum = StabilizerSumSimulator::new(n);
+        let mut sim = StatevectorSimulator::new(n);
+        for _ in 0..30 {
+            let (a, b) = (rng.gen_range(0..n), rng.gen_range(0..n));
+            let op = match rng.gen_range(0..9) {
+                0 => IROp::H(a),
+                1 => IROp::S(a),
+                2 => IROp::T(a),
+                3 if a != b => IROp::CNOT(a, b),
+                4 if a != b => IROp::CZ(a, b),
+                5 => IROp::Measure(a, 0),
+                6 => IROp::Tdg(a),
+                7 => IROp::RZ(a, rng.gen_range(-3.0..3.0)),
+                _ => IROp::SX(a),
+            };
+            if let IROp::Measure(q, _) = op {
+                let outcome = sum.measure_z(q);
+                let p = sim.probability_one(q);
+                let p = if outcome { p } else { 1.0 - p };
+                assert!(p > 1e-9, "stabilizer sum gave an impossible outcome");
+                for (b, amp) in sim.state.iter_mut().enumerate() {
+                    *amp = if (b >> q & 1 == 1) == outcome {
+                        Complex::new(amp.re / p.sqrt(), amp.im / p.sqrt())
+                    } else {
+                        Complex::zero()
+                    };
+                }
+            } else {
+                sum.apply_ir(&op);
+                sim.apply_ir(&op);
+            }
+            assert!(sum.num_terms() <= 1 << n);
+        }
+        assert!((overlap(&sim.state, &sum.to_statevector()) - 1.0).abs() < 1e-9);
+        // Measuring everything leaves a single stabilizer state
+        for q in 0..n {
+            sum.measure_z(q);
+        }
+        assert_eq!(sum.num_terms(), 1);
+        let tableau = sum.to_tableau().unwrap();
+        assert!((overlap(&sum.to_statevector(), &tableau.to_statevector()) - 1.0).abs() < 1e-9);
+    }
+}
+
+#[test]
+fn stabilizer_sum_outcomes_follow_the_amplitudes() {
+    // H T H|0> gives 1 with probability sin^2(pi/8) ~ 0.146
+    let ones = (0..2000).filter(|_| {
+        let mut sum = StabilizerSumSimulator::new(1);
+        for op in [IROp::H(0), IROp::T(0), IROp::H(0)] {
+            sum.apply_ir(&op);
+        }
+        assert_eq!(sum.num_terms(), 2);
+        sum.measure_z(0)
+    }).count();
+    assert!((220..370).contains(&ones), "{} ones out of 2000", ones);
+
+    // A magic state costs two terms, and measuring its qubit merges them
+    let mut sum = StabilizerSumSimulator::new(2);
+    sum.apply_ir(&IROp::H(0));
+    sum.apply_ir(&IROp::MagicState(1));
+    sum.apply_ir(&IROp::CNOT(0, 1));
+    assert_eq!(sum.num_terms(), 2);
+    sum.measure_z(1);
+    sum.measure_z(0);
+    assert_eq!(sum.num_terms(), 1);
+}
@@ -0,0 +1,175 @@
+use quantum_sim::compiler::codegen::compile;
+use quantum_sim::compiler::ir::IROp;
+use quantum_sim::compiler::optimizer::analysis::t_count;
+use quantum_sim::compiler::optimizer::pass_manager::{OptLevel, PassManager};
+use quantum_sim::compiler::optimizer::zx_simplify::{resynthesize, ZXSimplify};
+use quantum_sim::compiler::zx::rules::{fuse_gadgets, local_complement, pivot_gadgets, pivot_paulis, remove_ids};
+use quantum_sim::compiler::zx::{extract_circuit, full_reduce, EdgeKind, VertexKind, ZXGraph};
+use quantum_sim::math::complex::Complex;
+use quantum_sim::runtime::statevector_backend::StatevectorSimulator;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+// Statevector check on every basis state, up to one global phase
+fn assert_equivalent(n: usize, a: &[IROp], b: &[IROp]) {
+    let dim = 1 << n;
+    let mut phase: Option<Complex> = None;
+    for basis in 0..dim {
+        let run = |ops: &[IROp]| {
+            let mut state = vec![Complex::zero(); dim];
+            state[basis] = Complex::one();
+            let mut sim = StatevectorSimulator::from(state);
+            for op in ops {
+                sim.apply_ir(op);
+            }
+            sim.state
+        };
+        let (x, y) = (run(a), run(b));
+        for (u, v) in x.iter().zip(&y) {
+            if u.magnitude2() > 1e-12 && phase.is_none() {
+                let d = u.magnitude2();
+                phase = Some(Complex::new((v.re * u.re + v.im * u.im) / d, (v.im * u.re - v.re * u.im) / d));
+            }
+            let w = phase.unwrap_or(Complex::one()).mul(u);
+            assert!((w.re - v.re).abs() < 1e-9 && (w.im - v.im).abs() < 1e-9, "circuits differ on basis state {}:\n{:?}\n{:?}", basis, a, b);
+        }
+    }
+}
+
+fn random_circuit(rng: &mut StdRng, n: usize, len: usize) -> Vec<IROp> {
+    (0..len).map(|_| {
+        let (a, b, c) = (rng.gen_range(0..n), rng.gen_range(0..n), rng.gen_range(0..n));
+        match rng.gen_range(0..10) {
+            1 => IROp::S(a),
+            2 => IROp::T(a),
+            3 => IROp::Tdg(a),
+            4 if a != b => IROp::CNOT(a, b),
+            5 if a != b => IROp::CZ(a, b),
+            6 => IROp::X(a),
+            7 => IROp::RZ(a, rng.gen_range(-3.0..3.0)),
+            8 if a != b && b != c && a != c => IROp::Toffoli(a, b, c),
+            _ => IROp::H(a),
+        }
+    }).collect()
+}
+
+fn graph(n: usize, ops: &[IROp]) -> ZXGraph {
+    ZXGraph::from_circuit(n, ops).expect("supported circuit")
+}
+
+#[test]
+fn graph_like_form_uses_z_spiders_and_hadamard_edges() {
+    let ops = [IROp::H(0), IROp::CNOT(0, 1), IROp::RX(1, 0.5), IROp::T(0), IROp::SWAP(0, 1)];
+    let mut g = graph(2, &ops);
+    assert!(g.vertices().iter().any(|&v| g.kind(v) == VertexKind::X));
+    g.to_graph_like();
+    for v in g.vertices() {
+        assert_ne!(g.kind(v), VertexKind::X);
+        for w in g.neighbors(v) {
+            if !g.is_boundary(v) && !g.is_boundary(w) {
+                assert_eq!(g.edge(v, w), Some(EdgeKind::Hadamard));
+            }
+        }
+    }
+    assert_eq!((g.inputs().len(), g.outputs().len()), (2, 2));
+    assert_eq!(g.t_count(), 1);
+    assert!(ZXGraph::from_circuit(2, &[IROp::Measure(0, 0)]).is_none());
+    assert!(ZXGraph::from_circuit(2, &[IROp::CU3(0, 1, 0.1, 0.2, 0.3)]).is_none());
+}
+
+#[test]
+fn parallel_edges_and_self_loops_stay_simple() {
+    let mut g = ZXGraph::new();
+    let a = g.add_vertex(VertexKind::Z, 0.25);
+    let b = g.add_vertex(VertexKind::Z, 0.0);
+    g.connect(a, b, EdgeKind::Hadamard);
+    // Hopf rule: two Hadamard edges between Z spiders cancel
+    g.connect(a, b, EdgeKind::Hadamard);
+    assert_eq!(g.edge(a, b), None);
+    // A plain and a Hadamard edge leave a plain edge and a pi phase
+    g.connect(a, b, EdgeKind::Hadamard);
+    g.connect(a, b, EdgeKind::Simple);
+    assert_eq!(g.edge(a, b), Some(EdgeKind::Simple));
+    assert_eq!(g.phase(a), 1.25);
+    g.connect(b, b, EdgeKind::Hadamard);
+    assert_eq!(g.phase(b), 1.0);
+    g.fuse(a, b);
+    assert_eq!((g.num_vertices(), g.phase(a)), (1, 0.25));
+}
+
+#[test]
+fn each_rule_preserves_the_circuit() {
+    let mut rng = StdRng::seed_from_u64(3);
+    let rules: [fn(&mut ZXGraph) -> usize; 5] = [remove_ids, local_complement, pivot_paulis, pivot_gadgets, fuse_gadgets];
+    for _ in 0..60 {
+        let n = rng.gen_range(1..=4);
+        let len = rng.gen_range(0..40);
+        let ops = random_circuit(&mut rng, n, len);
+        for rule in rules {
+            let mut g = graph(n, &ops);
+            g.to_graph_like();
+            rule(&mut g);
+            assert_equivalent(n, &ops, &extract_circuit(g).expect("extracts"));
+        }
+    }
+}
+
+#[test]
+fn random_circuits_survive_full_reduce() {
+    let mut rng = StdRng::seed_from_u64(1);
+    for _ in 0..200 {
+        let n = rng.gen_range(1..=5);
+        let len = rng.gen_range(0..60);
+        let ops = random_circuit(&mut rng, n, len);
+        assert_equivalent(n, &ops, &extract_circuit(graph(n, &ops)).expect("extracts"));
+
+        let mut g = graph(n, &ops);
+        g.to_graph_like();
+        let before = g.t_count();
+        full_reduce(&mut g);
+        assert!(g.t_count() <= before);
+        let out = extract_circuit(g).expect("extracts");
+        assert!(t_count(&out) <= before, "{} -> {}", before, t_count(&out));
+        assert_equivalent(n, &ops, &out);
+    }
+}
+
+#[test]
+fn full_reduce_removes_clifford_spiders() {
+    let ops = [IROp::H(0), IROp::S(0), IROp::CNOT(0, 1), IROp::H(1), IROp::CZ(1, 2), IROp::Sdg(2), IROp::H(2), IROp::CNOT(2, 0)];
+    let mut g = graph(3, &ops);
+    full_reduce(&mut g);
+    // Only the spiders next to the boundaries are left
+    assert!(g.vertices().iter().all(|&v| g.is_boundary(v) || !g.is_interior(v)));
+    assert_equivalent(3, &ops, &extract_circuit(g).expect("extracts"));
+}
+
+#[test]
+fn phase_gadgets_on_the_same_parity_fuse() {
+    // Each CCZ is seven T gates, mostly on parities like a ^ b ^ c; the
+    // two copies cancel around the T on `a`, which commutes with them
+    let ops = [IROp::CCZ(0, 1, 2), IROp::T(0), IROp::CCZ(0, 1, 2)];
+    assert_eq!(graph(3, &ops).t_count(), 15);
+    let out = resynthesize(ops.to_vec());
+    assert_eq!(t_count(&out), 1);
+    assert_equivalent(3, &ops, &out);
+}
+
+#[test]
+fn zx_pass_keeps_unsupported_ops_in_place() {
+    let prog = compile("
+        qubit a, b;
+        t a; cnot a, b; t b; cnot a, b; cnot a, b; tdg b; cnot a, b;
+        measure a;
+        h b; t b; h b;
+    ").unwrap();
+    let out = PassManager::new().with(ZXSimplify).run(prog.clone());
+    let m = out.ops.iter().position(|op| matches!(op, IROp::Measure(..))).unwrap();
+    assert_equivalent(2, &prog.ops[..7], &out.ops[..m]);
+    assert!(t_count(&out.ops[..m]) <= 1);
+    // A lone T after the measurement cannot get cheaper, so it is untouched
+    assert_eq!(out.ops[m + 1..], prog.ops[8..]);
+
+    assert!(PassManager::for_level(OptLevel::O3).pass_names().contains(&"zx_simplify"));
+    assert!(!PassManager::for_level(OptLevel::O2).pass_names().contains(&"zx_simplify"));
+}